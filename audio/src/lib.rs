@@ -33,10 +33,15 @@ pub fn init<P: AsRef<Path>>(data_dirs: &[P]) -> AudioContext {
     }
 }
 
+/// Call the given function with AudioPlayer.
+/// Does nothing if not initialized, e.g. in tools running without sound devices.
 pub fn with_audio_player<F: FnOnce(&AudioPlayer)>(f: F) {
     AUDIO_PLAYER.with(|a| {
-        assert!(a.borrow().is_some());
-        f(a.borrow().as_ref().unwrap());
+        if let Some(a) = a.borrow().as_ref() {
+            f(a);
+        } else {
+            debug!("audio player is not initialized");
+        }
     });
 }
 
@@ -0,0 +1,194 @@
+//! Addons are directories with a manifest file.
+//! They can contain paks, rules patches, text files and sounds like the application directory.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE_NAME: &str = "addon.toml";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct AddonManifest {
+    pub name: String,
+    pub version: String,
+    /// Names of addons that must be loaded before this addon
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Addons with higher priority are loaded later, and override objects of other addons
+    #[serde(default)]
+    pub priority: i32,
+}
+
+#[derive(Clone, Debug)]
+pub struct Addon {
+    pub manifest: AddonManifest,
+    pub dir: PathBuf,
+    pub enabled: bool,
+}
+
+/// Find addons in the subdirectories of the given directory. Sorted by name.
+pub fn find_addons(addon_dir: &Path) -> Vec<Addon> {
+    let mut addons = Vec::new();
+
+    let entries = match fs::read_dir(addon_dir) {
+        Ok(entries) => entries,
+        Err(_) => {
+            return addons;
+        }
+    };
+
+    for entry in entries {
+        let dir = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                warn!("{}", e);
+                continue;
+            }
+        };
+        let manifest_path = dir.join(MANIFEST_FILE_NAME);
+        if !manifest_path.exists() {
+            continue;
+        }
+        match read_manifest(&manifest_path) {
+            Ok(manifest) => addons.push(Addon {
+                manifest,
+                dir,
+                enabled: true,
+            }),
+            Err(e) => warn!(
+                "cannot load addon manifest \"{}\": {}",
+                manifest_path.to_string_lossy(),
+                e
+            ),
+        }
+    }
+
+    addons.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    addons
+}
+
+fn read_manifest(path: &Path) -> Result<AddonManifest, Box<dyn std::error::Error>> {
+    let s = fs::read_to_string(path)?;
+    Ok(toml::de::from_str(&s)?)
+}
+
+/// Decide the load order of enabled addons.
+/// Dependencies are loaded first, and ties are broken by priority and name.
+/// Addons with missing or cyclic dependencies are not loaded.
+pub fn load_order(addons: &[Addon]) -> Vec<&Addon> {
+    let mut candidates: Vec<&Addon> = addons.iter().filter(|a| a.enabled).collect();
+
+    // Remove addons whose dependencies are not available
+    loop {
+        let n = candidates.len();
+        let names: Vec<String> = candidates.iter().map(|a| a.manifest.name.clone()).collect();
+        candidates.retain(|a| {
+            if let Some(d) = a.manifest.dependencies.iter().find(|d| !names.contains(d)) {
+                warn!(
+                    "addon \"{}\" is not loaded: missing dependency \"{}\"",
+                    a.manifest.name, d
+                );
+                false
+            } else {
+                true
+            }
+        });
+        if candidates.len() == n {
+            break;
+        }
+    }
+
+    let mut ordered: Vec<&Addon> = Vec::new();
+    while !candidates.is_empty() {
+        let next = candidates
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| {
+                a.manifest
+                    .dependencies
+                    .iter()
+                    .all(|d| ordered.iter().any(|o| &o.manifest.name == d))
+            })
+            .min_by_key(|(_, a)| (a.manifest.priority, &a.manifest.name))
+            .map(|(i, _)| i);
+
+        if let Some(i) = next {
+            ordered.push(candidates.remove(i));
+        } else {
+            for a in &candidates {
+                warn!(
+                    "addon \"{}\" is not loaded: cyclic dependency",
+                    a.manifest.name
+                );
+            }
+            break;
+        }
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addon(name: &str, dependencies: &[&str], priority: i32) -> Addon {
+        Addon {
+            manifest: AddonManifest {
+                name: name.to_owned(),
+                version: "0.1.0".to_owned(),
+                dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+                priority,
+            },
+            dir: PathBuf::from(name),
+            enabled: true,
+        }
+    }
+
+    fn names(addons: Vec<&Addon>) -> Vec<&str> {
+        addons.iter().map(|a| a.manifest.name.as_str()).collect()
+    }
+
+    #[test]
+    fn dependencies_first() {
+        let addons = vec![
+            addon("a", &["c"], 0),
+            addon("b", &[], 0),
+            addon("c", &["b"], 0),
+        ];
+        assert_eq!(names(load_order(&addons)), vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn priority_and_name() {
+        let addons = vec![
+            addon("a", &[], 1),
+            addon("b", &[], 0),
+            addon("c", &[], 0),
+            addon("d", &["a"], -1),
+        ];
+        assert_eq!(names(load_order(&addons)), vec!["b", "c", "a", "d"]);
+    }
+
+    #[test]
+    fn missing_dependency() {
+        let mut addons = vec![
+            addon("a", &["x"], 0),
+            addon("b", &["a"], 0),
+            addon("c", &[], 0),
+            addon("d", &["e"], 0),
+            addon("e", &[], 0),
+        ];
+        addons[4].enabled = false;
+        assert_eq!(names(load_order(&addons)), vec!["c"]);
+    }
+
+    #[test]
+    fn cyclic_dependency() {
+        let addons = vec![
+            addon("a", &["b"], 0),
+            addon("b", &["a"], 0),
+            addon("c", &["a"], 0),
+            addon("d", &[], 0),
+        ];
+        assert_eq!(names(load_order(&addons)), vec!["d"]);
+    }
+}
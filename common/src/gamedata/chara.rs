@@ -4,6 +4,7 @@ use super::site::SiteId;
 use super::skill::SkillList;
 use super::unknown_id_err;
 use crate::objholder::CharaTemplateIdx;
+use crate::script::TriggerScript;
 use std::collections::HashMap;
 
 /// Character's races
@@ -72,6 +73,21 @@ impl Relationship {
     }
 }
 
+impl std::str::FromStr for Relationship {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::Relationship::*;
+        match s {
+            "ally" => Ok(ALLY),
+            "friendly" => Ok(FRIENDLY),
+            "neutral" => Ok(NEUTRAL),
+            "hostile" => Ok(HOSTILE),
+            _ => Err(()),
+        }
+    }
+}
+
 /// All data for one character
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Chara {
@@ -80,6 +96,10 @@ pub struct Chara {
     pub template: CharaTemplateIdx,
     pub class: CharaClass,
     pub level: u32,
+    /// Character exp. If it reaches the required value, the character levels up.
+    pub exp: u32,
+    /// Attribute increase accumulated by level-ups
+    pub attr_growth: CharaAttrRevision,
     pub item_list: ItemList,
     pub equip: EquipItemList,
     pub wait_time: u32,
@@ -92,6 +112,9 @@ pub struct Chara {
     pub rel: Relationship,
     /// When talked, execute this script
     pub trigger_talk: Option<String>,
+    /// When died, execute this script
+    #[serde(default)]
+    pub trigger_death: Option<TriggerScript>,
 }
 
 /// Character attributes
@@ -119,6 +142,54 @@ pub struct CharaAttributes {
     pub view_range: i32,
 }
 
+/// Kind of attributes which can be referenced by name
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CharaAttrKind {
+    MaxHp,
+    Str,
+    Vit,
+    Dex,
+    Int,
+    Wil,
+    Cha,
+    Spd,
+}
+
+impl std::str::FromStr for CharaAttrKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::CharaAttrKind::*;
+        match s {
+            "max_hp" => Ok(MaxHp),
+            "str" => Ok(Str),
+            "vit" => Ok(Vit),
+            "dex" => Ok(Dex),
+            "int" => Ok(Int),
+            "wil" => Ok(Wil),
+            "cha" => Ok(Cha),
+            "spd" => Ok(Spd),
+            _ => Err(()),
+        }
+    }
+}
+
+impl CharaAttributes {
+    pub fn get(&self, kind: CharaAttrKind) -> i32 {
+        match kind {
+            CharaAttrKind::MaxHp => self.max_hp,
+            CharaAttrKind::Str => self.str as i32,
+            CharaAttrKind::Vit => self.vit as i32,
+            CharaAttrKind::Dex => self.dex as i32,
+            CharaAttrKind::Int => self.int as i32,
+            CharaAttrKind::Wil => self.wil as i32,
+            CharaAttrKind::Cha => self.cha as i32,
+            CharaAttrKind::Spd => self.spd as i32,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct CharaBaseAttr {
     pub base_hp: i32,
@@ -131,7 +202,7 @@ pub struct CharaBaseAttr {
     pub spd: i16,
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Default, Debug, Serialize, Deserialize)]
 pub struct CharaAttrRevision {
     pub hp: i32,
     pub str: i16,
@@ -158,6 +229,23 @@ impl CharaBaseAttr {
     }
 }
 
+impl std::ops::Add for CharaAttrRevision {
+    type Output = CharaAttrRevision;
+
+    fn add(self, other: CharaAttrRevision) -> CharaAttrRevision {
+        CharaAttrRevision {
+            hp: self.hp + other.hp,
+            str: self.str + other.str,
+            vit: self.vit + other.vit,
+            dex: self.dex + other.dex,
+            int: self.int + other.int,
+            wil: self.wil + other.wil,
+            cha: self.cha + other.cha,
+            spd: self.spd + other.spd,
+        }
+    }
+}
+
 /// Represents chara status
 #[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Hash, Serialize, Deserialize)]
 pub enum CharaStatus {
@@ -181,6 +269,8 @@ impl Default for Chara {
             template: CharaTemplateIdx::default(),
             class: CharaClass::default(),
             level: 0,
+            exp: 0,
+            attr_growth: CharaAttrRevision::default(),
             item_list: ItemList::new(),
             equip: EquipItemList::new(&[]),
             wait_time: crate::basic::WAIT_TIME_NUMERATOR,
@@ -191,6 +281,7 @@ impl Default for Chara {
             skills: SkillList::default(),
             rel: Relationship::NEUTRAL,
             trigger_talk: None,
+            trigger_death: None,
         }
     }
 }
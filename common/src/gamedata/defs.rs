@@ -48,5 +48,7 @@ impl<T> IndexMut<Element> for ElementArray<T> {
 #[derive(Clone, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
 pub struct Reward {
     pub money: i64,
+    /// Character exp for the player
+    pub exp: u32,
     pub item: Vec<ItemIdx>,
 }
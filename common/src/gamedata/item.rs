@@ -1,4 +1,4 @@
-use super::defs::ElementArray;
+use super::defs::{Element, ElementArray};
 use crate::objholder::ItemIdx;
 use array2d::Vec2d;
 use bitflags::bitflags;
@@ -36,6 +36,8 @@ pub struct ItemObject {
     pub gen_level: u32,
     pub dice_n: u16,
     pub dice_x: u16,
+    /// Element of the attack by this item. Used for weapons and ammunition
+    pub element: Element,
     /// Defence
     pub def: ElementArray<u16>,
     /// Effectiveness of this item
@@ -78,7 +80,10 @@ pub enum ItemKind {
     Food,
     Weapon(WeaponKind),
     Armor(ArmorKind),
+    Ammo(AmmoKind),
     Material,
+    /// Keys unlock locked doors
+    Key,
     Special,
 }
 
@@ -91,6 +96,7 @@ pub enum ItemKindRough {
     Food,
     Weapon,
     Armor,
+    Ammo,
     Material,
     Special,
 }
@@ -165,6 +171,32 @@ pub enum WeaponKind {
     Gun,
 }
 
+impl std::str::FromStr for WeaponKind {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::WeaponKind::*;
+        match s {
+            "sword" => Ok(Sword),
+            "spear" => Ok(Spear),
+            "axe" => Ok(Axe),
+            "whip" => Ok(Whip),
+            "bow" => Ok(Bow),
+            "crossbow" => Ok(Crossbow),
+            "gun" => Ok(Gun),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmmoKind {
+    Arrow,
+    Bolt,
+    Bullet,
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ArmorKind {
@@ -321,6 +353,7 @@ impl From<u32> for ItemMoveNum {
 pub enum EquipSlotKind {
     MeleeWeapon,
     RangedWeapon,
+    Ammo,
     BodyArmor,
     Shield,
 }
@@ -330,6 +363,7 @@ impl ItemKind {
         match self {
             ItemKind::Weapon(weapon_kind) => Some(weapon_kind.equip_slot_kind()),
             ItemKind::Armor(armor_kind) => Some(armor_kind.equip_slot_kind()),
+            ItemKind::Ammo(_) => Some(EquipSlotKind::Ammo),
             _ => None,
         }
     }
@@ -343,6 +377,17 @@ impl WeaponKind {
             _ => EquipSlotKind::RangedWeapon,
         }
     }
+
+    /// Returns the kind of ammunition this weapon uses.
+    /// Returns None if this weapon doesn't need ammunition.
+    pub fn ammo_kind(self) -> Option<AmmoKind> {
+        match self {
+            WeaponKind::Bow => Some(AmmoKind::Arrow),
+            WeaponKind::Crossbow => Some(AmmoKind::Bolt),
+            WeaponKind::Gun => Some(AmmoKind::Bullet),
+            _ => None,
+        }
+    }
 }
 
 impl ArmorKind {
@@ -426,17 +471,41 @@ impl EquipItemList {
         }
     }
 
+    /// Get specified equipped item and its number
+    pub fn item_with_num(&self, esk: EquipSlotKind, n: usize) -> Option<(&Item, u32)> {
+        assert!(n < MAX_SLOT_NUM_PER_KIND);
+        if let Some(a) = self.list_idx(esk, n) {
+            let a = &self.item_list.items[a];
+            Some((&a.0, a.1))
+        } else {
+            None
+        }
+    }
+
     /// Equip an item to specified slot (the nth slot of given ItemKind), and returns removed item
     pub fn equip(&mut self, esk: EquipSlotKind, n: usize, item: Item) -> Option<Item> {
+        self.equip_with_num(esk, n, item, 1)
+            .map(|(removed_item, _)| removed_item)
+    }
+
+    /// Equip items to specified slot, and returns removed items and its number.
+    /// Stackable equipments like ammunition use this.
+    pub fn equip_with_num(
+        &mut self,
+        esk: EquipSlotKind,
+        n: usize,
+        item: Item,
+        num: u32,
+    ) -> Option<(Item, u32)> {
         assert!(self.slot_num(esk) > n);
         if let Some(i) = self.list_idx(esk, n) {
             // Replace existing item
-            return Some(std::mem::replace(&mut self.item_list.items[i].0, item));
+            return Some(std::mem::replace(&mut self.item_list.items[i], (item, num)));
         }
 
         if self.item_list.items.is_empty() {
             // If any item is not equipped.
-            self.item_list.items.push((item, 1));
+            self.item_list.items.push((item, num));
             self.set_list_idx(esk, n, 0);
             return None;
         }
@@ -447,7 +516,7 @@ impl EquipItemList {
         for i_slot in 0..self.slots.len() {
             if self.slots[i_slot].esk == esk && self.slots[i_slot].n as usize == n {
                 self.set_list_idx(esk, n, new_idx);
-                self.item_list.items.insert(new_idx, (item, 1));
+                self.item_list.items.insert(new_idx, (item, num));
                 processed_slot = i_slot;
                 break;
             } else if self.slots[i_slot].list_idx.is_some() {
@@ -464,6 +533,31 @@ impl EquipItemList {
         None
     }
 
+    /// Decrease the number of equipped item in specified slot, and returns its clone.
+    /// If the number becomes zero, the slot will be empty.
+    pub fn consume(&mut self, esk: EquipSlotKind, n: usize, num: u32) -> Option<Item> {
+        let i = self.list_idx(esk, n)?;
+        let item = self.item_list.items[i].0.clone();
+        if self.item_list.items[i].1 > num {
+            self.item_list.items[i].1 -= num;
+            return Some(item);
+        }
+
+        self.item_list.items.remove(i);
+        for slot in self.slots.iter_mut() {
+            match slot.list_idx {
+                Some(list_idx) if list_idx as usize == i => {
+                    slot.list_idx = None;
+                }
+                Some(list_idx) if list_idx as usize > i => {
+                    slot.list_idx = Some(list_idx - 1);
+                }
+                _ => (),
+            }
+        }
+        Some(item)
+    }
+
     fn list_idx(&self, esk: EquipSlotKind, n: usize) -> Option<usize> {
         if let Some(slot) = self
             .slots
@@ -4,6 +4,7 @@ use crate::gamedata::item::{Item, ItemList};
 use crate::gamedata::region::RegionId;
 use crate::gamedata::site::SiteId;
 use crate::objholder::*;
+use crate::script::TriggerScript;
 use array2d::*;
 use arrayvec::ArrayVec;
 use std::collections::HashMap;
@@ -28,6 +29,9 @@ pub struct Map {
     /// If this is None, nearest tile's infomation will be used
     pub outside_tile: Option<OutsideTileInfo>,
     pub boundary: MapBoundary,
+    /// Scripts launched by events on this map
+    #[serde(default)]
+    pub triggers: MapTriggers,
 }
 
 pub type TileArray = ArrayVec<[TileIdxPP; N_TILE_IMG_LAYER]>;
@@ -98,6 +102,15 @@ pub enum SpecialTileKind {
     SiteSymbol {
         kind: SiteSymbolKind,
     },
+    /// Trap is hidden from the player until detected
+    Trap {
+        kind: TrapKind,
+        detected: bool,
+    },
+    /// Closed doors block movement and sight
+    Door {
+        state: DoorState,
+    },
 }
 
 impl SpecialTileKind {
@@ -107,6 +120,14 @@ impl SpecialTileKind {
             _ => false,
         }
     }
+
+    /// Returns true if this is a trap which the player has not detected yet
+    pub fn is_hidden(&self) -> bool {
+        match *self {
+            SpecialTileKind::Trap { detected, .. } => !detected,
+            _ => false,
+        }
+    }
 }
 
 impl Default for SpecialTileKind {
@@ -121,6 +142,34 @@ pub enum StairsKind {
     DownStairs,
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrapKind {
+    Damage,
+    Poison,
+    SleepGas,
+    Teleport,
+    /// Alarm calls monsters to the floor
+    Alarm,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DoorState {
+    Open,
+    Closed,
+    /// Locked doors can be opened by keys or lockpicking
+    Locked {
+        /// Difficulty of lockpicking
+        level: u32,
+    },
+}
+
+impl DoorState {
+    pub fn is_open(self) -> bool {
+        self == DoorState::Open
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum SiteSymbolKind {
     Cave,
@@ -128,6 +177,8 @@ pub enum SiteSymbolKind {
     Tower,
     Town,
     Village,
+    Port,
+    Pass,
 }
 
 impl SpecialTileKind {
@@ -147,6 +198,19 @@ impl SpecialTileKind {
                 SiteSymbolKind::Tower => "!rm-tower",
                 SiteSymbolKind::Town => "!rm-town",
                 SiteSymbolKind::Village => "!rm-village",
+                SiteSymbolKind::Port => "!rm-port",
+                SiteSymbolKind::Pass => "!rm-pass",
+            },
+            SpecialTileKind::Trap { kind, .. } => match kind {
+                TrapKind::Damage => "!trap-damage",
+                TrapKind::Poison => "!trap-poison",
+                TrapKind::SleepGas => "!trap-sleep-gas",
+                TrapKind::Teleport => "!trap-teleport",
+                TrapKind::Alarm => "!trap-alarm",
+            },
+            SpecialTileKind::Door { state } => match state {
+                DoorState::Open => "!door-open",
+                DoorState::Closed | DoorState::Locked { .. } => "!door-closed",
             },
         })
     }
@@ -195,6 +259,30 @@ pub struct MapBoundary {
     pub w: BoundaryBehavior,
 }
 
+/// Scripts launched by events on a map
+#[derive(Clone, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MapTriggers {
+    /// Launched when the player enters the map
+    pub enter: Option<TriggerScript>,
+    /// Launched when the player steps onto the tile
+    pub tiles: Vec<(Vec2d, TriggerScript)>,
+    /// Launched when the player picks up an item which has the id
+    pub item_pickup: Vec<(String, TriggerScript)>,
+}
+
+impl MapTriggers {
+    pub fn tile(&self, pos: Vec2d) -> Option<&TriggerScript> {
+        self.tiles.iter().find(|(p, _)| *p == pos).map(|(_, t)| t)
+    }
+
+    pub fn item_pickup(&self, item_id: &str) -> Option<&TriggerScript> {
+        self.item_pickup
+            .iter()
+            .find(|(id, _)| id == item_id)
+            .map(|(_, t)| t)
+    }
+}
+
 /// Reperesents the floor that boundary connect to
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum BoundaryBehavior {
@@ -202,6 +290,8 @@ pub enum BoundaryBehavior {
     Floor(u32),
     RegionMap,
     MapId(MapId, u32),
+    /// Go to the region map of another region
+    Region(RegionId),
 }
 
 impl Default for BoundaryBehavior {
@@ -232,6 +322,17 @@ impl TileInfo {
             TileIdx::default()
         }
     }
+
+    /// Returns true if this tile blocks sight
+    pub fn is_opaque(&self) -> bool {
+        if !self.wall.is_empty() {
+            return true;
+        }
+        match self.special {
+            SpecialTileKind::Door { state } => !state.is_open(),
+            _ => false,
+        }
+    }
 }
 
 impl Map {
@@ -247,6 +348,7 @@ impl Map {
             charas: Some(HashMap::new()),
             outside_tile: None,
             boundary: MapBoundary::default(),
+            triggers: MapTriggers::default(),
         }
     }
 
@@ -432,6 +534,28 @@ impl Map {
         None
     }
 
+    /// Get trap on given tile
+    pub fn get_trap(&self, pos: Vec2d) -> Option<(TrapKind, bool)> {
+        if !self.is_inside(pos) {
+            return None;
+        }
+        match self.tile[pos].special {
+            SpecialTileKind::Trap { kind, detected } => Some((kind, detected)),
+            _ => None,
+        }
+    }
+
+    /// Get door state on given tile
+    pub fn get_door(&self, pos: Vec2d) -> Option<DoorState> {
+        if !self.is_inside(pos) {
+            return None;
+        }
+        match self.tile[pos].special {
+            SpecialTileKind::Door { state } => Some(state),
+            _ => None,
+        }
+    }
+
     /// Returns boundart when player move from 'pos' to 'dir' direction
     /// If its destination tile is inside map, return None
     pub fn get_boundary_by_tile_and_dir(
@@ -440,13 +564,13 @@ impl Map {
         dir: Direction,
     ) -> Option<BoundaryBehavior> {
         let dest_pos = pos + dir.as_vec();
-        if dest_pos.0 < 0 {
+        if dest_pos.1 < 0 {
             Some(self.boundary.n)
-        } else if dest_pos.0 >= self.h as i32 {
+        } else if dest_pos.1 >= self.h as i32 {
             Some(self.boundary.s)
-        } else if dest_pos.1 < 0 {
+        } else if dest_pos.0 < 0 {
             Some(self.boundary.w)
-        } else if dest_pos.1 >= self.w as i32 {
+        } else if dest_pos.0 >= self.w as i32 {
             Some(self.boundary.e)
         } else {
             None
@@ -520,3 +644,52 @@ impl From<RegionId> for MapId {
         MapId::RegionMap { rid }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boundary_by_tile_and_dir() {
+        // Not square to detect swapped x and y
+        let mut map = Map::new(5, 3);
+        map.boundary = MapBoundary {
+            n: BoundaryBehavior::Floor(0),
+            s: BoundaryBehavior::Floor(1),
+            e: BoundaryBehavior::Floor(2),
+            w: BoundaryBehavior::Floor(3),
+        };
+
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(4, 0), Direction::N),
+            Some(BoundaryBehavior::Floor(0))
+        );
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(4, 2), Direction::S),
+            Some(BoundaryBehavior::Floor(1))
+        );
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(4, 2), Direction::E),
+            Some(BoundaryBehavior::Floor(2))
+        );
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(0, 2), Direction::W),
+            Some(BoundaryBehavior::Floor(3))
+        );
+        // Destinations inside the map
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(2, 2), Direction::E),
+            None
+        );
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(4, 1), Direction::S),
+            None
+        );
+        // North and south borders have priority on corners
+        let dir = Direction::new(HDirection::Left, VDirection::Up);
+        assert_eq!(
+            map.get_boundary_by_tile_and_dir(Vec2d(0, 0), dir),
+            Some(BoundaryBehavior::Floor(0))
+        );
+    }
+}
@@ -6,6 +6,7 @@ pub mod meta;
 pub mod player;
 pub mod quest;
 pub mod region;
+pub mod schedule;
 pub mod shop;
 pub mod site;
 pub mod skill;
@@ -23,6 +24,7 @@ pub use self::meta::*;
 pub use self::player::*;
 pub use self::quest::*;
 pub use self::region::*;
+pub use self::schedule::*;
 pub use self::shop::*;
 pub use self::site::*;
 pub use self::skill::*;
@@ -42,7 +44,12 @@ pub struct GameData {
     pub player: Player,
     pub quest: QuestHolder,
     pub vars: Variables,
+    pub schedule: Schedule,
     current_mapid: MapId,
+    /// Box ids of the maps removed after the last save.
+    /// Their files are deleted at the next save because the last save may still refer to them.
+    #[serde(skip)]
+    pub(crate) removed_maps: Vec<u64>,
 }
 
 impl GameData {
@@ -55,7 +62,9 @@ impl GameData {
             player: Player::default(),
             quest: QuestHolder::new(),
             vars: Variables::new(),
+            schedule: Schedule::default(),
             current_mapid: MapId::default(),
+            removed_maps: Vec::new(),
         }
     }
 
@@ -139,6 +148,7 @@ impl GameData {
             }
         }
         self.chara.remove_chara(cid);
+        self.vars.remove_chara_vars(cid);
     }
 
     pub fn add_site(
@@ -158,6 +168,11 @@ impl GameData {
         MapId::SiteMap { sid, floor }
     }
 
+    /// Mark the map file to be deleted at the next save
+    pub fn remove_map_file(&mut self, map_random_id: u64) {
+        self.removed_maps.push(map_random_id);
+    }
+
     pub fn set_current_mapid(&mut self, mid: MapId) {
         // OnMap characters on the next map
         let next_charas = self
@@ -1,6 +1,7 @@
 use super::map::*;
 use super::site::*;
 use super::unknown_id_err;
+use crate::impl_filebox::MapLoadError;
 use array2d::*;
 use filebox::FileBox;
 use std::collections::HashMap;
@@ -17,6 +18,22 @@ pub struct Region {
     pub(crate) sites: HashMap<SiteId, SiteInfo>,
     /// An map to represents this region
     pub(crate) map: BoxedMap,
+    /// The seed of procedural generation. None if the map is created from a map template.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Travel sites leading to other regions
+    #[serde(default)]
+    pub(crate) travel_links: Vec<TravelLink>,
+}
+
+/// Travel site such as ports and passes on the region map
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TravelLink {
+    /// Position on this region map
+    pub pos: Vec2d,
+    pub dest: RegionId,
+    /// Position on the destination region map
+    pub dest_pos: Vec2d,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -157,16 +174,14 @@ impl RegionHolder {
     }
 
     /// Preload map from file
-    pub fn preload_map<P: AsRef<Path>>(&mut self, mid: MapId, map_dir_path: P) {
+    pub fn preload_map<P: AsRef<Path>>(
+        &mut self,
+        mid: MapId,
+        map_dir_path: P,
+    ) -> Result<(), MapLoadError> {
         info!("preload map {:?}", mid);
         let boxed_map = self.get_boxed_map_mut(mid);
-        match boxed_map.read(map_dir_path) {
-            Ok(_) => (),
-            Err(e) => {
-                error!("{}", e);
-                unimplemented!()
-            }
-        }
+        boxed_map.read(map_dir_path)
     }
 
     pub fn get_map_mut_checked(&mut self, mid: MapId) -> Option<&mut Map> {
@@ -179,6 +194,29 @@ impl RegionHolder {
         }
     }
 
+    /// Search the town which has given id
+    pub fn search_town(&self, town_id: &str) -> Option<SiteId> {
+        for region in self.0.values() {
+            for (sid, site_info) in &region.sites {
+                match site_info.site.content {
+                    SiteContent::Town { ref town } if town.id() == town_id => {
+                        return Some(*sid);
+                    }
+                    _ => (),
+                }
+            }
+        }
+        None
+    }
+
+    /// Search the region which has given id
+    pub fn search_region(&self, id: &str) -> Option<RegionId> {
+        self.0
+            .iter()
+            .find(|(_, region)| region.name == id)
+            .map(|(rid, _)| *rid)
+    }
+
     pub fn add_region(&mut self, mut region: Region) -> RegionId {
         // Search unused id
         for i in 0.. {
@@ -214,6 +252,8 @@ impl Region {
             id: RegionId(0),
             sites: HashMap::new(),
             map: FileBox::new(map_random_id, map),
+            seed: None,
+            travel_links: Vec::new(),
         }
     }
 
@@ -232,6 +272,17 @@ impl Region {
         Some(sid)
     }
 
+    /// Remove the site from the region. Map files of the site must be removed by the caller.
+    pub fn remove_site(&mut self, sid: SiteId) -> Option<Site> {
+        self.sites.remove(&sid).map(|site_info| site_info.site)
+    }
+
+    pub fn iter_sites(&self) -> impl Iterator<Item = (SiteId, &Site)> {
+        self.sites
+            .iter()
+            .map(|(sid, site_info)| (*sid, &site_info.site))
+    }
+
     /// Get the number of sites on the region
     pub fn get_site_n(&self, kind: SiteKind) -> u32 {
         self.sites.keys().filter(|&sid| sid.kind == kind).count() as u32
@@ -251,6 +302,23 @@ impl Region {
         &self.map
     }
 
+    pub fn id(&self) -> RegionId {
+        self.id
+    }
+
+    pub fn add_travel_link(&mut self, link: TravelLink) {
+        self.travel_links.push(link);
+    }
+
+    /// Get the travel site at the position
+    pub fn get_travel_link(&self, pos: Vec2d) -> Option<&TravelLink> {
+        self.travel_links.iter().find(|link| link.pos == pos)
+    }
+
+    pub fn travel_links(&self) -> &[TravelLink] {
+        &self.travel_links
+    }
+
     pub fn get_map_mut(&mut self) -> &mut Map {
         &mut self.map
     }
@@ -0,0 +1,29 @@
+use super::time::Time;
+use crate::script::TriggerScript;
+
+/// Holds scripts which will be launched at the scheduled time
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    /// Sorted by the scheduled time
+    scripts: Vec<(Time, TriggerScript)>,
+}
+
+impl Schedule {
+    pub fn add(&mut self, time: Time, script: TriggerScript) {
+        let i = self
+            .scripts
+            .iter()
+            .position(|(t, _)| *t > time)
+            .unwrap_or(self.scripts.len());
+        self.scripts.insert(i, (time, script));
+    }
+
+    /// Remove and return the earliest script if its time has come
+    pub fn pop_due(&mut self, now: Time) -> Option<TriggerScript> {
+        if self.scripts.first().map_or(false, |(t, _)| *t <= now) {
+            Some(self.scripts.remove(0).1)
+        } else {
+            None
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::map::Map;
 use super::region::RegionId;
+use super::time::{Duration, Time};
 use super::town::Town;
 use filebox::FileBox;
 
@@ -25,6 +26,8 @@ pub enum SiteContent {
     AutoGenDungeon {
         /// It is used for map generation, enemy race weighting, etc.
         dungeon_kind: DungeonKind,
+        #[serde(default)]
+        state: DungeonState,
     },
     /// Town consists of residents and shops, etc.
     Town {
@@ -33,6 +36,57 @@ pub enum SiteContent {
     },
     /// This does not include specific data, but character and other elements can be placed its map
     Other,
+    /// Temporary site for a random encounter on the region map.
+    /// It is removed when the player leaves.
+    Encounter {
+        /// Id of the encounter table in the rules
+        table: String,
+    },
+}
+
+/// Progress of the player in an auto generated dungeon
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct DungeonState {
+    /// The time when all enemies on the deepest floor were defeated
+    pub cleared: Option<Time>,
+    /// The last time the player left each floor
+    pub last_visit: Vec<Option<Time>>,
+}
+
+impl DungeonState {
+    pub fn is_cleared(&self) -> bool {
+        self.cleared.is_some()
+    }
+
+    pub fn last_visit(&self, floor: u32) -> Option<Time> {
+        self.last_visit.get(floor as usize).cloned().unwrap_or(None)
+    }
+
+    pub fn set_last_visit(&mut self, floor: u32, time: Time) {
+        let floor = floor as usize;
+        if self.last_visit.len() <= floor {
+            self.last_visit.resize(floor + 1, None);
+        }
+        self.last_visit[floor] = Some(time);
+    }
+
+    /// The dungeon was cleared `expire_days` or more days before `now`
+    pub fn is_expired(&self, now: Time, expire_days: u32) -> bool {
+        match self.cleared {
+            Some(cleared) => now.duration_from(cleared) >= Duration::from_days(expire_days as u64),
+            None => false,
+        }
+    }
+
+    /// The player left the floor `repopulate_days` or more days before `now`
+    pub fn needs_repopulation(&self, floor: u32, now: Time, repopulate_days: u32) -> bool {
+        match self.last_visit(floor) {
+            Some(last_visit) => {
+                now.duration_from(last_visit) >= Duration::from_days(repopulate_days as u64)
+            }
+            None => false,
+        }
+    }
 }
 
 impl Site {
@@ -106,6 +160,7 @@ impl SiteContent {
             &SiteContent::AutoGenDungeon { .. } => SiteKind::AutoGenDungeon,
             &SiteContent::Town { .. } => SiteKind::Town,
             &SiteContent::Other { .. } => SiteKind::Other,
+            &SiteContent::Encounter { .. } => SiteKind::Encounter,
         }
     }
 }
@@ -116,6 +171,7 @@ pub enum SiteKind {
     AutoGenDungeon,
     Town,
     Other,
+    Encounter,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
@@ -153,3 +209,54 @@ impl DungeonKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamedata::time::SECS_PER_DAY;
+
+    #[test]
+    fn last_visit() {
+        let mut state = DungeonState::default();
+        assert_eq!(state.last_visit(0), None);
+
+        state.set_last_visit(2, Time::from_seconds(100));
+        assert_eq!(state.last_visit(0), None);
+        assert_eq!(state.last_visit(1), None);
+        assert_eq!(state.last_visit(2), Some(Time::from_seconds(100)));
+        assert_eq!(state.last_visit(3), None);
+
+        state.set_last_visit(0, Time::from_seconds(200));
+        state.set_last_visit(2, Time::from_seconds(300));
+        assert_eq!(state.last_visit(0), Some(Time::from_seconds(200)));
+        assert_eq!(state.last_visit(2), Some(Time::from_seconds(300)));
+        assert_eq!(state.last_visit.len(), 3);
+    }
+
+    #[test]
+    fn expiration() {
+        let mut state = DungeonState::default();
+        let cleared = Time::from_seconds(SECS_PER_DAY);
+        assert!(!state.is_cleared());
+        assert!(!state.is_expired(cleared, 0));
+
+        state.cleared = Some(cleared);
+        assert!(state.is_cleared());
+        assert!(state.is_expired(cleared, 0));
+        assert!(!state.is_expired(Time::from_seconds(3 * SECS_PER_DAY - 1), 2));
+        assert!(state.is_expired(Time::from_seconds(3 * SECS_PER_DAY), 2));
+    }
+
+    #[test]
+    fn repopulation() {
+        let mut state = DungeonState::default();
+        let left = Time::from_seconds(SECS_PER_DAY);
+        assert!(!state.needs_repopulation(1, Time::from_seconds(10 * SECS_PER_DAY), 2));
+
+        state.set_last_visit(1, left);
+        assert!(!state.needs_repopulation(0, Time::from_seconds(10 * SECS_PER_DAY), 2));
+        assert!(!state.needs_repopulation(1, left, 2));
+        assert!(!state.needs_repopulation(1, Time::from_seconds(3 * SECS_PER_DAY - 1), 2));
+        assert!(state.needs_repopulation(1, Time::from_seconds(3 * SECS_PER_DAY), 2));
+    }
+}
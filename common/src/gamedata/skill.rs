@@ -9,9 +9,32 @@ pub enum SkillKind {
     Defence,
     Evasion,
     MartialArts,
+    /// Used to detect and disarm traps
+    Searching,
+    /// Used to open locked doors without keys
+    Lockpicking,
     Weapon(WeaponKind),
 }
 
+impl std::str::FromStr for SkillKind {
+    type Err = ();
+
+    /// Weapon skills are specified by the weapon kind name
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use self::SkillKind::*;
+        match s {
+            "endurance" => Ok(Endurance),
+            "healing" => Ok(Healing),
+            "defence" => Ok(Defence),
+            "evasion" => Ok(Evasion),
+            "martial_arts" => Ok(MartialArts),
+            "searching" => Ok(Searching),
+            "lockpicking" => Ok(Lockpicking),
+            _ => s.parse().map(Weapon),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct SkillList {
     pub skills: FnvHashMap<SkillKind, u32>,
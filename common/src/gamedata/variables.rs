@@ -1,3 +1,4 @@
+use crate::gamedata::chara::CharaId;
 use crate::hashmap::HashMap;
 use crate::script::Value;
 
@@ -5,12 +6,23 @@ use crate::script::Value;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Variables {
     global: HashMap<String, Value>,
+    /// Variables for each character
+    chara: HashMap<CharaId, HashMap<String, Value>>,
+    /// Script-local variables. They are cleared when a new script starts.
+    #[serde(skip)]
+    local: HashMap<String, Value>,
+    /// The character related to the current script
+    #[serde(skip)]
+    script_cid: Option<CharaId>,
 }
 
 impl Variables {
     pub fn new() -> Variables {
         Variables {
             global: HashMap::default(),
+            chara: HashMap::default(),
+            local: HashMap::default(),
+            script_cid: None,
         }
     }
 
@@ -24,6 +36,11 @@ impl Variables {
         self.global.get_mut(name)
     }
 
+    /// Iterate over global variables
+    pub fn iter_global(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.global.iter()
+    }
+
     /// Get globally named variable
     pub fn set_global_var<S: ToString>(&mut self, name: S, v: Value) {
         self.global.insert(name.to_string(), v);
@@ -34,4 +51,48 @@ impl Variables {
     pub fn set_last_result(&mut self, v: Value) {
         self.global.insert("?".to_owned(), v);
     }
+
+    /// Clear script-local variables and set the character related to the new script
+    pub fn begin_script(&mut self, cid: Option<CharaId>) {
+        self.local.clear();
+        self.script_cid = cid;
+    }
+
+    /// The character related to the current script
+    pub fn script_cid(&self) -> Option<CharaId> {
+        self.script_cid
+    }
+
+    /// Get script-local variable
+    pub fn local_var(&self, name: &str) -> Option<&Value> {
+        self.local.get(name)
+    }
+
+    /// Iterate over script-local variables
+    pub fn iter_local(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.local.iter()
+    }
+
+    /// Set script-local variable
+    pub fn set_local_var<S: ToString>(&mut self, name: S, v: Value) {
+        self.local.insert(name.to_string(), v);
+    }
+
+    /// Get variable of given character
+    pub fn chara_var(&self, cid: CharaId, name: &str) -> Option<&Value> {
+        self.chara.get(&cid).and_then(|vars| vars.get(name))
+    }
+
+    /// Set variable of given character
+    pub fn set_chara_var<S: ToString>(&mut self, cid: CharaId, name: S, v: Value) {
+        self.chara
+            .entry(cid)
+            .or_insert_with(HashMap::default)
+            .insert(name.to_string(), v);
+    }
+
+    /// Remove variables of given character
+    pub fn remove_chara_vars(&mut self, cid: CharaId) {
+        self.chara.remove(&cid);
+    }
 }
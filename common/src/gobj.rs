@@ -1,32 +1,119 @@
 //! This module provides global state objholder
 
+use crate::idx_conv::IdxConvTable;
 use crate::objholder::*;
+use crate::reloadable::Reloadable;
 use lazy_static::lazy_static;
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Mutex;
 
-/// Initialize lazy static
+/// Initialize global objholder
 pub fn init(pak_dirs: Vec<PathBuf>) {
+    let objholder = ObjectHolder::load(&pak_dirs);
     *PAK_DIRS.lock().unwrap() = Some(pak_dirs);
-    ::lazy_static::initialize(&OBJ_HOLDER);
+    set_objholder(objholder);
 }
 
-lazy_static! {
-    static ref PAK_DIRS: Mutex<Option<Vec<PathBuf>>> = Mutex::new(None);
-    static ref OBJ_HOLDER: ObjectHolder = {
+/// Objects loaded again, which are not used until `install` is called
+pub struct ReloadedObjects {
+    objholder: ObjectHolder,
+    hash: u64,
+}
+
+/// Load objects again from the pak directories given at initialization.
+/// Returns the table to convert indices for the reloaded objects if the id table is changed.
+/// Indices in the game data must be converted before installing the objects.
+pub fn reload() -> Option<(ReloadedObjects, Option<IdxConvTable>)> {
+    if !can_reload() {
+        warn!("reached the reload limit of objects, restart to apply changes");
+        return None;
+    }
+    let objholder = {
         let pak_dirs = PAK_DIRS.lock().unwrap();
-        ObjectHolder::load(pak_dirs.as_ref().unwrap())
-    };
-    pub static ref OBJ_HOLDER_HASH: u64 = {
-        use std::hash::{Hash, Hasher};
-        let mut hasher = fnv::FnvHasher::default();
-        get_objholder().hash(&mut hasher);
-        hasher.finish()
+        ObjectHolder::load(pak_dirs.as_ref().expect("objholder is not initialized"))
     };
+    let hash = hash_objholder(&objholder);
+
+    let mut prev_table = Vec::new();
+    get_objholder()
+        .write_table(&mut prev_table)
+        .expect("writing id table failed");
+    let prev_ids = id_set(&prev_table);
+
+    let mut table = Vec::new();
+    objholder
+        .write_table(&mut table)
+        .expect("writing id table failed");
+    let ids = id_set(&table);
+    for id in ids.difference(&prev_ids) {
+        info!("object {} is added", id);
+    }
+    for id in prev_ids.difference(&ids) {
+        info!("object {} is removed", id);
+    }
+
+    let mut prev_table_with_hash = format!("{:016x}\n", objholder_hash()).into_bytes();
+    prev_table_with_hash.extend_from_slice(&prev_table);
+    let idx_conv_table = IdxConvTable::read_with(&prev_table_with_hash[..], hash, &objholder)
+        .expect("reading id table failed");
+    Some((ReloadedObjects { objholder, hash }, idx_conv_table))
+}
+
+/// Replace the current objects with the reloaded objects
+pub fn install(objects: ReloadedObjects) {
+    OBJ_HOLDER.set(objects.objholder);
+    OBJ_HOLDER_HASH.store(objects.hash, Ordering::Release);
+}
+
+/// Returns false if objects have been reloaded too many times
+pub fn can_reload() -> bool {
+    OBJ_HOLDER.can_reload()
+}
+
+fn set_objholder(objholder: ObjectHolder) {
+    let hash = hash_objholder(&objholder);
+    OBJ_HOLDER.set(objholder);
+    OBJ_HOLDER_HASH.store(hash, Ordering::Release);
+}
+
+fn hash_objholder(objholder: &ObjectHolder) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = fnv::FnvHasher::default();
+    objholder.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Ids in the id table with object type names
+fn id_set(table: &[u8]) -> HashSet<String> {
+    let table = String::from_utf8_lossy(table);
+    let mut obj_type = "";
+    let mut ids = HashSet::new();
+    for line in table.lines() {
+        if line.starts_with(crate::basic::ID_TABLE_SECTION_TAG) {
+            obj_type = line.trim_start_matches(crate::basic::ID_TABLE_SECTION_TAG);
+        } else {
+            ids.insert(format!("{} \"{}\"", obj_type, line));
+        }
+    }
+    ids
+}
+
+lazy_static! {
+    static ref PAK_DIRS: Mutex<Option<Vec<PathBuf>>> = Mutex::new(None);
+}
+
+static OBJ_HOLDER: Reloadable<ObjectHolder> = Reloadable::new();
+static OBJ_HOLDER_HASH: AtomicU64 = AtomicU64::new(0);
+
+/// Hash of the current objholder to verify the identity of id tables
+pub fn objholder_hash() -> u64 {
+    OBJ_HOLDER_HASH.load(Ordering::Acquire)
 }
 
 pub fn get_objholder() -> &'static ObjectHolder {
-    &*OBJ_HOLDER
+    &OBJ_HOLDER
 }
 
 pub fn get_obj<T: ObjectIndex>(idx: T) -> &'static T::ObjectType {
@@ -57,3 +144,22 @@ pub fn get_by_id<T: FromId>(id: &str) -> &'static T {
 pub fn get_by_id_checked<T: FromId>(id: &str) -> Option<&'static T> {
     T::get_obj_from_objholder_by_id(id, &OBJ_HOLDER)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::basic::ID_TABLE_SECTION_TAG;
+
+    #[test]
+    fn id_set_with_types() {
+        let table = format!(
+            "{0}CharaTemplateObject\nslime\nrat\n{0}ItemObject\nslime\n",
+            ID_TABLE_SECTION_TAG
+        );
+        let ids = id_set(table.as_bytes());
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains("CharaTemplateObject \"slime\""));
+        assert!(ids.contains("CharaTemplateObject \"rat\""));
+        assert!(ids.contains("ItemObject \"slime\""));
+    }
+}
@@ -23,9 +23,16 @@ macro_rules! idx_conv {
 
         impl IdxConvTable {
             #[cfg(feature="global_state_obj")]
-            pub fn read<R: std::io::BufRead>(mut r: R, dest_hash: u64)
+            pub fn read<R: std::io::BufRead>(r: R, dest_hash: u64)
                                              -> Result<Option<IdxConvTable>, Box<std::error::Error>> {
+                Self::read_with(r, dest_hash, crate::gobj::get_objholder())
+            }
+
+            /// Read the id table to convert indices for the given objects
+            pub fn read_with<R: std::io::BufRead>(mut r: R, dest_hash: u64, dest: &crate::objholder::ObjectHolder)
+                                                  -> Result<Option<IdxConvTable>, Box<std::error::Error>> {
                 use crate::basic::ID_TABLE_SECTION_TAG;
+                use crate::objholder::ObjectIndex;
 
                 let hash = {
                     let mut buf = String::new();
@@ -48,7 +55,7 @@ macro_rules! idx_conv {
                         match current_obj_type.as_str() {
                             $(
                                 stringify!($obj) => {
-                                    if let Some(dest_idx) = crate::gobj::id_to_idx_checked::<$idx>(&line) {
+                                    if let Some(dest_idx) = $idx::search_idx(&line, dest) {
                                         table.$mem.push(dest_idx.as_usize() as u32);
                                     } else {
                                         table.$mem.push($idx::default().as_usize() as u32);
@@ -4,6 +4,7 @@ extern crate serde_derive;
 extern crate log;
 extern crate rusted_ruins_array2d as array2d;
 
+pub mod addon;
 pub mod basic;
 pub mod hashmap;
 pub mod obj;
@@ -18,6 +19,7 @@ pub mod objholder;
 pub mod pakutil;
 pub mod piece_pattern;
 pub mod regiongen;
+pub mod reloadable;
 pub mod saveload;
 pub mod script;
 pub mod sitegen;
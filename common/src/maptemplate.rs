@@ -1,7 +1,7 @@
 use crate::basic::N_TILE_IMG_LAYER;
 #[cfg(feature = "global_state_obj")]
 use crate::gamedata::map::TileLayers;
-use crate::gamedata::ItemGen;
+use crate::gamedata::{ItemGen, MapTriggers};
 #[cfg(feature = "global_state_obj")]
 use crate::objholder::ObjectIndex;
 use crate::piece_pattern::*;
@@ -25,6 +25,9 @@ pub struct MapTemplateObject {
     pub deco: Array2d<Option<u32>>,
     pub boundary: MapTemplateBoundary,
     pub items: Vec<(Vec2d, ItemGen)>,
+    /// Scripts launched by events on this map
+    #[serde(default)]
+    pub triggers: MapTriggers,
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
@@ -1,5 +1,6 @@
 use crate::gamedata;
 use crate::gamedata::CharaBaseAttr;
+use crate::script::TriggerScript;
 use std::fmt;
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -47,6 +48,8 @@ pub struct CharaTemplateObject {
     /// Default AI kind for this character
     pub default_ai_kind: gamedata::NpcAIKind,
     pub base_attr: CharaBaseAttr,
+    /// Script launched when a character of this template dies
+    pub trigger_death: Option<TriggerScript>,
 }
 
 #[derive(Serialize, Deserialize)]
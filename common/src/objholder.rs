@@ -97,6 +97,8 @@ macro_rules! impl_objholder {
                 }
             }
 
+            /// Load objects from the given directories in order.
+            /// If some objects have the same id, the object loaded later overrides the others.
             pub fn load<P: AsRef<Path>>(dirs: &[P]) -> ObjectHolder {
                 let mut objholder = ObjectHolder::new();
 
@@ -114,7 +116,19 @@ macro_rules! impl_objholder {
 
             fn sort(&mut self) {
                 {
-                    $(self.$mem.sort_by(|a, b| a.id.cmp(&b.id)));*
+                    // Stable sort keeps the loading order of objects which have the same id
+                    $(
+                        self.$mem.sort_by(|a, b| a.id.cmp(&b.id));
+                        self.$mem.dedup_by(|later, former| {
+                            if later.id == former.id {
+                                info!("object \"{}\" is overridden", later.id);
+                                std::mem::swap(later, former);
+                                true
+                            } else {
+                                false
+                            }
+                        });
+                    )*
                 }
 
                 // chara_template is sorted by the special function
@@ -216,3 +230,33 @@ fn cmp_chara_template(a: &CharaTemplateObject, b: &CharaTemplateObject) -> std::
     }
     a.id.cmp(&b.id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hashmap::HashMap;
+    use crate::script::Script;
+
+    fn script_obj(id: &str, section: &str) -> ScriptObject {
+        let mut map = HashMap::default();
+        map.insert(section.to_owned(), Vec::new());
+        ScriptObject {
+            id: id.to_owned(),
+            script: Script::from_map(map),
+        }
+    }
+
+    #[test]
+    fn later_object_overrides() {
+        let mut objholder = ObjectHolder::new();
+        objholder.script.push(script_obj("b", "base"));
+        objholder.script.push(script_obj("a", "base"));
+        objholder.script.push(script_obj("b", "addon1"));
+        objholder.script.push(script_obj("b", "addon2"));
+        objholder.sort();
+
+        let ids: Vec<&str> = objholder.script.iter().map(|o| o.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        assert!(objholder.script[1].script.has_section("addon2"));
+    }
+}
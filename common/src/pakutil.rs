@@ -44,15 +44,19 @@ fn walk_dir(dir: &Path, cb: &mut FnMut(Object), err_stack: &mut Vec<PakLoadingEr
         }
     };
 
+    // Sort paths to make the loading order deterministic
+    let mut paths = Vec::new();
     for entry in entry_iter {
-        let entry = match entry {
-            Ok(o) => o,
+        match entry {
+            Ok(o) => paths.push(o.path()),
             Err(e) => {
                 err_stack.push(PakLoadingError::Io(e));
-                continue;
             }
-        };
-        let path = entry.path();
+        }
+    }
+    paths.sort();
+
+    for path in paths {
         if path.is_dir() {
             walk_dir(&path, cb, err_stack);
         } else if path.extension() != None && path.extension().unwrap() == "pak" {
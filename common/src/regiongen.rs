@@ -1,3 +1,6 @@
+use crate::gamedata::map::SiteSymbolKind;
+use crate::gamedata::site::DungeonKind;
+use crate::sitegen::SitePlacement;
 use array2d::Vec2d;
 
 /// Hold data for region generation
@@ -7,4 +10,99 @@ pub struct RegionGenObject {
     pub map_template_id: String,
     /// Id and position of SiteGenObject for towns
     pub towns: Vec<(String, Vec2d)>,
+    /// Parameters to generate the region map procedurally instead of the map template
+    #[serde(default)]
+    pub gen: Option<RegionMapGenParams>,
+    /// Dungeon kinds generated in this region. All kinds in the rules if empty.
+    #[serde(default)]
+    pub dungeons: Vec<DungeonKind>,
+    /// Connections to other regions. They are made in both directions,
+    /// so only one of the connected regions needs to have it.
+    #[serde(default)]
+    pub connections: Vec<RegionConnection>,
+    /// Random encounters happen more often and enemies are stronger in dangerous regions
+    #[serde(default)]
+    pub danger_level: u32,
+}
+
+/// Connection to another region
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RegionConnection {
+    /// Id of the connected RegionGenObject
+    pub region: String,
+    pub via: RegionConnectionKind,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RegionConnectionKind {
+    /// Crossing the border of this region map leads to the opposite border of the other region
+    Border(MapBorder),
+    /// Travel sites such as ports and passes are placed on both region maps
+    Site {
+        symbol: SiteSymbolKind,
+        /// Position on this region map
+        #[serde(default)]
+        pos: Option<Vec2d>,
+        /// Position on the other region map
+        #[serde(default)]
+        dest_pos: Option<Vec2d>,
+        /// Constraints to place the sites on generated regions if the position is not given
+        #[serde(default)]
+        placement: SitePlacement,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MapBorder {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl MapBorder {
+    pub fn opposite(self) -> MapBorder {
+        match self {
+            MapBorder::North => MapBorder::South,
+            MapBorder::South => MapBorder::North,
+            MapBorder::East => MapBorder::West,
+            MapBorder::West => MapBorder::East,
+        }
+    }
+}
+
+/// Terrain kinds of procedurally generated region maps
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Biome {
+    Plains,
+    Forest,
+    Mountains,
+    Water,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RegionMapGenParams {
+    pub size: Vec2d,
+    /// Approximate size of continents and lakes in tiles
+    pub noise_scale: f64,
+    /// Tiles lower than this are water. Heights are normalized in [0, 1].
+    pub water_level: f64,
+    /// Tiles higher than this are mountains
+    pub mountain_level: f64,
+    /// Land tiles with higher moisture than this are forests
+    pub forest_level: f64,
+    /// Tile ids for each biome
+    pub biome_tiles: Vec<BiomeTile>,
+    /// Ids of SiteGenObject for towns placed by their placement constraints
+    #[serde(default)]
+    pub towns: Vec<String>,
+    /// Minimum manhattan distance between sites
+    #[serde(default)]
+    pub min_site_distance: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct BiomeTile {
+    pub biome: Biome,
+    pub tile: String,
 }
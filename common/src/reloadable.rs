@@ -0,0 +1,106 @@
+//! Global state which can be replaced while the game runs.
+
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// Maximum number of times a value can be replaced.
+/// Replaced values are never freed, so this bounds the leaked memory.
+pub const MAX_RELOADS: usize = 64;
+
+/// Holds a global value which can be replaced by reloading.
+/// Replaced values are leaked to keep the references given before valid,
+/// so reloading should be used only for development.
+pub struct Reloadable<T> {
+    ptr: AtomicPtr<T>,
+    n_reloads: AtomicUsize,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Reloadable<T> {
+    pub const fn new() -> Reloadable<T> {
+        Reloadable {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            n_reloads: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns false if the value has been replaced MAX_RELOADS times
+    pub fn can_reload(&self) -> bool {
+        self.ptr.load(Ordering::Acquire).is_null()
+            || self.n_reloads.load(Ordering::Acquire) < MAX_RELOADS
+    }
+
+    /// Set the initial or reloaded value.
+    /// Returns false and keeps the current value if it cannot be reloaded any more.
+    pub fn set(&self, value: T) -> bool {
+        if !self.can_reload() {
+            warn!("reached the reload limit, restart to apply changes");
+            return false;
+        }
+        let p = Box::into_raw(Box::new(value));
+        let prev = self.ptr.swap(p, Ordering::AcqRel);
+        if !prev.is_null() {
+            self.n_reloads.fetch_add(1, Ordering::AcqRel);
+        }
+        true
+    }
+}
+
+impl<T> Default for Reloadable<T> {
+    fn default() -> Reloadable<T> {
+        Reloadable::new()
+    }
+}
+
+impl<T> Deref for Reloadable<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        let p = self.ptr.load(Ordering::Acquire);
+        if p.is_null() {
+            panic!("access to uninitialized global state");
+        }
+        // SAFETY: non-null pointers are only stored by `set` from `Box::into_raw`,
+        // and they are never freed even after replaced. So the value lives for
+        // the rest of the program and is never mutated.
+        unsafe { &*p }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_and_reload() {
+        let r: Reloadable<String> = Reloadable::new();
+        assert!(r.set("a".to_owned()));
+        let a: &String = &r;
+        assert!(r.set("b".to_owned()));
+        // References given before reloading are still valid
+        assert_eq!(a, "a");
+        assert_eq!(&*r, "b");
+    }
+
+    #[test]
+    #[should_panic]
+    fn uninitialized() {
+        let r: Reloadable<u32> = Reloadable::new();
+        let _ = *r;
+    }
+
+    #[test]
+    fn reload_limit() {
+        let r: Reloadable<usize> = Reloadable::new();
+        assert!(r.set(0));
+        for i in 1..=MAX_RELOADS {
+            assert!(r.set(i));
+        }
+        assert!(!r.can_reload());
+        assert!(!r.set(MAX_RELOADS + 1));
+        assert_eq!(*r, MAX_RELOADS);
+    }
+}
@@ -1,8 +1,9 @@
 use crate::basic::SAVE_EXTENSION;
 use crate::gamedata::*;
 use crate::impl_filebox::MapLoadError;
-use serde_cbor::from_reader;
+use serde_cbor::ser::to_vec_packed;
 use serde_cbor::ser::to_writer_packed;
+use serde_cbor::{from_reader, from_slice};
 use std::fs::{create_dir_all, File};
 use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
@@ -10,7 +11,7 @@ use std::path::{Path, PathBuf};
 #[cfg(feature = "global_state_obj")]
 impl GameData {
     /// Save game data to the specified directory
-    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<std::error::Error>> {
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Box<std::error::Error>> {
         if cfg!(debug_assertions) {
             print_save_data_size(self); // Debug code for save file size optimization
         }
@@ -22,7 +23,7 @@ impl GameData {
 
         // Write id table file
         let mut file = BufWriter::new(File::create(save_dir.join("idtable"))?);
-        writeln!(file, "{:016x}", *crate::gobj::OBJ_HOLDER_HASH)?;
+        writeln!(file, "{:016x}", crate::gobj::objholder_hash())?;
         crate::gobj::get_objholder().write_table(&mut file)?;
 
         // Write metadata file
@@ -48,9 +49,31 @@ impl GameData {
             return Err(errors.into_iter().next().unwrap().into());
         }
 
+        self.delete_removed_maps(&map_dir);
         Ok(())
     }
 
+    /// Delete files of the removed maps after the save which does not refer to them is written
+    fn delete_removed_maps(&mut self, map_dir: &Path) {
+        // Ids of maps whose files were never written can be reused by new maps
+        let mut used_ids = std::collections::HashSet::new();
+        self.region.visit_all_maps(|_mid, map| {
+            used_ids.insert(map.id());
+        });
+
+        for id in self.removed_maps.drain(..) {
+            if used_ids.contains(&id) {
+                continue;
+            }
+            let path = BoxedMap::empty(id).path(map_dir);
+            if path.exists() {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    warn!("Failed to remove {}: {}", path.to_string_lossy(), e);
+                }
+            }
+        }
+    }
+
     /// Load game data from specified directory
     pub fn load<P: AsRef<Path>>(path: P) -> Result<GameData, Box<std::error::Error>> {
         let save_dir = path.as_ref();
@@ -62,7 +85,7 @@ impl GameData {
         // Read index conversion table
         let mut file = BufReader::new(File::open(save_dir.join("idtable"))?);
         let idx_conv_table =
-            crate::idx_conv::IdxConvTable::read(&mut file, *crate::gobj::OBJ_HOLDER_HASH)?;
+            crate::idx_conv::IdxConvTable::read(&mut file, crate::gobj::objholder_hash())?;
         let is_table_changed = idx_conv_table.is_some();
         if is_table_changed {
             info!("Detected changes in the id table. Conversion table is created.");
@@ -82,17 +105,61 @@ impl GameData {
                 mid_vec.push(mid);
             });
             for mid in &mid_vec {
-                gamedata.region.preload_map(*mid, &map_dir);
+                gamedata.region.preload_map(*mid, &map_dir)?;
             }
         } else {
             // Preload current map
             let mid = gamedata.get_current_mapid();
-            gamedata.region.preload_map(mid, &map_dir);
+            gamedata.region.preload_map(mid, &map_dir)?;
         }
 
         Ok(gamedata)
     }
 
+    /// Convert object indices after objects are reloaded.
+    /// Maps not loaded yet are loaded from the given directory before conversion.
+    pub fn convert_idx<P: AsRef<Path>>(
+        &mut self,
+        idx_conv_table: crate::idx_conv::IdxConvTable,
+        map_dir: P,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut mid_vec = Vec::new();
+        self.region.visit_all_maps(|mid, _map| {
+            mid_vec.push(mid);
+        });
+        for mid in &mid_vec {
+            self.region.preload_map(*mid, map_dir.as_ref())?;
+        }
+
+        // Serialize with the previous indices, and deserialize with the conversion table
+        let gamedata = to_vec_packed(&self)?;
+        let mut maps = Vec::new();
+        for mid in &mid_vec {
+            maps.push(to_vec_packed(self.region.get_map(*mid))?);
+        }
+
+        crate::idx_conv::set_idx_conv_table(Some(idx_conv_table));
+        let result = (|| -> Result<GameData, serde_cbor::error::Error> {
+            let mut gamedata: GameData = from_slice(&gamedata)?;
+            for (mid, map) in mid_vec.iter().zip(maps.iter()) {
+                let map: Map = from_slice(map)?;
+                let boxed_map = gamedata.region.get_boxed_map_mut(*mid);
+                *boxed_map = BoxedMap::new(boxed_map.id(), map);
+                // Mutable access marks the map as changed to be written at the next save
+                let _ = &mut **boxed_map;
+            }
+            Ok(gamedata)
+        })();
+        crate::idx_conv::set_idx_conv_table(None);
+
+        let mut gamedata = result?;
+        std::mem::swap(&mut gamedata.meta, &mut self.meta);
+        std::mem::swap(&mut gamedata.vars, &mut self.vars);
+        std::mem::swap(&mut gamedata.removed_maps, &mut self.removed_maps);
+        *self = gamedata;
+        Ok(())
+    }
+
     pub fn save_dir<P: AsRef<Path>>(&self, path: P) -> PathBuf {
         path.as_ref()
             .join(format!("{}.{}", self.meta.save_name(), SAVE_EXTENSION))
@@ -102,7 +169,6 @@ impl GameData {
 /// Print save data size
 #[cfg(debug_assertions)]
 fn print_save_data_size(gd: &GameData) {
-    use serde_cbor::ser::to_vec_packed;
     let v = to_vec_packed(&gd).unwrap();
     println!("Total size = {}", v.len());
     let v = to_vec_packed(&gd.region.0).unwrap();
@@ -118,3 +184,51 @@ fn print_save_data_size(gd: &GameData) {
 
 #[cfg(not(debug_assertions))]
 fn print_save_data_size(_gd: &GameData) {}
+
+#[cfg(all(test, feature = "global_state_obj"))]
+mod tests {
+    use super::*;
+    use crate::idx_conv::IdxConvTable;
+    use crate::script::Value;
+    use filebox::FileBox;
+
+    #[test]
+    fn convert_idx_keeps_vars() {
+        let mut gd = GameData::empty();
+        gd.vars.set_local_var("a", Value::Int(1));
+        gd.convert_idx(IdxConvTable::default(), "").unwrap();
+        assert_eq!(gd.vars.local_var("a"), Some(&Value::Int(1)));
+    }
+
+    #[test]
+    fn convert_idx_missing_map_file() {
+        let mut gd = GameData::empty();
+        let mut region = Region::new("test", Map::new(1, 1), 0);
+        region.map = FileBox::empty(0);
+        gd.region.add_region(region);
+        let map_dir = std::env::temp_dir().join("rusted-ruins-missing-map-dir");
+        assert!(gd.convert_idx(IdxConvTable::default(), map_dir).is_err());
+    }
+
+    #[test]
+    fn removed_map_files_are_kept_until_save() {
+        let map_dir = std::env::temp_dir().join("rusted-ruins-removed-map-dir");
+        create_dir_all(&map_dir).unwrap();
+        let path = |id: u64| BoxedMap::empty(id).path(&map_dir);
+        File::create(path(1)).unwrap();
+        File::create(path(2)).unwrap();
+
+        // Map 2 is reused by a new region after its previous file was removed
+        let mut gd = GameData::empty();
+        gd.region.add_region(Region::new("test", Map::new(1, 1), 2));
+        gd.remove_map_file(1);
+        gd.remove_map_file(2);
+        assert!(path(1).exists());
+
+        gd.delete_removed_maps(&map_dir);
+        assert!(!path(1).exists());
+        assert!(path(2).exists());
+        assert!(gd.removed_maps.is_empty());
+        std::fs::remove_dir_all(&map_dir).unwrap();
+    }
+}
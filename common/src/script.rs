@@ -1,5 +1,6 @@
-use crate::gamedata::Time;
+use crate::gamedata::{CharaAttrKind, Relationship, SkillKind, Time};
 use crate::hashmap::HashMap;
+use array2d::Vec2d;
 use std::fmt;
 use std::ops::Index;
 
@@ -10,18 +11,50 @@ pub enum Instruction {
     Jump(String),
     /// Jump if given expr is true
     JumpIf(String, Expr),
-    /// Talk instruction (textid, Vec<choice's textid, destination section>)
-    Talk(String, Vec<(String, String)>),
+    /// Talk instruction (textid, choices)
+    Talk(String, Vec<TalkChoice>),
+    /// Call given section as a subroutine
+    Call(String),
+    /// Return from the current subroutine. Quit the script if not in a subroutine
+    Return,
     /// Set global variable
     GSet(String, Expr),
+    /// Set script-local variable
+    LSet(String, Expr),
+    /// Set variable of the character related to the script
+    CSet(String, Expr),
     /// Player receive money
     ReceiveMoney(Expr),
     /// Remove item form player's inventory
     RemoveItem(String),
+    /// Player receive items (ItemGen id, number)
+    GiveItem(String, u32),
+    /// Spawn a character on the current map (chara template id, position, relationship)
+    SpawnChara(String, Vec2d, Relationship),
+    /// Change relationship of the character related to the script
+    SetRelationship(Relationship),
+    /// Move the player to the given map and position
+    Teleport(MapDest, Vec2d),
+    /// Start a new quest (quest, reward money, reward exp)
+    StartQuest(ScriptQuest, i64, u32),
+    /// Play a sound effect
+    PlaySound(String),
+    /// Change background music
+    PlayMusic(String),
     /// Special Instruction
     Special(SpecialInstruction),
 }
 
+/// Choice of talk instruction
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TalkChoice {
+    pub text_id: String,
+    /// Destination section
+    pub section: String,
+    /// The choice is shown only if this condition is true
+    pub cond: Option<Expr>,
+}
+
 /// Special Instructions
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum SpecialInstruction {
@@ -53,6 +86,24 @@ impl std::str::FromStr for SpecialInstruction {
     }
 }
 
+/// Destination map of teleport instruction
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum MapDest {
+    /// Floor of the current site
+    Floor(u32),
+    /// Floor of the town with given id
+    Town(String, u32),
+    /// Region map of the current region
+    Region,
+}
+
+/// Quests which can be started by scripts
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum ScriptQuest {
+    /// Slay monsters (chara template id, goal)
+    SlayMonsters(String, u32),
+}
+
 /// Expression in script.
 #[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Expr {
@@ -60,10 +111,18 @@ pub enum Expr {
     Term(Vec<(Operator, Expr)>),
     /// Reference to global variable
     GVar(String),
+    /// Reference to script-local variable
+    LVar(String),
+    /// Reference to variable of the character related to the script
+    CVar(String),
     IsGVarEmpty(String),
     CurrentTime,
     DurationHour(Box<Expr>, Box<Expr>),
     HasItem(String),
+    /// Skill level of the player
+    PlayerSkill(SkillKind),
+    /// Attribute value of the player
+    PlayerAttr(CharaAttrKind),
 }
 
 /// Value is the result of evaluation of Expr.
@@ -71,6 +130,7 @@ pub enum Expr {
 pub enum Value {
     Bool(bool),
     Int(i32),
+    String(String),
     Time(Time),
     /// Referenced for unknown variable. This can be changed to 0 or false.
     RefUnknownVar,
@@ -123,6 +183,14 @@ impl Script {
     pub fn section(&self, s: &str) -> &[Instruction] {
         self.0[s].as_ref()
     }
+
+    pub fn has_section(&self, s: &str) -> bool {
+        self.0.contains_key(s)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Vec<Instruction>)> {
+        self.0.iter()
+    }
 }
 
 pub const QUIT_SECTION: &'static str = "quit";
@@ -132,9 +200,19 @@ pub const CONTINUE_SECTION: &'static str = "continue";
 pub struct ScriptPos {
     pub section: String,
     pub i: usize,
+    /// Return positions of called subroutines
+    pub call_stack: Vec<(String, usize)>,
 }
 
 impl ScriptPos {
+    pub fn new<S: ToString>(section: S) -> ScriptPos {
+        ScriptPos {
+            section: section.to_string(),
+            i: 0,
+            call_stack: Vec::new(),
+        }
+    }
+
     pub fn advance(&mut self) {
         self.i += 1;
     }
@@ -148,6 +226,23 @@ impl ScriptPos {
         self.i = 0;
         self.section = section;
     }
+
+    /// Call given section. The next instruction is saved as the return position.
+    pub fn call<S: ToString>(&mut self, section: S) {
+        self.call_stack.push((self.section.clone(), self.i + 1));
+        self.set_section(section);
+    }
+
+    /// Return to the saved position. Returns false if the call stack is empty.
+    pub fn ret(&mut self) -> bool {
+        if let Some((section, i)) = self.call_stack.pop() {
+            self.section = section;
+            self.i = i;
+            true
+        } else {
+            false
+        }
+    }
 }
 
 impl<'a> Index<&'a ScriptPos> for Script {
@@ -158,6 +253,21 @@ impl<'a> Index<&'a ScriptPos> for Script {
     }
 }
 
+/// A script launched by an event
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TriggerScript {
+    /// Id of the script object
+    pub id: String,
+    /// The first section to execute. If not specified, the script starts from "start".
+    pub section: Option<String>,
+}
+
+impl TriggerScript {
+    pub fn section(&self) -> &str {
+        self.section.as_ref().map(|s| s.as_str()).unwrap_or("start")
+    }
+}
+
 /// Object that include script data.
 #[derive(Serialize, Deserialize)]
 pub struct ScriptObject {
@@ -1,5 +1,7 @@
 use crate::gamedata::shop::ShopKind;
 use crate::gamedata::site::SiteKind;
+use crate::regiongen::Biome;
+use crate::script::TriggerScript;
 use array2d::Vec2d;
 
 /// Hold data for site generation
@@ -10,6 +12,21 @@ pub struct SiteGenObject {
     pub map_template_id: Vec<String>,
     pub unique_citizens: Vec<UniqueCitizenGenData>,
     pub shops: Vec<ShopGenData>,
+    pub scheduled_scripts: Vec<ScheduledScriptGenData>,
+    /// Constraints to place the site on procedurally generated regions
+    #[serde(default)]
+    pub placement: SitePlacement,
+}
+
+/// Where the site can be placed on procedurally generated regions
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct SitePlacement {
+    /// Biomes the site can be placed on. Any land biome if empty.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    /// The site is placed next to water tiles, like ports
+    #[serde(default)]
+    pub near_water: bool,
 }
 
 /// Data to generate a unique citizen
@@ -22,6 +39,15 @@ pub struct UniqueCitizenGenData {
     pub n: u32,
     pub chara_template_id: String,
     pub talk_script_id: Option<String>,
+    pub trigger_death: Option<TriggerScript>,
+}
+
+/// Data to schedule a script when the site is generated
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ScheduledScriptGenData {
+    /// Hours from the site generation
+    pub hours: u32,
+    pub script: TriggerScript,
 }
 
 /// Data to generate a shop on the site
@@ -0,0 +1,408 @@
+//! Incremental build of paks from a source directory.
+//! Each subdirectory of the source directory is built into one pak.
+//! Built objects are cached with the hashes of their input files, referenced images and base objects,
+//! and only paks including changed objects are rewritten.
+
+use crate::compile::{print_error, read_input, write_data_to_tar, write_to_vec};
+use crate::dir;
+use crate::error::*;
+use crate::template;
+use crate::verbose::print_verbose;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+const CACHE_DIR_NAME: &str = ".makepak-cache";
+const CACHE_INDEX_FILE_NAME: &str = "index";
+
+#[derive(Default, Serialize, Deserialize)]
+struct Cache {
+    /// Cache is discarded if built by another version
+    version: String,
+    objects: HashMap<PathBuf, CacheEntry>,
+    /// Input files of each pak
+    paks: HashMap<String, Vec<PathBuf>>,
+    /// Scripts are checked with talk text ids, so objects are rebuilt if they are changed
+    talk_text_ids: Option<u64>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    /// Ids of the objects generated from the input file
+    ids: Vec<String>,
+    /// Input file and referenced files with their content hashes
+    deps: Vec<(PathBuf, u64)>,
+}
+
+#[derive(Default)]
+pub struct BuildSummary {
+    /// Ids of rebuilt objects
+    pub rebuilt: Vec<String>,
+    pub n_reused: usize,
+    pub n_failed: usize,
+    pub written_paks: Vec<PathBuf>,
+    pub removed_paks: Vec<PathBuf>,
+    pub n_unchanged_paks: usize,
+}
+
+impl std::fmt::Display for BuildSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for id in &self.rebuilt {
+            writeln!(f, "rebuilt: {}", id)?;
+        }
+        for pak in &self.written_paks {
+            writeln!(f, "written: {}", pak.to_string_lossy())?;
+        }
+        for pak in &self.removed_paks {
+            writeln!(f, "removed: {}", pak.to_string_lossy())?;
+        }
+        write!(
+            f,
+            "{} objects rebuilt, {} reused, {} failed. {} paks written, {} unchanged.",
+            self.rebuilt.len(),
+            self.n_reused,
+            self.n_failed,
+            self.written_paks.len(),
+            self.n_unchanged_paks
+        )
+    }
+}
+
+/// Build paks from the subdirectories of src_dir into out_dir
+pub fn build(src_dir: &Path, out_dir: &Path) -> Result<BuildSummary, Error> {
+    let cache_dir = out_dir.join(CACHE_DIR_NAME);
+    fs::create_dir_all(&cache_dir)?;
+    let mut prev_cache = load_cache(&cache_dir);
+    let mut cache = Cache {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        talk_text_ids: crate::talk_text::ids_hash(),
+        ..Cache::default()
+    };
+    if prev_cache.talk_text_ids != cache.talk_text_ids {
+        print_verbose(|| "Talk text ids are changed".to_owned());
+        prev_cache.objects.clear();
+    }
+    let mut summary = BuildSummary::default();
+
+    let mut paks = Vec::new();
+    for pak_src_dir in sorted_entries(src_dir)?.into_iter().filter(|p| p.is_dir()) {
+        let pak_name = format!("{}.pak", pak_src_dir.file_name().unwrap().to_string_lossy());
+        let mut inputs = Vec::new();
+        input_files(&pak_src_dir, &mut inputs)?;
+        paks.push((pak_name, inputs));
+    }
+    // Objects can inherit base objects in other paks
+    template::load_bases(&paks.iter().flat_map(|p| p.1.iter()).collect::<Vec<_>>());
+
+    for (pak_name, inputs) in paks {
+        let pak_path = out_dir.join(&pak_name);
+        let mut changed = !pak_path.exists() || prev_cache.paks.get(&pak_name) != Some(&inputs);
+        let mut objs: Vec<(String, Vec<u8>)> = Vec::new();
+        let mut failed = false;
+
+        for input in &inputs {
+            // Reuse the cached objects if all dependencies are unchanged
+            if let Some(entry) = prev_cache.objects.get(input) {
+                if let Some(cached) = read_cached_objs(&cache_dir, input, entry) {
+                    print_verbose(|| format!("Reuse \"{}\"", input.to_string_lossy()));
+                    summary.n_reused += cached.len();
+                    objs.extend(cached);
+                    cache.objects.insert(input.clone(), entry.clone());
+                    continue;
+                }
+            }
+
+            changed = true;
+            dir::take_dependencies();
+            let input_objs = match read_input(input) {
+                Ok(o) => o,
+                Err(echain) => {
+                    print_error(input, &echain);
+                    summary.n_failed += 1;
+                    failed = true;
+                    continue;
+                }
+            };
+
+            let mut deps = vec![input.clone()];
+            deps.extend(dir::take_dependencies());
+            let deps = deps
+                .into_iter()
+                .map(|p| {
+                    let hash = hash_file(&p)?;
+                    Ok((p, hash))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let mut ids = Vec::new();
+            for (i, obj) in input_objs.iter().enumerate() {
+                let data = write_to_vec(obj)?;
+                fs::write(cache_dir.join(obj_file_name(input, i)), &data)?;
+                let id = obj.get_id().to_owned();
+                summary.rebuilt.push(id.clone());
+                ids.push(id.clone());
+                objs.push((id, data));
+            }
+            cache
+                .objects
+                .insert(input.clone(), CacheEntry { ids, deps });
+        }
+
+        // Paks lacking objects are not written to keep the previous ones
+        if changed && !failed {
+            let mut builder = tar::Builder::new(File::create(&pak_path)?);
+            for (id, data) in &objs {
+                write_data_to_tar(&mut builder, data, id);
+            }
+            builder.finish()?;
+            summary.written_paks.push(pak_path);
+        } else if !failed {
+            summary.n_unchanged_paks += 1;
+        }
+        cache.paks.insert(pak_name, inputs);
+    }
+
+    // Remove paks whose source directories are removed
+    for pak_name in prev_cache.paks.keys() {
+        let pak_path = out_dir.join(pak_name);
+        if !cache.paks.contains_key(pak_name) && pak_path.exists() {
+            fs::remove_file(&pak_path)?;
+            summary.removed_paks.push(pak_path);
+        }
+    }
+
+    save_cache(&cache_dir, &cache)?;
+    remove_unused_objects(&cache_dir, &cache)?;
+    if summary.n_failed > 0 {
+        return Err(PakCompileError::BuildFailed {
+            n_failed: summary.n_failed,
+        }
+        .into());
+    }
+    Ok(summary)
+}
+
+/// Read cached objects if all dependencies of the input are unchanged
+fn read_cached_objs(
+    cache_dir: &Path,
+    input: &Path,
+    entry: &CacheEntry,
+) -> Option<Vec<(String, Vec<u8>)>> {
+    if !entry
+        .deps
+        .iter()
+        .all(|(path, hash)| hash_file(path).ok() == Some(*hash))
+    {
+        return None;
+    }
+    entry
+        .ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| {
+            let data = fs::read(cache_dir.join(obj_file_name(input, i))).ok()?;
+            Some((id.clone(), data))
+        })
+        .collect()
+}
+
+/// Collect toml and rrscript files recursively
+fn input_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for path in sorted_entries(dir)? {
+        if path.is_dir() {
+            input_files(&path, files)?;
+        } else if path
+            .extension()
+            .map(|e| e == "toml" || e == "rrscript")
+            .unwrap_or(false)
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn sorted_entries(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        entries.push(entry?.path());
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+fn load_cache(cache_dir: &Path) -> Cache {
+    let file = match File::open(cache_dir.join(CACHE_INDEX_FILE_NAME)) {
+        Ok(file) => file,
+        Err(_) => return Cache::default(),
+    };
+    match serde_cbor::from_reader::<Cache, _>(BufReader::new(file)) {
+        Ok(cache) if cache.version == env!("CARGO_PKG_VERSION") => cache,
+        _ => {
+            print_verbose(|| "Cache is discarded".to_owned());
+            Cache::default()
+        }
+    }
+}
+
+fn save_cache(cache_dir: &Path, cache: &Cache) -> Result<(), Error> {
+    let file = File::create(cache_dir.join(CACHE_INDEX_FILE_NAME))?;
+    serde_cbor::to_writer(&mut BufWriter::new(file), cache)?;
+    Ok(())
+}
+
+fn remove_unused_objects(cache_dir: &Path, cache: &Cache) -> Result<(), Error> {
+    let used: HashSet<String> = cache
+        .objects
+        .iter()
+        .flat_map(|(input, entry)| (0..entry.ids.len()).map(move |i| obj_file_name(input, i)))
+        .collect();
+    for path in sorted_entries(cache_dir)? {
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if name != CACHE_INDEX_FILE_NAME && !used.contains(&name) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Cached object file name for the i-th object generated from the input file
+fn obj_file_name(input: &Path, i: usize) -> String {
+    format!(
+        "{:016x}-{}",
+        hash_bytes(input.to_string_lossy().as_bytes()),
+        i
+    )
+}
+
+fn hash_file(path: &Path) -> Result<u64, Error> {
+    Ok(hash_bytes(&fs::read(path)?))
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = fnv::FnvHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "object_type = \"script\"\nid = \"base\"\nabstract = true\n\
+                        [script]\nscript = \"\"\"\n--- start\ngset(a, 1)\n\"\"\"\n";
+    const CHILD: &str = "id = \"child\"\ninherit = \"base\"\n";
+
+    /// Create a source directory with a pak "a" including two objects
+    fn setup(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("rusted-ruins-makepak-{}", name));
+        let _ = fs::remove_dir_all(&dir);
+        let src_dir = dir.join("src");
+        let out_dir = dir.join("out");
+        fs::create_dir_all(src_dir.join("a")).unwrap();
+        fs::write(src_dir.join("a/base.toml"), BASE).unwrap();
+        fs::write(src_dir.join("a/child.toml"), CHILD).unwrap();
+        fs::write(
+            src_dir.join("a/talk.rrscript"),
+            "talk\n--- start\ntalk(hello)\n",
+        )
+        .unwrap();
+        (src_dir, out_dir)
+    }
+
+    #[test]
+    fn reuse_unchanged() {
+        let (src_dir, out_dir) = setup("reuse");
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.rebuilt, vec!["child", "talk"]);
+        assert_eq!(summary.written_paks, vec![out_dir.join("a.pak")]);
+
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert!(summary.rebuilt.is_empty());
+        assert_eq!(summary.n_reused, 2);
+        assert_eq!(summary.n_unchanged_paks, 1);
+    }
+
+    #[test]
+    fn rebuild_changed_input_and_dependency() {
+        let (src_dir, out_dir) = setup("changed");
+        build(&src_dir, &out_dir).unwrap();
+
+        fs::write(src_dir.join("a/talk.rrscript"), "talk\n--- start\n").unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.rebuilt, vec!["talk"]);
+        assert_eq!(summary.written_paks.len(), 1);
+
+        // Changes of the base object are applied to the inheriting object
+        fs::write(src_dir.join("a/base.toml"), BASE.replace("1", "2")).unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.rebuilt, vec!["child"]);
+    }
+
+    #[test]
+    fn remove_pak_of_removed_dir() {
+        let (src_dir, out_dir) = setup("removed");
+        fs::create_dir_all(src_dir.join("b")).unwrap();
+        fs::write(src_dir.join("b/other.rrscript"), "other\n--- start\n").unwrap();
+        build(&src_dir, &out_dir).unwrap();
+        assert!(out_dir.join("b.pak").exists());
+
+        fs::remove_dir_all(src_dir.join("b")).unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.removed_paks, vec![out_dir.join("b.pak")]);
+        assert!(!out_dir.join("b.pak").exists());
+        assert!(out_dir.join("a.pak").exists());
+    }
+
+    #[test]
+    fn rebuild_changed_talk_text() {
+        let (src_dir, out_dir) = setup("talk-text");
+        let text_dir = src_dir.parent().unwrap().join("text");
+        fs::create_dir_all(text_dir.join("talk")).unwrap();
+        fs::write(text_dir.join("talk/a.txt"), "% hello\nHello\n").unwrap();
+        crate::talk_text::set_text_dir(&text_dir).unwrap();
+        build(&src_dir, &out_dir).unwrap();
+
+        // Unchanged ids do not affect objects
+        fs::write(text_dir.join("talk/a.txt"), "% hello\nHi\n").unwrap();
+        crate::talk_text::set_text_dir(&text_dir).unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert!(summary.rebuilt.is_empty());
+
+        fs::write(text_dir.join("talk/a.txt"), "% bye\nBye\n").unwrap();
+        crate::talk_text::set_text_dir(&text_dir).unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.rebuilt, vec!["child", "talk"]);
+    }
+
+    #[test]
+    fn fail_with_invalid_input() {
+        let (src_dir, out_dir) = setup("failed");
+        build(&src_dir, &out_dir).unwrap();
+        let pak = fs::read(out_dir.join("a.pak")).unwrap();
+
+        fs::write(src_dir.join("a/child.toml"), "id = \"child\"\n").unwrap();
+        assert!(build(&src_dir, &out_dir).is_err());
+        // The previous pak is kept
+        assert_eq!(fs::read(out_dir.join("a.pak")).unwrap(), pak);
+
+        fs::write(src_dir.join("a/child.toml"), CHILD).unwrap();
+        let summary = build(&src_dir, &out_dir).unwrap();
+        assert_eq!(summary.rebuilt, vec!["child"]);
+        assert_eq!(summary.written_paks, vec![out_dir.join("a.pak")]);
+    }
+
+    #[test]
+    fn compile_takes_dependencies() {
+        let (src_dir, out_dir) = setup("compile");
+        fs::create_dir_all(&out_dir).unwrap();
+        let files = [src_dir.join("a/base.toml"), src_dir.join("a/child.toml")];
+        let files: Vec<&str> = files.iter().map(|f| f.to_str().unwrap()).collect();
+        crate::compile::compile(
+            &files,
+            &out_dir.join("a.pak").to_string_lossy().into_owned(),
+        );
+        assert!(dir::take_dependencies().is_empty());
+    }
+}
@@ -1,6 +1,7 @@
 use common::script::{Expr, Operator, Value};
 use nom::types::CompleteStr;
 use nom::{digit1, multispace0};
+use std::str::FromStr;
 
 trait Join {
     fn join(self, op: Operator, e: Expr) -> Expr;
@@ -67,6 +68,13 @@ named!(integer<CompleteStr, Expr>,
     )
 );
 
+named!(string_literal<CompleteStr, Expr>,
+    do_parse!(
+        s: delimited!(char!('"'), take_until!("\""), char!('"')) >>
+        (Expr::Value(Value::String(s.to_string())))
+    )
+);
+
 named!(gvar<CompleteStr, Expr>,
     do_parse!(
         char!('$') >>
@@ -77,6 +85,24 @@ named!(gvar<CompleteStr, Expr>,
     )
 );
 
+named!(lvar<CompleteStr, Expr>,
+    do_parse!(
+        tag!("$l(") >>
+        var_name: id >>
+        char!(')') >>
+        (Expr::LVar(var_name))
+    )
+);
+
+named!(cvar<CompleteStr, Expr>,
+    do_parse!(
+        tag!("$c(") >>
+        var_name: id >>
+        char!(')') >>
+        (Expr::CVar(var_name))
+    )
+);
+
 named!(gvar_special<CompleteStr, Expr>,
     do_parse!(
         tag!("$?") >>
@@ -128,17 +154,40 @@ named!(has_item<CompleteStr, Expr>,
     )
 );
 
+named!(skill<CompleteStr, Expr>,
+    do_parse!(
+        tag!("skill") >>
+        multispace0 >>
+        kind: map_res!(delimited!(char!('('), ws!(symbol), char!(')')), FromStr::from_str) >>
+        (Expr::PlayerSkill(kind))
+    )
+);
+
+named!(attr<CompleteStr, Expr>,
+    do_parse!(
+        tag!("attr") >>
+        multispace0 >>
+        kind: map_res!(delimited!(char!('('), ws!(symbol), char!(')')), FromStr::from_str) >>
+        (Expr::PlayerAttr(kind))
+    )
+);
+
 named!(factor<CompleteStr, Expr>,
     ws!(alt_complete!(
         true_literal |
         false_literal |
         integer |
+        string_literal |
         gvar |
+        lvar |
+        cvar |
         gvar_special |
         is_gvar_empty |
         current_time |
         duration_hours |
         has_item |
+        skill |
+        attr |
         parens
     ))
 );
@@ -262,6 +311,7 @@ named!(pub expr<CompleteStr, Expr>,
 
 #[test]
 fn expr_test() {
+    use common::gamedata::{CharaAttrKind, SkillKind, WeaponKind};
     assert_eq!(
         expr(CompleteStr("true")),
         Ok((CompleteStr(""), Expr::Value(Value::Bool(true))))
@@ -282,6 +332,37 @@ fn expr_test() {
         expr(CompleteStr("is_gvar_empty(bb)")),
         Ok((CompleteStr(""), Expr::IsGVarEmpty("bb".to_owned())))
     );
+    assert_eq!(
+        expr(CompleteStr("$l(aa) + $c(bb)")),
+        Ok((
+            CompleteStr(""),
+            Expr::Term(vec![
+                (Operator::None, Expr::LVar("aa".to_owned())),
+                (Operator::Add, Expr::CVar("bb".to_owned())),
+            ])
+        ))
+    );
+    assert_eq!(
+        expr(CompleteStr("\"a b\"")),
+        Ok((
+            CompleteStr(""),
+            Expr::Value(Value::String("a b".to_owned()))
+        ))
+    );
+    assert_eq!(
+        expr(CompleteStr("skill(lockpicking) + skill(sword) + attr(str)")),
+        Ok((
+            CompleteStr(""),
+            Expr::Term(vec![
+                (Operator::None, Expr::PlayerSkill(SkillKind::Lockpicking)),
+                (
+                    Operator::Add,
+                    Expr::PlayerSkill(SkillKind::Weapon(WeaponKind::Sword))
+                ),
+                (Operator::Add, Expr::PlayerAttr(CharaAttrKind::Str)),
+            ])
+        ))
+    );
     let a = Expr::HasItem("box".to_owned());
     assert_eq!(expr(CompleteStr("has_item(box)")), Ok((CompleteStr(""), a)));
     assert_eq!(
@@ -13,6 +13,7 @@ pub fn build_img(input: ImgInput) -> Result<(Img, ImgData), Error> {
     } else {
         path.to_owned()
     };
+    crate::dir::add_dependency(&newpath);
 
     let imgdata = ImgData::load(&newpath)?;
     let w = input.w.unwrap_or(imgdata.dimensions.0);
@@ -1,7 +1,7 @@
 use super::img::build_img;
 use crate::error::*;
 use crate::tomlinput::*;
-use common::gamedata::defs::ElementArray;
+use common::gamedata::defs::{Element, ElementArray};
 use common::gamedata::item::*;
 use common::obj::*;
 
@@ -26,7 +26,9 @@ pub fn build_item_object(tomlinput: TomlInput) -> Result<ItemObject, Error> {
         }
         "weapon" => ItemKind::Weapon(get_optional_field!(item, weapon_kind)),
         "armor" => ItemKind::Armor(get_optional_field!(item, armor_kind)),
+        "ammo" => ItemKind::Ammo(get_optional_field!(item, ammo_kind)),
         "material" => ItemKind::Material,
+        "key" => ItemKind::Key,
         "special" => ItemKind::Special,
         _ => {
             bail!(PakCompileError::UnexpectedValue {
@@ -48,6 +50,7 @@ pub fn build_item_object(tomlinput: TomlInput) -> Result<ItemObject, Error> {
         gen_level: item.gen_level,
         dice_n: item.dice_n.unwrap_or(0),
         dice_x: item.dice_x.unwrap_or(0),
+        element: item.element.unwrap_or(Element::Physical),
         def: item.def.unwrap_or(ElementArray([0, 0, 0, 0, 0, 0])),
         eff: item.eff.unwrap_or(0),
         medical_effect: item.medical_effect.unwrap_or_default(),
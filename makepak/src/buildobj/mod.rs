@@ -1,6 +1,7 @@
 mod expr_parser;
 mod img;
 mod item;
+mod script_checker;
 mod script_parser;
 
 use self::img::*;
@@ -13,6 +14,71 @@ use common::gamedata::CharaBaseAttr;
 use common::obj::*;
 pub use script_parser::parse as script_parse;
 
+/// Parse and check script. Warnings are printed with the line in the source,
+/// and errors are returned together.
+/// line_offset is the line number of the script text beginning in the source file.
+pub fn script_parse_checked(
+    input: &str,
+    line_offset: usize,
+) -> Result<common::script::Script, Error> {
+    let (script, lines) = script_parser::parse_with_lines(input)?;
+    let file = crate::dir::get_src_file();
+    let mut errors = Vec::new();
+
+    for d in script_checker::check(&script, &lines) {
+        let e = PakCompileError::ScriptError {
+            file: file.clone(),
+            line: d.line + line_offset,
+            description: d.description,
+        };
+        if d.is_error {
+            errors.push(e.to_string());
+        } else {
+            eprintln!("warning: {}", e);
+        }
+    }
+
+    if !errors.is_empty() {
+        bail!(PakCompileError::ScriptCheckFailed {
+            n_errors: errors.len(),
+            description: errors.join("\n"),
+        });
+    }
+    Ok(script)
+}
+
+/// Get the line offset of the script from its position given by the toml parser.
+/// Returns 0 if the script is not written in the source (e.g. given by templates).
+fn script_line_offset(src: &str) -> usize {
+    #[derive(Deserialize)]
+    struct Src {
+        script: Option<ScriptSrc>,
+    }
+    #[derive(Deserialize)]
+    struct ScriptSrc {
+        script: Option<toml::Spanned<String>>,
+    }
+
+    let start = match toml::from_str::<Src>(src) {
+        Ok(Src {
+            script: Some(ScriptSrc {
+                script: Some(script),
+            }),
+        }) => script.start(),
+        _ => return 0,
+    };
+    let offset = src[..start].matches('\n').count();
+    // The line break right after the opening delimiter of multi-line strings is trimmed
+    let s = &src[start..];
+    if (s.starts_with("\"\"\"") || s.starts_with("'''"))
+        && (s[3..].starts_with('\n') || s[3..].starts_with("\r\n"))
+    {
+        offset + 1
+    } else {
+        offset
+    }
+}
+
 pub fn build_object(tomlinput: TomlInput) -> Result<Object, Error> {
     let object_type = tomlinput.object_type.clone();
     match object_type.as_ref() {
@@ -155,6 +221,7 @@ fn build_chara_template_object(tomlinput: TomlInput) -> Result<CharaTemplateObje
             .default_ai_kind
             .unwrap_or(gamedata::NpcAIKind::None),
         base_attr,
+        trigger_death: chara_dep_input.trigger_death,
     })
 }
 
@@ -175,16 +242,27 @@ fn build_region_gen_object(tomlinput: TomlInput) -> Result<RegionGenObject, Erro
         v.into_iter().map(|a| (a.id, a.pos)).collect()
     };
 
+    if rg.map_template_id.is_none() && rg.gen.is_none() {
+        bail!(PakCompileError::MissingField {
+            field_name: "region_gen.map_template_id".into()
+        });
+    }
+
     Ok(RegionGenObject {
         id: tomlinput.id,
-        map_template_id: rg.map_template_id,
-        towns: f(rg.towns),
+        map_template_id: rg.map_template_id.unwrap_or_default(),
+        towns: f(rg.towns.unwrap_or(vec![])),
+        gen: rg.gen,
+        dungeons: rg.dungeons.unwrap_or(vec![]),
+        connections: rg.connections.unwrap_or(vec![]),
+        danger_level: rg.danger_level.unwrap_or(0),
     })
 }
 
 fn build_script_object(tomlinput: TomlInput) -> Result<ScriptObject, Error> {
     let s = get_optional_field!(tomlinput, script);
-    let script = script_parse(&s.script)?;
+    let src = std::fs::read_to_string(crate::dir::get_src_file()).unwrap_or_default();
+    let script = script_parse_checked(&s.script, script_line_offset(&src))?;
 
     Ok(ScriptObject {
         id: tomlinput.id,
@@ -201,5 +279,20 @@ fn build_site_gen_object(tomlinput: TomlInput) -> Result<SiteGenObject, Error> {
         map_template_id: sg.map_template_id,
         unique_citizens: sg.unique_citizens.unwrap_or(vec![]),
         shops: sg.shops.unwrap_or(vec![]),
+        scheduled_scripts: sg.scheduled_scripts.unwrap_or(vec![]),
+        placement: sg.placement.unwrap_or_default(),
     })
 }
+
+#[test]
+fn script_line_offset_test() {
+    let src = "id = \"a\"\n[script]\nscript = \"\"\"\n\ntalk(a)\nend\n\"\"\"\n";
+    assert_eq!(script_line_offset(src), 3);
+    // The script begins on the line of the key
+    let src = "[script]\nscript = \"\"\"talk(a)\n\"\"\"\n";
+    assert_eq!(script_line_offset(src), 1);
+    // Not confused by the same text before the script
+    let src = "# talk(a)\n[script]\nscript = '''\r\ntalk(a)\r\n'''\r\n";
+    assert_eq!(script_line_offset(src), 3);
+    assert_eq!(script_line_offset("id = \"a\"\n"), 0);
+}
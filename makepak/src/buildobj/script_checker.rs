@@ -0,0 +1,429 @@
+//! Static checks for scripts.
+//! Infers types of variables and expressions, and reports errors found before execution.
+
+use super::script_parser::ScriptLines;
+use crate::talk_text::has_talk_text;
+use common::script::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Type {
+    Bool,
+    Int,
+    String,
+    Time,
+    /// Not inferred. Referenced unknown variables are converted to any type.
+    Unknown,
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Type::Bool => "bool",
+            Type::Int => "int",
+            Type::String => "string",
+            Type::Time => "time",
+            Type::Unknown => "unknown",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub is_error: bool,
+    pub description: String,
+}
+
+#[derive(Default)]
+struct Vars {
+    global: HashMap<String, Type>,
+    local: HashMap<String, Type>,
+    chara: HashMap<String, Type>,
+}
+
+impl Vars {
+    fn get(&self, var: &Var) -> Type {
+        let map = match var {
+            Var::Global(_) => &self.global,
+            Var::Local(_) => &self.local,
+            Var::Chara(_) => &self.chara,
+        };
+        map.get(var.name()).cloned().unwrap_or(Type::Unknown)
+    }
+
+    fn set(&mut self, var: &Var, t: Type) {
+        let map = match var {
+            Var::Global(_) => &mut self.global,
+            Var::Local(_) => &mut self.local,
+            Var::Chara(_) => &mut self.chara,
+        };
+        map.insert(var.name().to_owned(), t);
+    }
+}
+
+enum Var<'a> {
+    Global(&'a str),
+    Local(&'a str),
+    Chara(&'a str),
+}
+
+impl<'a> Var<'a> {
+    fn from_instruction(instruction: &'a Instruction) -> Option<(Var<'a>, &'a Expr)> {
+        match instruction {
+            Instruction::GSet(name, e) => Some((Var::Global(name), e)),
+            Instruction::LSet(name, e) => Some((Var::Local(name), e)),
+            Instruction::CSet(name, e) => Some((Var::Chara(name), e)),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Var::Global(name) | Var::Local(name) | Var::Chara(name) => name,
+        }
+    }
+}
+
+impl<'a> fmt::Display for Var<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Var::Global(name) => write!(f, "$({})", name),
+            Var::Local(name) => write!(f, "$l({})", name),
+            Var::Chara(name) => write!(f, "$c({})", name),
+        }
+    }
+}
+
+/// Check the script. Returned diagnostics are sorted by line.
+pub fn check(script: &Script, lines: &ScriptLines) -> Vec<Diagnostic> {
+    let mut sections: Vec<(&String, &Vec<Instruction>)> = script.iter().collect();
+    sections.sort_by_key(|(name, _)| (lines.section(name), name.as_str()));
+
+    let vars = infer_vars(&sections);
+    let mut diagnostics = Vec::new();
+    let mut error = |line: usize, description: String| {
+        diagnostics.push(Diagnostic {
+            line,
+            is_error: true,
+            description,
+        });
+    };
+
+    for (section, instructions) in &sections {
+        for (i, instruction) in instructions.iter().enumerate() {
+            let line = lines.instruction(section, i);
+            if let Err(e) = check_instruction(script, &vars, instruction) {
+                error(line, e);
+            }
+        }
+    }
+
+    let mut warning = |line: usize, description: String| {
+        diagnostics.push(Diagnostic {
+            line,
+            is_error: false,
+            description,
+        });
+    };
+
+    for (section, instructions) in &sections {
+        for (i, instruction) in instructions.iter().enumerate() {
+            if let Instruction::Talk(text_id, choices) = instruction {
+                let text_ids = std::iter::once(text_id).chain(choices.iter().map(|c| &c.text_id));
+                for text_id in text_ids {
+                    if has_talk_text(text_id) == Some(false) {
+                        warning(
+                            lines.instruction(section, i),
+                            format!("talk text \"{}\" is not translated", text_id),
+                        );
+                    }
+                }
+                // A talk without shown choices continues to the next instruction
+                if !choices.is_empty() && choices.iter().all(|c| c.cond.is_some()) {
+                    warning(
+                        lines.instruction(section, i),
+                        "all choices of talk can be hidden by their conditions".to_owned(),
+                    );
+                }
+            }
+        }
+    }
+
+    if script.has_section("start") {
+        let reachable = reachable_sections(script, "start");
+        for (section, _) in &sections {
+            // Generated sections of control flow statements are not reported
+            if !reachable.contains(section.as_str()) && !section.contains('#') {
+                warning(
+                    lines.section(section),
+                    format!("section \"{}\" is unreachable from start", section),
+                );
+            }
+        }
+    } else {
+        warning(1, "section \"start\" is missing".to_owned());
+    }
+
+    diagnostics.sort_by_key(|d| d.line);
+    diagnostics
+}
+
+/// Infer variable types from assignments.
+/// Repeated because assigned expressions may refer to other variables.
+fn infer_vars(sections: &[(&String, &Vec<Instruction>)]) -> Vars {
+    let mut vars = Vars::default();
+    loop {
+        let mut changed = false;
+        for (_, instructions) in sections {
+            for (var, e) in instructions.iter().filter_map(Var::from_instruction) {
+                if vars.get(&var) != Type::Unknown {
+                    continue;
+                }
+                match expr_type(&vars, e) {
+                    Ok(Type::Unknown) | Err(_) => (),
+                    Ok(t) => {
+                        vars.set(&var, t);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if !changed {
+            return vars;
+        }
+    }
+}
+
+fn check_instruction(
+    script: &Script,
+    vars: &Vars,
+    instruction: &Instruction,
+) -> Result<(), String> {
+    let check_dest = |section: &str| -> Result<(), String> {
+        if section == QUIT_SECTION || section == CONTINUE_SECTION || script.has_section(section) {
+            Ok(())
+        } else {
+            Err(format!("section \"{}\" is not defined", section))
+        }
+    };
+
+    match instruction {
+        Instruction::Jump(section) => check_dest(section)?,
+        Instruction::JumpIf(section, e) => {
+            check_dest(section)?;
+            match expr_type(vars, e)? {
+                Type::Bool | Type::Unknown => (),
+                t => return Err(format!("condition of jump_if must be bool, found {}", t)),
+            }
+        }
+        Instruction::Talk(_, choices) => {
+            for choice in choices {
+                check_dest(&choice.section)?;
+                if let Some(cond) = choice.cond.as_ref() {
+                    match expr_type(vars, cond)? {
+                        Type::Bool | Type::Unknown => (),
+                        t => return Err(format!("condition of choice must be bool, found {}", t)),
+                    }
+                }
+            }
+        }
+        Instruction::Call(section) => {
+            if !script.has_section(section) {
+                return Err(format!("section \"{}\" is not defined", section));
+            }
+        }
+        Instruction::GSet(..) | Instruction::LSet(..) | Instruction::CSet(..) => {
+            let (var, e) = Var::from_instruction(instruction).unwrap();
+            let t = expr_type(vars, e)?;
+            let var_type = vars.get(&var);
+            if t != Type::Unknown && t != var_type {
+                return Err(format!(
+                    "{} is inferred as {}, but {} is assigned",
+                    var, var_type, t
+                ));
+            }
+        }
+        Instruction::ReceiveMoney(e) => match expr_type(vars, e)? {
+            Type::Int | Type::Unknown => (),
+            t => return Err(format!("receive_money requires int, found {}", t)),
+        },
+        _ => (),
+    }
+    Ok(())
+}
+
+fn expr_type(vars: &Vars, expr: &Expr) -> Result<Type, String> {
+    Ok(match expr {
+        Expr::Value(value) => match value {
+            Value::Bool(_) => Type::Bool,
+            Value::Int(_) => Type::Int,
+            Value::String(_) => Type::String,
+            Value::Time(_) => Type::Time,
+            Value::RefUnknownVar | Value::Error(_) => Type::Unknown,
+        },
+        Expr::Term(v) => {
+            // Evaluated from left to right as the script engine does
+            let mut a = expr_type(vars, &v[0].1)?;
+            for (o, b) in v.iter().skip(1) {
+                let b = expr_type(vars, b)?;
+                a = binary_operation_type(*o, a, b)?;
+            }
+            a
+        }
+        Expr::GVar(name) => vars.get(&Var::Global(name)),
+        Expr::LVar(name) => vars.get(&Var::Local(name)),
+        Expr::CVar(name) => vars.get(&Var::Chara(name)),
+        Expr::IsGVarEmpty(_) => Type::Bool,
+        Expr::CurrentTime => Type::Time,
+        Expr::DurationHour(a, b) => {
+            for e in &[a, b] {
+                match expr_type(vars, e)? {
+                    Type::Time | Type::Unknown => (),
+                    t => return Err(format!("duration_hours requires time, found {}", t)),
+                }
+            }
+            Type::Int
+        }
+        Expr::HasItem(_) => Type::Bool,
+        Expr::PlayerSkill(_) | Expr::PlayerAttr(_) => Type::Int,
+    })
+}
+
+fn binary_operation_type(o: Operator, a: Type, b: Type) -> Result<Type, String> {
+    let t = match (a, b) {
+        (Type::Unknown, t) | (t, Type::Unknown) => t,
+        (a, b) if a == b => a,
+        _ => {
+            return Err(format!(
+                "mismatched types {} and {} for operator {}",
+                a,
+                b,
+                operator_str(o)
+            ));
+        }
+    };
+    let invalid = || {
+        Err(format!(
+            "operator {} cannot be applied to {}",
+            operator_str(o),
+            t
+        ))
+    };
+
+    match o {
+        Operator::Or | Operator::And => match t {
+            Type::Bool | Type::Unknown => Ok(Type::Bool),
+            _ => invalid(),
+        },
+        Operator::Eq
+        | Operator::NotEq
+        | Operator::Less
+        | Operator::LessEq
+        | Operator::Greater
+        | Operator::GreaterEq => match t {
+            Type::Time => invalid(),
+            _ => Ok(Type::Bool),
+        },
+        Operator::Add => match t {
+            Type::Int | Type::String | Type::Unknown => Ok(t),
+            _ => invalid(),
+        },
+        Operator::Sub | Operator::Mul | Operator::Div => match t {
+            Type::Int | Type::Unknown => Ok(Type::Int),
+            _ => invalid(),
+        },
+        Operator::None => invalid(),
+    }
+}
+
+fn operator_str(o: Operator) -> &'static str {
+    match o {
+        Operator::None => "",
+        Operator::Or => "||",
+        Operator::And => "&&",
+        Operator::Eq => "==",
+        Operator::NotEq => "!=",
+        Operator::Less => "<",
+        Operator::LessEq => "<=",
+        Operator::Greater => ">",
+        Operator::GreaterEq => ">=",
+        Operator::Add => "+",
+        Operator::Sub => "-",
+        Operator::Mul => "*",
+        Operator::Div => "/",
+    }
+}
+
+fn reachable_sections<'a>(script: &'a Script, start: &'a str) -> HashSet<&'a str> {
+    let mut reachable = HashSet::new();
+    let mut stack = vec![start];
+
+    while let Some(section) = stack.pop() {
+        if !script.has_section(section) || !reachable.insert(section) {
+            continue;
+        }
+        for instruction in script.section(section) {
+            match instruction {
+                Instruction::Jump(dest)
+                | Instruction::JumpIf(dest, _)
+                | Instruction::Call(dest) => {
+                    stack.push(dest);
+                }
+                Instruction::Talk(_, choices) => {
+                    stack.extend(choices.iter().map(|c| c.section.as_str()));
+                }
+                _ => (),
+            }
+        }
+    }
+    reachable
+}
+
+#[test]
+fn check_test() {
+    use super::script_parser::parse_with_lines;
+
+    let check_str = |s: &str| {
+        let (script, lines) = parse_with_lines(s).unwrap();
+        check(&script, &lines)
+            .into_iter()
+            .map(|d| (d.line, d.is_error))
+            .collect::<Vec<_>>()
+    };
+
+    let script = r#"--- start
+gset(a, 1)
+gset(b, $(a) + 2)
+if($(b) > 2 && has_item(key))
+  talk(text, [(yes, next), (no, quit)])
+end
+--- next
+receive_money($(b))
+"#;
+    assert_eq!(check_str(script), vec![]);
+
+    let script = r#"--- start
+gset(a, 1)
+jump_if(next, $(a) + 1)
+gset(a, true)
+lset(s, "a" + 1)
+jump(undefined)
+--- next
+--- unused
+"#;
+    assert_eq!(
+        check_str(script),
+        vec![(3, true), (4, true), (5, true), (6, true), (8, false)]
+    );
+
+    let script = r#"--- start
+talk(text, [(yes, quit, $(a)), (no, quit)])
+talk(text, [(yes, quit, $(a)), (no, quit, $(a) == false)])
+"#;
+    assert_eq!(check_str(script), vec![(3, false)]);
+
+    assert_eq!(check_str("--- other\n"), vec![(1, false)]);
+}
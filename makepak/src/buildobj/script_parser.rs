@@ -1,6 +1,7 @@
+use array2d::Vec2d;
 use common::hashmap::HashMap;
 use nom::types::CompleteStr;
-use nom::{line_ending, space};
+use nom::{digit1, line_ending, multispace0, space, IResult};
 use std::str::FromStr;
 
 use super::expr_parser::*;
@@ -131,14 +132,26 @@ named!(talk_instruction<CompleteStr, Instruction>,
     )
 );
 
+/// Choice of talk. The optional third element is the condition to show it.
+named!(talk_choice<CompleteStr, TalkChoice>,
+    do_parse!(
+        char!('(') >>
+        text_id: ws!(id) >>
+        char!(',') >>
+        section: ws!(id) >>
+        cond: opt!(preceded!(char!(','), ws!(expr))) >>
+        char!(')') >>
+        (TalkChoice { text_id, section, cond })
+    )
+);
+
 named!(talk_instruction_with_choices<CompleteStr, Instruction>,
     do_parse!(
         ws!(tag!("talk")) >>
         char!('(') >>
         text_id: ws!(id) >>
         char!(',') >>
-        choices: array!(delimited!(
-            char!('('), separated_pair!(ws!(id), char!(','), ws!(id)), char!(')') )) >>
+        choices: array!(call!(talk_choice)) >>
         char!(')') >>
         end_line >>
         (Instruction::Talk(text_id, choices))
@@ -158,6 +171,75 @@ named!(gset_instruction<CompleteStr, Instruction>,
     )
 );
 
+named!(lset_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("lset")) >>
+        char!('(') >>
+        var_name: ws!(id) >>
+        char!(',') >>
+        value: ws!(expr) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::LSet(var_name, value))
+    )
+);
+
+named!(cset_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("cset")) >>
+        char!('(') >>
+        var_name: ws!(id) >>
+        char!(',') >>
+        value: ws!(expr) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::CSet(var_name, value))
+    )
+);
+
+named!(call_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("call")) >>
+        s: delimited!(tag!("("), ws!(id), tag!(")")) >>
+        end_line >>
+        (Instruction::Call(s))
+    )
+);
+
+named!(return_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        multispace0 >>
+        tag!("return") >>
+        end_line >>
+        (Instruction::Return)
+    )
+);
+
+#[test]
+fn call_instruction_test() {
+    assert_eq!(
+        call_instruction(CompleteStr("call(sub)\n")),
+        Ok((CompleteStr(""), Instruction::Call("sub".to_owned())))
+    );
+    assert_eq!(
+        return_instruction(CompleteStr("  return\n")),
+        Ok((CompleteStr(""), Instruction::Return))
+    );
+    assert_eq!(
+        lset_instruction(CompleteStr("lset(a, $l(a) + 1)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::LSet(
+                "a".to_owned(),
+                Expr::Term(vec![
+                    (Operator::None, Expr::LVar("a".to_owned())),
+                    (Operator::Add, Expr::Value(Value::Int(1))),
+                ])
+            )
+        ))
+    );
+}
+
 named!(receive_money_instruction<CompleteStr, Instruction>,
     do_parse!(
         ws!(tag!("receive_money")) >>
@@ -176,17 +258,209 @@ named!(remove_item_instruction<CompleteStr, Instruction>,
     )
 );
 
+named!(uint<CompleteStr, u32>,
+    map_res!(digit1, |s: CompleteStr| s.0.parse())
+);
+
+named!(int<CompleteStr, i32>,
+    map_res!(recognize!(pair!(opt!(char!('-')), digit1)), |s: CompleteStr| s.0.parse())
+);
+
+named!(give_item_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("give_item")) >>
+        char!('(') >>
+        item_id: ws!(id) >>
+        char!(',') >>
+        n: ws!(uint) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::GiveItem(item_id, n))
+    )
+);
+
+named!(spawn_chara_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("spawn_chara")) >>
+        char!('(') >>
+        chara_id: ws!(id) >>
+        char!(',') >>
+        x: ws!(int) >>
+        char!(',') >>
+        y: ws!(int) >>
+        char!(',') >>
+        rel: map_res!(ws!(symbol), FromStr::from_str) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::SpawnChara(chara_id, Vec2d(x, y), rel))
+    )
+);
+
+named!(set_relationship_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("set_relationship")) >>
+        rel: map_res!(delimited!(char!('('), ws!(symbol), char!(')')), FromStr::from_str) >>
+        end_line >>
+        (Instruction::SetRelationship(rel))
+    )
+);
+
+named!(map_dest<CompleteStr, MapDest>,
+    alt!(
+        do_parse!(
+            tag!("floor") >>
+            floor: delimited!(char!('('), ws!(uint), char!(')')) >>
+            (MapDest::Floor(floor))
+        ) |
+        do_parse!(
+            tag!("town") >>
+            char!('(') >>
+            town_id: ws!(id) >>
+            char!(',') >>
+            floor: ws!(uint) >>
+            char!(')') >>
+            (MapDest::Town(town_id, floor))
+        ) |
+        do_parse!(
+            tag!("region") >>
+            (MapDest::Region)
+        )
+    )
+);
+
+named!(teleport_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("teleport")) >>
+        char!('(') >>
+        dest: ws!(map_dest) >>
+        char!(',') >>
+        x: ws!(int) >>
+        char!(',') >>
+        y: ws!(int) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::Teleport(dest, Vec2d(x, y)))
+    )
+);
+
+named!(script_quest<CompleteStr, ScriptQuest>,
+    do_parse!(
+        tag!("slay_monsters") >>
+        char!('(') >>
+        chara_id: ws!(id) >>
+        char!(',') >>
+        goal: ws!(uint) >>
+        char!(')') >>
+        (ScriptQuest::SlayMonsters(chara_id, goal))
+    )
+);
+
+named!(start_quest_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("start_quest")) >>
+        char!('(') >>
+        quest: ws!(script_quest) >>
+        char!(',') >>
+        money: ws!(uint) >>
+        char!(',') >>
+        exp: ws!(uint) >>
+        char!(')') >>
+        end_line >>
+        (Instruction::StartQuest(quest, money as i64, exp))
+    )
+);
+
+named!(play_sound_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("play_sound")) >>
+        name: delimited!(char!('('), ws!(id), char!(')')) >>
+        end_line >>
+        (Instruction::PlaySound(name))
+    )
+);
+
+named!(play_music_instruction<CompleteStr, Instruction>,
+    do_parse!(
+        ws!(tag!("play_music")) >>
+        name: delimited!(char!('('), ws!(id), char!(')')) >>
+        end_line >>
+        (Instruction::PlayMusic(name))
+    )
+);
+
+#[test]
+fn world_instruction_test() {
+    use common::gamedata::Relationship;
+    assert_eq!(
+        give_item_instruction(CompleteStr("give_item(healing-potion, 3)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::GiveItem("healing-potion".to_owned(), 3)
+        ))
+    );
+    assert_eq!(
+        spawn_chara_instruction(CompleteStr("spawn_chara(rat, 3, -4, hostile)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::SpawnChara("rat".to_owned(), Vec2d(3, -4), Relationship::HOSTILE)
+        ))
+    );
+    assert_eq!(
+        set_relationship_instruction(CompleteStr("set_relationship(ally)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::SetRelationship(Relationship::ALLY)
+        ))
+    );
+    assert_eq!(
+        teleport_instruction(CompleteStr("teleport(town(town-a, 1), 5, 6)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::Teleport(MapDest::Town("town-a".to_owned(), 1), Vec2d(5, 6))
+        ))
+    );
+    assert_eq!(
+        teleport_instruction(CompleteStr("teleport(region, 5, 6)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::Teleport(MapDest::Region, Vec2d(5, 6))
+        ))
+    );
+    assert_eq!(
+        start_quest_instruction(CompleteStr("start_quest(slay_monsters(rat, 5), 100, 20)\n")),
+        Ok((
+            CompleteStr(""),
+            Instruction::StartQuest(ScriptQuest::SlayMonsters("rat".to_owned(), 5), 100, 20)
+        ))
+    );
+}
+
 #[test]
 fn talk_instruction_test() {
+    let choice = |text_id: &str, section: &str, cond: Option<Expr>| TalkChoice {
+        text_id: text_id.to_owned(),
+        section: section.to_owned(),
+        cond,
+    };
+    let result = Instruction::Talk(
+        "text-id".to_owned(),
+        vec![choice("a", "b", None), choice("c", "d", None)],
+    );
+    assert_eq!(
+        talk_instruction_with_choices(CompleteStr("talk(text-id, [(a, b), (c, d)])\n")),
+        Ok((CompleteStr(""), result))
+    );
     let result = Instruction::Talk(
         "text-id".to_owned(),
         vec![
-            ("a".to_owned(), "b".to_owned()),
-            ("c".to_owned(), "d".to_owned()),
+            choice("a", "b", Some(Expr::HasItem("key".to_owned()))),
+            choice("c", "d", None),
         ],
     );
     assert_eq!(
-        talk_instruction_with_choices(CompleteStr("talk(text-id, [(a, b), (c, d)])\n")),
+        talk_instruction_with_choices(CompleteStr(
+            "talk(text-id, [(a, b, has_item(key)), (c, d)])\n"
+        )),
         Ok((CompleteStr(""), result))
     );
 }
@@ -198,37 +472,303 @@ named!(instruction<CompleteStr, Instruction>,
         talk_instruction_with_choices |
         talk_instruction |
         gset_instruction |
+        lset_instruction |
+        cset_instruction |
+        call_instruction |
+        return_instruction |
         receive_money_instruction |
         remove_item_instruction |
+        give_item_instruction |
+        spawn_chara_instruction |
+        set_relationship_instruction |
+        teleport_instruction |
+        start_quest_instruction |
+        play_sound_instruction |
+        play_music_instruction |
         special_instruction
     )
 );
 
-named!(section<CompleteStr, (String, Vec<Instruction>)>,
+/// Statements in a section. Control flow statements are lowered to instructions.
+#[derive(Clone, PartialEq, Debug)]
+enum Statement {
+    Instruction(Instruction),
+    /// If statement (condition, then block, else block)
+    If(Expr, Vec<Located<Statement>>, Vec<Located<Statement>>),
+    /// Loop statement. Repeated while the condition is true if it is given.
+    Loop(Option<Expr>, Vec<Located<Statement>>),
+    Break,
+}
+
+/// Item with its position, the length of the remaining input.
+/// Converted to line numbers after parsing.
+type Located<T> = (usize, T);
+
+/// Get the position of the next non-whitespace character without consuming input
+fn position(input: CompleteStr) -> IResult<CompleteStr, usize> {
+    let pos = input.0.trim_start().len();
+    Ok((input, pos))
+}
+
+named!(else_line<CompleteStr, ()>,
+    do_parse!(
+        multispace0 >>
+        tag!("else") >>
+        end_line >>
+        (())
+    )
+);
+
+named!(end_block_line<CompleteStr, ()>,
+    do_parse!(
+        multispace0 >>
+        tag!("end") >>
+        end_line >>
+        (())
+    )
+);
+
+named!(if_statement<CompleteStr, Statement>,
+    do_parse!(
+        ws!(tag!("if")) >>
+        cond: delimited!(char!('('), ws!(expr), char!(')')) >>
+        end_line >>
+        then_block: many0!(located_statement) >>
+        else_block: opt!(preceded!(else_line, many0!(located_statement))) >>
+        end_block_line >>
+        (Statement::If(cond, then_block, else_block.unwrap_or_default()))
+    )
+);
+
+named!(loop_statement<CompleteStr, Statement>,
+    do_parse!(
+        ws!(tag!("loop")) >>
+        cond: opt!(delimited!(char!('('), ws!(expr), char!(')'))) >>
+        end_line >>
+        body: many0!(located_statement) >>
+        end_block_line >>
+        (Statement::Loop(cond, body))
+    )
+);
+
+named!(break_statement<CompleteStr, Statement>,
     do_parse!(
-        section: section_start >>
-        instructions: many0!(instruction) >>
-        (section.to_string(), instructions)
+        multispace0 >>
+        tag!("break") >>
+        end_line >>
+        (Statement::Break)
+    )
+);
+
+named!(statement<CompleteStr, Statement>,
+    alt!(
+        if_statement |
+        loop_statement |
+        break_statement |
+        map!(instruction, Statement::Instruction)
     )
 );
 
-named!(sections<CompleteStr, HashMap<String, Vec<Instruction>>>,
+named!(located_statement<CompleteStr, Located<Statement>>,
+    pair!(position, statement)
+);
+
+/// Instructions of a section with their positions
+struct LoweredSection {
+    name: String,
+    pos: usize,
+    instructions: Vec<Instruction>,
+    positions: Vec<usize>,
+}
+
+impl LoweredSection {
+    fn new(name: String, pos: usize) -> LoweredSection {
+        LoweredSection {
+            name,
+            pos,
+            instructions: Vec::new(),
+            positions: Vec::new(),
+        }
+    }
+}
+
+/// Lowers statements of a section to instructions.
+/// Blocks of control flow statements are put into generated sections named "<section>#<n>".
+struct Lowering {
+    section: String,
+    n: u32,
+    /// Position of the statement being lowered
+    pos: usize,
+    current: LoweredSection,
+    sections: Vec<LoweredSection>,
+}
+
+impl Lowering {
+    fn new(section: String, pos: usize) -> Lowering {
+        Lowering {
+            current: LoweredSection::new(section.clone(), pos),
+            section,
+            n: 0,
+            pos,
+            sections: Vec::new(),
+        }
+    }
+
+    fn new_label(&mut self) -> String {
+        self.n += 1;
+        format!("{}#{}", self.section, self.n)
+    }
+
+    fn push(&mut self, instruction: Instruction) {
+        self.current.instructions.push(instruction);
+        self.current.positions.push(self.pos);
+    }
+
+    /// Finish the current section and start the given section
+    fn switch_to(&mut self, label: String) {
+        let finished = std::mem::replace(&mut self.current, LoweredSection::new(label, self.pos));
+        self.sections.push(finished);
+    }
+
+    fn lower(
+        &mut self,
+        statements: Vec<Located<Statement>>,
+        loop_end: Option<&str>,
+    ) -> Result<(), String> {
+        for (pos, statement) in statements {
+            self.pos = pos;
+            match statement {
+                Statement::Instruction(instruction) => {
+                    self.push(instruction);
+                }
+                Statement::If(cond, then_block, else_block) => {
+                    let then_label = self.new_label();
+                    let after_label = self.new_label();
+                    self.push(Instruction::JumpIf(then_label.clone(), cond));
+                    self.lower(else_block, loop_end)?;
+                    self.pos = pos;
+                    self.push(Instruction::Jump(after_label.clone()));
+                    self.switch_to(then_label);
+                    self.lower(then_block, loop_end)?;
+                    self.pos = pos;
+                    self.push(Instruction::Jump(after_label.clone()));
+                    self.switch_to(after_label);
+                }
+                Statement::Loop(cond, body) => {
+                    let head_label = self.new_label();
+                    let after_label = self.new_label();
+                    self.push(Instruction::Jump(head_label.clone()));
+                    self.switch_to(head_label.clone());
+                    if let Some(cond) = cond {
+                        let body_label = self.new_label();
+                        self.push(Instruction::JumpIf(body_label.clone(), cond));
+                        self.push(Instruction::Jump(after_label.clone()));
+                        self.switch_to(body_label);
+                    }
+                    self.lower(body, Some(&after_label))?;
+                    self.pos = pos;
+                    self.push(Instruction::Jump(head_label));
+                    self.switch_to(after_label);
+                }
+                Statement::Break => {
+                    if let Some(loop_end) = loop_end {
+                        self.push(Instruction::Jump(loop_end.to_owned()));
+                    } else {
+                        return Err(format!("break outside of loop in section {}", self.section));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Vec<LoweredSection> {
+        self.sections.push(self.current);
+        self.sections
+    }
+}
+
+fn lower_section(
+    (section, pos, statements): (String, usize, Vec<Located<Statement>>),
+) -> Result<Vec<LoweredSection>, String> {
+    let mut lowering = Lowering::new(section, pos);
+    lowering.lower(statements, None)?;
+    Ok(lowering.finish())
+}
+
+named!(section<CompleteStr, Vec<LoweredSection>>,
+    map_res!(
+        do_parse!(
+            pos: position >>
+            section: section_start >>
+            statements: many0!(located_statement) >>
+            (section, pos, statements)
+        ),
+        lower_section
+    )
+);
+
+named!(sections<CompleteStr, Vec<LoweredSection>>,
     exact!(fold_many0!(
         section,
-        HashMap::default(),
-        | mut s: HashMap<String, Vec<Instruction>>, section: (String, Vec<Instruction>) | {
-            s.insert(section.0, section.1);
+        Vec::new(),
+        | mut s: Vec<LoweredSection>, sections: Vec<LoweredSection> | {
+            s.extend(sections);
             s
         }))
 );
 
+/// Line numbers of sections and instructions in the source
+#[derive(Clone, Default, Debug)]
+pub struct ScriptLines(HashMap<String, (usize, Vec<usize>)>);
+
+impl ScriptLines {
+    /// Line of the section header. Generated sections have the line of their statement.
+    pub fn section(&self, section: &str) -> usize {
+        self.0.get(section).map(|a| a.0).unwrap_or(0)
+    }
+
+    pub fn instruction(&self, section: &str, i: usize) -> usize {
+        self.0
+            .get(section)
+            .and_then(|a| a.1.get(i).cloned())
+            .unwrap_or_else(|| self.section(section))
+    }
+}
+
 pub fn parse(input: &str) -> Result<Script, PakCompileError> {
-    match sections(CompleteStr(input)) {
-        Ok(o) => Ok(Script::from_map(o.1)),
-        Err(e) => Err(PakCompileError::ScriptParseError {
-            description: e.to_string(),
-        }),
+    parse_with_lines(input).map(|(script, _)| script)
+}
+
+/// Parse script and get line numbers of instructions
+pub fn parse_with_lines(input: &str) -> Result<(Script, ScriptLines), PakCompileError> {
+    let lowered = match sections(CompleteStr(input)) {
+        Ok(o) => o.1,
+        Err(e) => {
+            return Err(PakCompileError::ScriptParseError {
+                description: e.to_string(),
+            });
+        }
+    };
+
+    let newlines: Vec<usize> = input.match_indices('\n').map(|(i, _)| i).collect();
+    let line = |pos: usize| -> usize {
+        let offset = input.len() - pos;
+        match newlines.binary_search(&offset) {
+            Ok(i) | Err(i) => i + 1,
+        }
+    };
+
+    let mut map = HashMap::default();
+    let mut lines = ScriptLines::default();
+    for s in lowered {
+        let instruction_lines = s.positions.iter().map(|pos| line(*pos)).collect();
+        lines
+            .0
+            .insert(s.name.clone(), (line(s.pos), instruction_lines));
+        map.insert(s.name, s.instructions);
     }
+    Ok((Script::from_map(map), lines))
 }
 
 #[test]
@@ -256,11 +796,90 @@ talk(textid1,
         vec![Instruction::Talk(
             "textid1".to_owned(),
             vec![
-                ("aaa".to_owned(), "bbb".to_owned()),
-                ("ccc".to_owned(), "ddd".to_owned()),
+                TalkChoice {
+                    text_id: "aaa".to_owned(),
+                    section: "bbb".to_owned(),
+                    cond: None,
+                },
+                TalkChoice {
+                    text_id: "ccc".to_owned(),
+                    section: "ddd".to_owned(),
+                    cond: None,
+                },
             ],
         )],
     );
 
-    assert_eq!(sections(CompleteStr(script)), Ok((CompleteStr(""), result)))
+    assert_eq!(parse(script).unwrap(), Script::from_map(result));
+}
+
+#[test]
+fn control_flow_test() {
+    let script = r#"--- start
+lset(n, 0)
+loop($l(n) < 3)
+  lset(n, $l(n) + 1)
+  if($l(n) == 2)
+    talk(two)
+  else
+    break
+  end
+end
+talk(end)
+"#;
+    let mut result = HashMap::default();
+    let n = Expr::LVar("n".to_owned());
+    let int = |i| Expr::Value(Value::Int(i));
+
+    result.insert(
+        "start".to_owned(),
+        vec![
+            Instruction::LSet("n".to_owned(), int(0)),
+            Instruction::Jump("start#1".to_owned()),
+        ],
+    );
+    result.insert(
+        "start#1".to_owned(),
+        vec![
+            Instruction::JumpIf(
+                "start#3".to_owned(),
+                Expr::Term(vec![(Operator::None, n.clone()), (Operator::Less, int(3))]),
+            ),
+            Instruction::Jump("start#2".to_owned()),
+        ],
+    );
+    result.insert(
+        "start#3".to_owned(),
+        vec![
+            Instruction::LSet(
+                "n".to_owned(),
+                Expr::Term(vec![(Operator::None, n.clone()), (Operator::Add, int(1))]),
+            ),
+            Instruction::JumpIf(
+                "start#4".to_owned(),
+                Expr::Term(vec![(Operator::None, n.clone()), (Operator::Eq, int(2))]),
+            ),
+            Instruction::Jump("start#2".to_owned()),
+            Instruction::Jump("start#5".to_owned()),
+        ],
+    );
+    result.insert(
+        "start#4".to_owned(),
+        vec![
+            Instruction::Talk("two".to_owned(), vec![]),
+            Instruction::Jump("start#5".to_owned()),
+        ],
+    );
+    result.insert(
+        "start#5".to_owned(),
+        vec![Instruction::Jump("start#1".to_owned())],
+    );
+    result.insert(
+        "start#2".to_owned(),
+        vec![Instruction::Talk("end".to_owned(), vec![])],
+    );
+
+    assert_eq!(parse(script).unwrap(), Script::from_map(result));
+
+    assert!(parse("--- start\nbreak\n").is_err());
 }
@@ -1,6 +1,7 @@
 use crate::dir;
 use crate::error::*;
 use crate::rrscript::read_rrscript;
+use crate::template;
 use crate::verbose::print_verbose;
 use common::obj::Object;
 use common::pakutil::write_object;
@@ -17,37 +18,53 @@ pub fn compile(files: &[&str], output_file: &String) {
     let out = File::create(output_file).unwrap();
     let mut builder = tar::Builder::new(out);
 
+    template::load_bases(files);
+
     for f in files {
         let f = Path::new(f);
-        if f.is_relative() {
-            dir::set_src_dir(f.parent());
-        } else {
-            dir::set_src_dir(None);
-        }
-
-        let read_result = if Some(true) == f.extension().map(|e| e == "rrscript") {
-            read_rrscript(f)
-        } else {
-            read_toml(f)
-        };
-
-        let obj = match read_result {
+        let objs = read_input(f);
+        // Dependencies are used only in incremental builds
+        dir::take_dependencies();
+        let objs = match objs {
             Ok(o) => o,
             Err(echain) => {
-                eprintln!("Cannot process \"{}\"", f.to_string_lossy());
-                for e in echain.iter_chain() {
-                    eprintln!("{}", e);
-                }
+                print_error(f, &echain);
                 continue;
             }
         };
-        let v = write_to_vec(&obj).unwrap();
-        write_data_to_tar(&mut builder, &v, &obj.get_id());
+        for obj in &objs {
+            let v = write_to_vec(obj).unwrap();
+            write_data_to_tar(&mut builder, &v, &obj.get_id());
+        }
     }
     builder.finish().unwrap();
 }
 
-fn read_toml<P: AsRef<Path>>(path: P) -> Result<Object, Error> {
+/// Read one input file, toml or rrscript.
+/// A toml file can generate multiple objects or no object by templates.
+pub(crate) fn read_input(f: &Path) -> Result<Vec<Object>, Error> {
+    dir::set_src_file(f);
+    if f.is_relative() {
+        dir::set_src_dir(f.parent());
+    } else {
+        dir::set_src_dir(None);
+    }
+
+    if Some(true) == f.extension().map(|e| e == "rrscript") {
+        Ok(vec![read_rrscript(f)?])
+    } else {
+        read_toml(f)
+    }
+}
+
+pub(crate) fn print_error(f: &Path, echain: &Error) {
+    eprintln!("Cannot process \"{}\"", f.to_string_lossy());
+    for e in echain.iter_chain() {
+        eprintln!("{}", e);
+    }
+}
+
+fn read_toml<P: AsRef<Path>>(path: P) -> Result<Vec<Object>, Error> {
     let s = {
         let mut f = File::open(path.as_ref())?;
         let mut s = String::new();
@@ -55,16 +72,20 @@ fn read_toml<P: AsRef<Path>>(path: P) -> Result<Object, Error> {
         s
     };
 
-    let tomlinput: TomlInput = from_str(&s)?;
-
+    let value: toml::Value = from_str(&s)?;
     print_verbose(|| format!("Processing \"{:?}\"", path.as_ref()));
-    print_verbose(|| format!("{:?}", tomlinput));
-    let object = build_object(tomlinput)?;
 
-    Ok(object)
+    let mut objects = Vec::new();
+    for value in template::expand(value)? {
+        let tomlinput: TomlInput = value.try_into()?;
+        print_verbose(|| format!("{:?}", tomlinput));
+        objects.push(build_object(tomlinput)?);
+    }
+
+    Ok(objects)
 }
 
-fn write_to_vec(obj: &Object) -> Result<Vec<u8>, Error> {
+pub(crate) fn write_to_vec(obj: &Object) -> Result<Vec<u8>, Error> {
     let mut v = Vec::new();
     match write_object(&mut v, obj) {
         Ok(_) => Ok(v),
@@ -72,7 +93,7 @@ fn write_to_vec(obj: &Object) -> Result<Vec<u8>, Error> {
     }
 }
 
-fn write_data_to_tar<W: Write>(builder: &mut tar::Builder<W>, data: &[u8], path: &str) {
+pub(crate) fn write_data_to_tar<W: Write>(builder: &mut tar::Builder<W>, data: &[u8], path: &str) {
     let mut header = tar::Header::new_gnu();
     header.set_path(path).unwrap();
     header.set_size(data.len() as u64);
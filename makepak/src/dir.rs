@@ -1,11 +1,33 @@
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
 use std::env::current_dir;
 use std::path::{Path, PathBuf};
 
 thread_local!(
     pub static SRC_DIR: Cell<Option<PathBuf>> = Cell::new(None);
+    pub static SRC_FILE: Cell<Option<PathBuf>> = Cell::new(None);
+    static DEPENDENCIES: RefCell<Vec<PathBuf>> = RefCell::new(Vec::new());
 );
 
+/// Set the file being processed, used in error messages
+pub fn set_src_file(path: &Path) {
+    SRC_FILE.with(|src_file| {
+        src_file.replace(Some(path.to_owned()));
+    });
+}
+
+pub fn get_src_file() -> String {
+    SRC_FILE.with(|src_file| {
+        let tmp = src_file.replace(None);
+        let return_val = if let Some(ref path) = tmp {
+            path.to_string_lossy().into_owned()
+        } else {
+            "<unknown>".to_owned()
+        };
+        src_file.replace(tmp);
+        return_val
+    })
+}
+
 pub fn set_src_dir(path: Option<&Path>) {
     let path = path.to_owned();
 
@@ -33,3 +55,13 @@ pub fn path_from_src_dir<P: AsRef<Path>>(p: P) -> PathBuf {
     src_dir.push(p);
     src_dir
 }
+
+/// Record a file referenced by the file being processed, such as images
+pub fn add_dependency<P: AsRef<Path>>(p: P) {
+    DEPENDENCIES.with(|d| d.borrow_mut().push(p.as_ref().to_owned()));
+}
+
+/// Take the recorded dependencies and clear them
+pub fn take_dependencies() -> Vec<PathBuf> {
+    DEPENDENCIES.with(|d| d.replace(Vec::new()))
+}
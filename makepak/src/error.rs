@@ -23,4 +23,26 @@ pub enum PakCompileError {
     ObjWriteError { description: String },
     #[fail(display = "script parse error\n{}", description)]
     ScriptParseError { description: String },
+    #[fail(display = "{}:{}: {}", file, line, description)]
+    ScriptError {
+        file: String,
+        line: usize,
+        description: String,
+    },
+    #[fail(
+        display = "script check failed with {} error(s)\n{}",
+        n_errors, description
+    )]
+    ScriptCheckFailed {
+        n_errors: usize,
+        description: String,
+    },
+    #[fail(display = "base object \"{}\" is not found", id)]
+    UnknownBaseObject { id: String },
+    #[fail(display = "cyclic inheritance of \"{}\"", id)]
+    CyclicInheritance { id: String },
+    #[fail(display = "invalid variants: {}", description)]
+    InvalidVariants { description: String },
+    #[fail(display = "{} input file(s) failed to compile", n_failed)]
+    BuildFailed { n_failed: usize },
 }
@@ -0,0 +1,27 @@
+//! Pak file maker for Rusted Ruins.
+//! The compiling functions and the script parser are also used by other tools.
+
+#[macro_use]
+extern crate lazy_static;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate nom;
+extern crate rusted_ruins_array2d as array2d;
+extern crate rusted_ruins_common as common;
+
+pub mod verbose;
+#[macro_use]
+mod tomlinput;
+pub mod build;
+mod buildobj;
+pub mod compile;
+mod dir;
+pub mod error;
+pub mod rrscript;
+pub mod talk_text;
+mod template;
+
+pub use crate::buildobj::script_parse;
@@ -1,37 +1,42 @@
-#[macro_use]
-extern crate lazy_static;
-#[macro_use]
-extern crate serde_derive;
-#[macro_use]
-extern crate failure;
-#[macro_use]
-extern crate nom;
-extern crate rusted_ruins_array2d as array2d;
-extern crate rusted_ruins_common as common;
+extern crate rusted_ruins_makepak as makepak;
 
-mod verbose;
-#[macro_use]
-mod tomlinput;
-mod buildobj;
-mod compile;
-mod dir;
-mod error;
-mod rrscript;
+use makepak::{build, compile, talk_text, verbose};
+use std::path::Path;
 
 fn main() {
     let matches = create_matches();
 
+    // Verbose mode
+    if matches.is_present("verbose") {
+        verbose::set_verbose(true);
+    }
+
+    // Text directory to check talk text ids in scripts
+    if let Some(dir) = matches.value_of("text_dir") {
+        if let Err(e) = talk_text::set_text_dir(dir) {
+            eprintln!("Cannot load text directory \"{}\": {}", dir, e);
+        }
+    }
+
+    // Incremental build from the source directory
+    if let Some(src_dir) = matches.value_of("build") {
+        let out_dir = matches.value_of("output").unwrap_or(".");
+        match build::build(Path::new(src_dir), Path::new(out_dir)) {
+            Ok(summary) => println!("{}", summary),
+            Err(e) => {
+                eprintln!("Build failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // Input files
     let files: Vec<&str> = matches.values_of("INPUT").unwrap().collect();
     if files.is_empty() {
         return;
     }
 
-    // Verbose mode
-    if matches.is_present("verbose") {
-        verbose::set_verbose(true);
-    }
-
     // Print infomation of pak files
     if matches.is_present("info") {
         print_info(&files);
@@ -75,7 +80,23 @@ fn create_matches() -> clap::ArgMatches<'static> {
                 .short("o")
                 .long("output")
                 .value_name("FILE")
-                .help("Set output pakage file name")
+                .help("Set output pakage file name, or output directory in build mode")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("text_dir")
+                .short("t")
+                .long("text-dir")
+                .value_name("DIR")
+                .help("Set text directory to check talk text ids in scripts")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("build")
+                .short("b")
+                .long("build")
+                .value_name("SRC_DIR")
+                .help("Build each subdirectory into a pak, reusing unchanged objects")
                 .takes_value(true),
         )
         .arg(
@@ -83,7 +104,7 @@ fn create_matches() -> clap::ArgMatches<'static> {
                 .help("Input toml files")
                 .index(1)
                 .multiple(true)
-                .required(true),
+                .required_unless("build"),
         )
         .get_matches()
 }
@@ -1,4 +1,4 @@
-use crate::buildobj::script_parse;
+use crate::buildobj::script_parse_checked;
 use crate::error::*;
 use crate::verbose::print_verbose;
 use common::obj::{Object, ScriptObject};
@@ -15,9 +15,11 @@ pub fn read_rrscript<P: AsRef<Path>>(path: P) -> Result<Object, Error> {
     f.read_to_string(&mut script_text)?;
 
     print_verbose(|| format!("Processing \"{:?}\"", path.as_ref()));
+    crate::dir::set_src_file(path.as_ref());
 
     let object_id = first_line.trim().to_owned();
-    let script = script_parse(&script_text)?;
+    // The first line is object id
+    let script = script_parse_checked(&script_text, 1)?;
 
     Ok(Object::Script(ScriptObject {
         id: object_id,
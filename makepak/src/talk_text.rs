@@ -0,0 +1,59 @@
+//! Talk text ids used to check text ids in scripts
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+thread_local!(static TALK_TEXT_IDS: RefCell<Option<HashSet<String>>> = RefCell::new(None));
+
+/// Load talk text ids from "<dir>/talk". The dir is a text directory for one language.
+pub fn set_text_dir<P: AsRef<Path>>(dir: P) -> Result<(), io::Error> {
+    let mut ids = HashSet::new();
+    add_dir(&dir.as_ref().join("talk"), &mut ids)?;
+    TALK_TEXT_IDS.with(|a| {
+        a.replace(Some(ids));
+    });
+    Ok(())
+}
+
+/// Returns None if the text directory is not given
+pub fn has_talk_text(id: &str) -> Option<bool> {
+    TALK_TEXT_IDS.with(|a| a.borrow().as_ref().map(|ids| ids.contains(id)))
+}
+
+/// Hash of the loaded talk text ids. Returns None if the text directory is not given
+pub fn ids_hash() -> Option<u64> {
+    use std::hash::Hasher;
+    TALK_TEXT_IDS.with(|a| {
+        a.borrow().as_ref().map(|ids| {
+            let mut ids: Vec<&String> = ids.iter().collect();
+            ids.sort();
+            let mut hasher = fnv::FnvHasher::default();
+            for id in ids {
+                hasher.write(id.as_bytes());
+                hasher.write_u8(0);
+            }
+            hasher.finish()
+        })
+    })
+}
+
+fn add_dir(dir: &Path, ids: &mut HashSet<String>) -> Result<(), io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            add_dir(&path, ids)?;
+        } else if path.extension().map(|e| e == "txt").unwrap_or(false) {
+            let file = BufReader::new(fs::File::open(&path)?);
+            for line in file.lines() {
+                let line = line?;
+                if line.starts_with('%') {
+                    ids.insert(line[1..].trim().to_owned());
+                }
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,237 @@
+//! Object templates in toml input.
+//! An object can inherit fields from a base object by `inherit = "base-id"`.
+//! Objects with `abstract = true` are used only as bases, and not written to paks.
+//! `[variants]` generates multiple objects from one definition with scaled fields.
+//!
+//! ```toml
+//! [variants]
+//! fields = ["item.dice_x", "item.basic_price", "item.gen_level"]
+//! tiers = [
+//!     { id = "bronze-sword", scale = 1.0 },
+//!     { id = "iron-sword", scale = 1.5, set = { image = { path = "iron-sword.png" } } },
+//! ]
+//! ```
+
+use crate::error::*;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::{Table, Value};
+
+thread_local!(static BASES: RefCell<HashMap<String, (PathBuf, Value)>> = RefCell::new(HashMap::new()));
+
+/// Load toml files which can be inherited by other objects
+pub fn load_bases<P: AsRef<Path>>(files: &[P]) {
+    let mut bases = HashMap::new();
+    for path in files {
+        let path = path.as_ref();
+        if path.extension().map(|e| e != "toml").unwrap_or(true) {
+            continue;
+        }
+        // Errors are reported when the file is processed
+        let value: Value = match fs::read_to_string(path).map(|s| s.parse()) {
+            Ok(Ok(value)) => value,
+            _ => continue,
+        };
+        if let Some(id) = value.get("id").and_then(|id| id.as_str()) {
+            bases.insert(id.to_owned(), (path.to_owned(), value.clone()));
+        }
+    }
+    BASES.with(|b| {
+        b.replace(bases);
+    });
+}
+
+/// Resolve inheritance and generate variants. Abstract objects are expanded to nothing.
+pub fn expand(mut value: Value) -> Result<Vec<Value>, Error> {
+    resolve_inherit(&mut value, &mut Vec::new())?;
+
+    let table = as_table_mut(&mut value)?;
+    let is_abstract = table
+        .remove("abstract")
+        .map(|a| a.as_bool() == Some(true))
+        .unwrap_or(false);
+    let variants = table.remove("variants");
+
+    if is_abstract {
+        return Ok(Vec::new());
+    }
+    if let Some(variants) = variants {
+        return gen_variants(&value, &variants);
+    }
+    Ok(vec![value])
+}
+
+fn resolve_inherit(value: &mut Value, chain: &mut Vec<String>) -> Result<(), Error> {
+    let base_id = match as_table_mut(value)?.remove("inherit") {
+        Some(Value::String(base_id)) => base_id,
+        Some(_) => bail!(PakCompileError::UnexpectedValue {
+            field_name: "inherit".into(),
+            value: "not string".into(),
+        }),
+        None => return Ok(()),
+    };
+    if chain.contains(&base_id) {
+        bail!(PakCompileError::CyclicInheritance { id: base_id });
+    }
+
+    let (base_path, mut base) = BASES
+        .with(|b| b.borrow().get(&base_id).cloned())
+        .ok_or_else(|| PakCompileError::UnknownBaseObject {
+            id: base_id.clone(),
+        })?;
+    crate::dir::add_dependency(&base_path);
+
+    chain.push(base_id);
+    resolve_inherit(&mut base, chain)?;
+    chain.pop();
+
+    let base_table = as_table_mut(&mut base)?;
+    base_table.remove("abstract");
+    base_table.remove("variants");
+    base_table.remove("id");
+    // Image paths in the base are relative to the base file
+    if let Some(Value::String(path)) = base_table
+        .get_mut("image")
+        .and_then(|image| image.get_mut("path"))
+    {
+        if Path::new(path.as_str()).is_relative() {
+            let base_dir = base_path.parent().unwrap_or_else(|| Path::new(""));
+            let abs_path = std::env::current_dir()?.join(base_dir).join(&path);
+            *path = abs_path.to_string_lossy().into_owned();
+        }
+    }
+
+    merge(&mut base, value.clone());
+    *value = base;
+    Ok(())
+}
+
+/// Merge fields. Tables are merged recursively, and other values are overwritten.
+fn merge(target: &mut Value, value: Value) {
+    match (target, value) {
+        (Value::Table(target), Value::Table(table)) => {
+            for (k, v) in table {
+                if let Some(t) = target.get_mut(&k) {
+                    merge(t, v);
+                    continue;
+                }
+                target.insert(k, v);
+            }
+        }
+        (target, value) => {
+            *target = value;
+        }
+    }
+}
+
+fn gen_variants(value: &Value, variants: &Value) -> Result<Vec<Value>, Error> {
+    let invalid = |description: &str| PakCompileError::InvalidVariants {
+        description: description.to_owned(),
+    };
+    let fields: Vec<&str> = match variants.get("fields") {
+        Some(Value::Array(fields)) => fields.iter().filter_map(|f| f.as_str()).collect(),
+        None => Vec::new(),
+        _ => bail!(invalid("fields must be an array of strings")),
+    };
+    let tiers = match variants.get("tiers") {
+        Some(Value::Array(tiers)) => tiers,
+        _ => bail!(invalid("tiers must be an array")),
+    };
+
+    let mut generated = Vec::new();
+    for tier in tiers {
+        let id = tier
+            .get("id")
+            .and_then(|id| id.as_str())
+            .ok_or_else(|| invalid("tier must have id"))?;
+        let scale = match tier.get("scale") {
+            Some(Value::Float(scale)) => *scale,
+            Some(Value::Integer(scale)) => *scale as f64,
+            None => 1.0,
+            _ => bail!(invalid("scale must be a number")),
+        };
+
+        let mut v = value.clone();
+        for field in &fields {
+            scale_field(&mut v, field, scale)?;
+        }
+        if let Some(set) = tier.get("set") {
+            merge(&mut v, set.clone());
+        }
+        as_table_mut(&mut v)?.insert("id".to_owned(), Value::String(id.to_owned()));
+        generated.push(v);
+    }
+    Ok(generated)
+}
+
+fn scale_field(value: &mut Value, field: &str, scale: f64) -> Result<(), Error> {
+    let mut v = value;
+    for key in field.split('.') {
+        v = v
+            .get_mut(key)
+            .ok_or_else(|| PakCompileError::MissingField {
+                field_name: field.to_owned(),
+            })?;
+    }
+    match v {
+        Value::Integer(i) => *i = (*i as f64 * scale).round() as i64,
+        Value::Float(f) => *f *= scale,
+        _ => bail!(PakCompileError::InvalidVariants {
+            description: format!("field \"{}\" is not a number", field),
+        }),
+    }
+    Ok(())
+}
+
+fn as_table_mut(value: &mut Value) -> Result<&mut Table, Error> {
+    match value {
+        Value::Table(table) => Ok(table),
+        _ => bail!("toml input must be a table"),
+    }
+}
+
+#[test]
+fn expand_test() {
+    let base: Value = r#"
+object_type = "item"
+id = "sword-base"
+abstract = true
+[item]
+item_kind = "Weapon"
+basic_price = 100
+gen_level = 2
+dice_x = 3
+"#
+    .parse()
+    .unwrap();
+    BASES.with(|b| {
+        b.borrow_mut().insert(
+            "sword-base".to_owned(),
+            (PathBuf::from("base.toml"), base.clone()),
+        );
+    });
+    assert!(expand(base).unwrap().is_empty());
+
+    let sword: Value = r#"
+inherit = "sword-base"
+[item]
+basic_price = 120
+[variants]
+fields = ["item.basic_price", "item.dice_x"]
+tiers = [{ id = "bronze-sword", scale = 1 }, { id = "iron-sword", scale = 1.5, set = { item = { gen_level = 5 } } }]
+"#
+    .parse()
+    .unwrap();
+    let expanded = expand(sword).unwrap();
+    assert_eq!(expanded.len(), 2);
+
+    let item = |i: usize, key: &str| expanded[i]["item"][key].as_integer().unwrap();
+    assert_eq!(expanded[1]["id"].as_str(), Some("iron-sword"));
+    assert_eq!(expanded[1]["object_type"].as_str(), Some("item"));
+    assert_eq!((item(0, "basic_price"), item(0, "dice_x")), (120, 3));
+    assert_eq!((item(1, "basic_price"), item(1, "dice_x")), (180, 5));
+    assert_eq!((item(0, "gen_level"), item(1, "gen_level")), (2, 5));
+    assert!(expanded[1].get("variants").is_none());
+}
@@ -1,5 +1,7 @@
 use array2d::Vec2d;
 use common::gamedata::{self, ElementArray};
+use common::regiongen;
+use common::script::TriggerScript;
 use common::sitegen;
 
 #[derive(Debug, Deserialize)]
@@ -60,6 +62,7 @@ pub struct CharaTemplateDepInput {
     pub wil: u16,
     pub cha: u16,
     pub spd: u16,
+    pub trigger_death: Option<TriggerScript>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -94,6 +97,9 @@ pub struct ItemDepInput {
     pub dice_x: Option<u16>,
     pub weapon_kind: Option<gamedata::item::WeaponKind>,
     pub armor_kind: Option<gamedata::item::ArmorKind>,
+    pub ammo_kind: Option<gamedata::item::AmmoKind>,
+    /// For weapons and ammunition
+    pub element: Option<gamedata::Element>,
     pub medical_effect: Option<gamedata::item::MedicalEffect>,
     /// For armor items
     pub def: Option<ElementArray<u16>>,
@@ -103,8 +109,13 @@ pub struct ItemDepInput {
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct RegionGenDepInput {
-    pub map_template_id: String,
-    pub towns: Vec<SiteGenIdAndPos>,
+    /// Not needed if the region map is generated
+    pub map_template_id: Option<String>,
+    pub towns: Option<Vec<SiteGenIdAndPos>>,
+    pub gen: Option<regiongen::RegionMapGenParams>,
+    pub dungeons: Option<Vec<gamedata::DungeonKind>>,
+    pub connections: Option<Vec<regiongen::RegionConnection>>,
+    pub danger_level: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -121,6 +132,8 @@ pub struct SiteGenDepInput {
     pub map_template_id: Vec<String>,
     pub unique_citizens: Option<Vec<sitegen::UniqueCitizenGenData>>,
     pub shops: Option<Vec<sitegen::ShopGenData>>,
+    pub scheduled_scripts: Option<Vec<sitegen::ScheduledScriptGenData>>,
+    pub placement: Option<sitegen::SitePlacement>,
 }
 
 #[derive(Debug, Deserialize)]
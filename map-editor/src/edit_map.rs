@@ -1,6 +1,6 @@
 use array2d::*;
 use common::basic::N_TILE_IMG_LAYER;
-use common::gamedata::{ItemGen, TileLayers};
+use common::gamedata::{ItemGen, MapTriggers, TileLayers};
 use common::gobj;
 use common::maptemplate::*;
 use common::objholder::*;
@@ -206,6 +206,7 @@ impl EditingMap {
             deco: deco_map,
             boundary: self.property.boundary,
             items,
+            triggers: self.property.triggers.clone(),
         }
     }
 }
@@ -215,6 +216,7 @@ pub struct MapProperty {
     pub id: String,
     pub is_region_map: bool,
     pub boundary: MapTemplateBoundary,
+    pub triggers: MapTriggers,
 }
 
 impl MapProperty {
@@ -223,6 +225,7 @@ impl MapProperty {
             id: id.to_owned(),
             is_region_map: false,
             boundary: MapTemplateBoundary::default(),
+            triggers: MapTriggers::default(),
         }
     }
 }
@@ -251,6 +254,7 @@ impl From<MapTemplateObject> for EditingMap {
         }
 
         map.property.boundary = obj.boundary;
+        map.property.triggers = obj.triggers;
 
         map
     }
@@ -56,11 +56,15 @@ fn get_app_dir() -> Option<PathBuf> {
     None
 }
 
-/// Get addon directories
+/// Get addon directories in the load order
 fn get_addon_dir() -> Vec<PathBuf> {
-    let mut v = Vec::new();
     if let Some(e) = env::var_os("RUSTED_RUINS_ADDON_DIR") {
-        v.push(PathBuf::from(e));
+        let addons = common::addon::find_addons(&PathBuf::from(e));
+        common::addon::load_order(&addons)
+            .into_iter()
+            .map(|a| a.dir.clone())
+            .collect()
+    } else {
+        Vec::new()
     }
-    v
 }
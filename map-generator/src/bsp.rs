@@ -0,0 +1,224 @@
+//! Rooms and corridors map generation by binary space partitioning.
+//! The map is split recursively, and one room is placed in each leaf.
+//! Sibling subtrees are connected by a corridor between their rooms.
+
+use super::{GeneratedMap, Room, RoomTag, TileKind};
+use array2d::*;
+use rng::gen_range;
+
+/// Minimum width of walls between a room and the edge of its partition
+const MARGIN: i32 = 1;
+
+enum Node {
+    Leaf { room: usize },
+    Split { a: Box<Node>, b: Box<Node> },
+}
+
+pub fn write_to_map(
+    gm: &mut GeneratedMap,
+    min_room_size: u32,
+    max_room_size: u32,
+    door_weight: f64,
+) {
+    let min_room_size = std::cmp::max(min_room_size as i32, 1);
+    let max_room_size = std::cmp::max(max_room_size as i32, min_room_size);
+
+    // No room fits in the map with walls around it, so the map is left flat
+    if gm.size.0 < 1 + MARGIN * 2 || gm.size.1 < 1 + MARGIN * 2 {
+        return;
+    }
+
+    for p in gm.tile.iter_idx() {
+        gm.tile[p] = TileKind::Wall;
+    }
+    gm.rooms.clear();
+
+    let root = split(gm, Vec2d(0, 0), gm.size, min_room_size, max_room_size);
+    for room in &gm.rooms {
+        for p in RectIter::new(room.top_left, room.bottom_right()) {
+            gm.tile[p] = TileKind::Floor;
+        }
+    }
+
+    let mut n_connection = vec![0; gm.rooms.len()];
+    connect(gm, &root, &mut n_connection);
+    place_doors(gm, door_weight);
+    set_tags(gm, &n_connection);
+}
+
+/// Split the given area recursively, and create rooms on leaves.
+/// The area must be larger than the margins so that a room fits in each leaf.
+fn split(
+    gm: &mut GeneratedMap,
+    top_left: Vec2d,
+    size: Vec2d,
+    min_room_size: i32,
+    max_room_size: i32,
+) -> Node {
+    let min_area = min_room_size + MARGIN * 2;
+    let can_split_x = size.0 >= min_area * 2;
+    let can_split_y = size.1 >= min_area * 2;
+    let need_split = size.0 > max_room_size + MARGIN * 2 || size.1 > max_room_size + MARGIN * 2;
+
+    if need_split && (can_split_x || can_split_y) {
+        // Split along the longer side
+        let split_x = if can_split_x && can_split_y {
+            size.0 >= size.1
+        } else {
+            can_split_x
+        };
+        let (a, b) = if split_x {
+            let w = gen_range(min_area, size.0 - min_area + 1);
+            (
+                (top_left, Vec2d(w, size.1)),
+                (top_left + (w, 0), Vec2d(size.0 - w, size.1)),
+            )
+        } else {
+            let h = gen_range(min_area, size.1 - min_area + 1);
+            (
+                (top_left, Vec2d(size.0, h)),
+                (top_left + (0, h), Vec2d(size.0, size.1 - h)),
+            )
+        };
+        return Node::Split {
+            a: Box::new(split(gm, a.0, a.1, min_room_size, max_room_size)),
+            b: Box::new(split(gm, b.0, b.1, min_room_size, max_room_size)),
+        };
+    }
+
+    let max_w = std::cmp::max(std::cmp::min(max_room_size, size.0 - MARGIN * 2), 1);
+    let max_h = std::cmp::max(std::cmp::min(max_room_size, size.1 - MARGIN * 2), 1);
+    let w = gen_range(std::cmp::min(min_room_size, max_w), max_w + 1);
+    let h = gen_range(std::cmp::min(min_room_size, max_h), max_h + 1);
+    let x = gen_range(MARGIN, std::cmp::max(size.0 - w - MARGIN, MARGIN) + 1);
+    let y = gen_range(MARGIN, std::cmp::max(size.1 - h - MARGIN, MARGIN) + 1);
+    gm.rooms.push(Room {
+        top_left: top_left + (x, y),
+        size: Vec2d(w, h),
+        doors: Vec::new(),
+        tags: Vec::new(),
+    });
+    Node::Leaf {
+        room: gm.rooms.len() - 1,
+    }
+}
+
+/// Connect two subtrees of each node by a corridor. Returns rooms in the node.
+fn connect(gm: &mut GeneratedMap, node: &Node, n_connection: &mut [u32]) -> Vec<usize> {
+    match node {
+        Node::Leaf { room } => vec![*room],
+        Node::Split { a, b } => {
+            let rooms_a = connect(gm, a, n_connection);
+            let rooms_b = connect(gm, b, n_connection);
+
+            // Connect the nearest pair of rooms
+            let (ra, rb) = rooms_a
+                .iter()
+                .flat_map(|ra| rooms_b.iter().map(move |rb| (*ra, *rb)))
+                .min_by_key(|(ra, rb)| gm.rooms[*ra].center().mdistance(gm.rooms[*rb].center()))
+                .unwrap();
+            dig_corridor(gm, gm.rooms[ra].center(), gm.rooms[rb].center());
+            n_connection[ra] += 1;
+            n_connection[rb] += 1;
+
+            let mut rooms = rooms_a;
+            rooms.extend(rooms_b);
+            rooms
+        }
+    }
+}
+
+/// Dig L-shaped corridor
+fn dig_corridor(gm: &mut GeneratedMap, start: Vec2d, end: Vec2d) {
+    let corner = if gen_range(0, 2) == 0 {
+        Vec2d(end.0, start.1)
+    } else {
+        Vec2d(start.0, end.1)
+    };
+    dig_straight(gm, start, corner);
+    dig_straight(gm, corner, end);
+}
+
+fn dig_straight(gm: &mut GeneratedMap, start: Vec2d, end: Vec2d) {
+    let (x0, x1) = (std::cmp::min(start.0, end.0), std::cmp::max(start.0, end.0));
+    let (y0, y1) = (std::cmp::min(start.1, end.1), std::cmp::max(start.1, end.1));
+    for p in RectIter::new((x0, y0), (x1, y1)) {
+        gm.tile[p] = TileKind::Floor;
+    }
+}
+
+/// Place doors at one tile width openings of rooms
+fn place_doors(gm: &mut GeneratedMap, door_weight: f64) {
+    for i in 0..gm.rooms.len() {
+        let room = &gm.rooms[i];
+        let tl = room.top_left - (1, 1);
+        let br = room.bottom_right() + (1, 1);
+        let mut doors = Vec::new();
+
+        for p in RectIter::new(tl, br) {
+            let on_x_edge = p.0 == tl.0 || p.0 == br.0;
+            let on_y_edge = p.1 == tl.1 || p.1 == br.1;
+            // Skip inside and corners
+            if on_x_edge == on_y_edge {
+                continue;
+            }
+            if gm.tile.get(p) != Some(&TileKind::Floor) {
+                continue;
+            }
+            let (side_a, side_b) = if on_x_edge {
+                (p + (0, -1), p + (0, 1))
+            } else {
+                (p + (-1, 0), p + (1, 0))
+            };
+            let is_wall = |p: Vec2d| gm.tile.get(p).map(|t| *t == TileKind::Wall).unwrap_or(true);
+            // Avoid double doors on a short corridor between two rooms
+            let next_to_door = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+                .iter()
+                .any(|d| gm.tile.get(p + *d) == Some(&TileKind::Door));
+            if is_wall(side_a)
+                && is_wall(side_b)
+                && !next_to_door
+                && door_weight > gen_range(0.0, 1.0)
+            {
+                gm.tile[p] = TileKind::Door;
+                doors.push(p);
+            }
+        }
+
+        gm.rooms[i].doors = doors;
+    }
+}
+
+/// Set entrance, exit and tags of rooms
+fn set_tags(gm: &mut GeneratedMap, n_connection: &[u32]) {
+    let entrance_room = gen_range(0, gm.rooms.len());
+    let entrance_center = gm.rooms[entrance_room].center();
+    let distance = |room: &Room| room.center().mdistance(entrance_center);
+
+    gm.entrance = gm.rooms[entrance_room].random_pos();
+    gm.rooms[entrance_room].tags.push(RoomTag::Entrance);
+
+    if gm.rooms.len() < 2 {
+        return;
+    }
+
+    let exit_room = (0..gm.rooms.len())
+        .filter(|i| *i != entrance_room)
+        .max_by_key(|i| distance(&gm.rooms[*i]))
+        .unwrap();
+    gm.exit = Some(gm.rooms[exit_room].random_pos());
+    gm.rooms[exit_room].tags.push(RoomTag::Exit);
+
+    // The farthest dead end becomes the treasure room
+    let treasure_room = (0..gm.rooms.len())
+        .filter(|i| *i != entrance_room && *i != exit_room && n_connection[*i] == 1)
+        .max_by_key(|i| distance(&gm.rooms[*i]));
+    if let Some(treasure_room) = treasure_room {
+        gm.rooms[treasure_room].tags.push(RoomTag::Treasure);
+    }
+    for (i, room) in gm.rooms.iter_mut().enumerate() {
+        if n_connection[i] == 1 && !room.tags.contains(&RoomTag::Treasure) {
+            room.tags.push(RoomTag::DeadEnd);
+        }
+    }
+}
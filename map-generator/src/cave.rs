@@ -0,0 +1,224 @@
+//! Cave map generation by cellular automata.
+//! Water pools and chasms are grown by the same automata on the floor,
+//! and are placed only if they do not divide the cave.
+
+use super::validate::EIGHT_DIRS;
+use super::{DecoKind, GeneratedMap, TileKind};
+use array2d::*;
+use rng::gen_range;
+
+/// Generated caves smaller than this ratio of the map are discarded
+const MIN_FLOOR_RATIO: f64 = 0.3;
+
+/// The number of retries to generate a large enough cave
+const MAX_TRY: usize = 20;
+
+pub fn write_to_map(
+    gm: &mut GeneratedMap,
+    wall_ratio: f64,
+    n_iteration: u32,
+    water_ratio: f64,
+    chasm_ratio: f64,
+    rubble_ratio: f64,
+) {
+    let size = gm.size;
+    let n_tile = (size.0 * size.1) as f64;
+
+    // Retry until the largest cave is large enough
+    let mut ok = false;
+    for _ in 0..MAX_TRY {
+        let wall = automata(size, wall_ratio, n_iteration, |_| true);
+        for p in gm.tile.iter_idx() {
+            gm.tile[p] = if wall[p] {
+                TileKind::Wall
+            } else {
+                TileKind::Floor
+            };
+        }
+        if keep_largest_region(gm) as f64 >= n_tile * MIN_FLOOR_RATIO {
+            ok = true;
+            break;
+        }
+    }
+    // Fallback to an open cave surrounded by walls
+    if !ok {
+        for p in gm.tile.iter_idx() {
+            let is_edge = p.0 == 0 || p.1 == 0 || p.0 == size.0 - 1 || p.1 == size.1 - 1;
+            gm.tile[p] = if is_edge {
+                TileKind::Wall
+            } else {
+                TileKind::Floor
+            };
+        }
+    }
+
+    let floor = |gm: &GeneratedMap, p: Vec2d| gm.tile[p] == TileKind::Floor;
+    let pools = automata(size, 1.0 - water_ratio, n_iteration, |p| floor(gm, p));
+    place_pools(gm, &pools, TileKind::Water);
+    let chasms = automata(size, 1.0 - chasm_ratio, n_iteration, |p| floor(gm, p));
+    place_pools(gm, &chasms, TileKind::Chasm);
+
+    // Rubble is scattered along walls
+    for p in gm.tile.iter_idx() {
+        if gm.tile[p] == TileKind::Floor
+            && count_around(&gm.tile, p, |t| t == Some(&TileKind::Wall)) > 0
+            && rubble_ratio > gen_range(0.0, 1.0)
+        {
+            gm.deco[p] = Some(DecoKind::Rubble);
+        }
+    }
+
+    set_entrance_exit(gm);
+}
+
+/// Create random cells and apply the automata rule.
+/// Returns true for wall cells. Cells outside of the mask are always walls.
+fn automata<F: Fn(Vec2d) -> bool>(
+    size: Vec2d,
+    wall_ratio: f64,
+    n_iteration: u32,
+    mask: F,
+) -> Array2d<bool> {
+    let mut cells = Array2d::new(size.0 as u32, size.1 as u32, true);
+    for p in cells.iter_idx() {
+        let is_edge = p.0 == 0 || p.1 == 0 || p.0 == size.0 - 1 || p.1 == size.1 - 1;
+        cells[p] = is_edge || !mask(p) || wall_ratio > gen_range(0.0, 1.0);
+    }
+
+    for _ in 0..n_iteration {
+        let prev = cells.clone();
+        for p in cells.iter_idx() {
+            if !mask(p) {
+                continue;
+            }
+            let n_wall = count_around(&prev, p, |c| c.cloned().unwrap_or(true));
+            // Walls survive with 4 neighbor walls, and are born with 5
+            cells[p] = if prev[p] { n_wall >= 4 } else { n_wall >= 5 };
+        }
+    }
+    cells
+}
+
+fn count_around<T, F: Fn(Option<&T>) -> bool>(cells: &Array2d<T>, p: Vec2d, f: F) -> u32 {
+    let mut count = 0;
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if (dx, dy) != (0, 0) && f(cells.get(p + (dx, dy))) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Fill floor tiles except for the largest region. Returns the number of remaining floor tiles.
+fn keep_largest_region(gm: &mut GeneratedMap) -> u32 {
+    let mut visited = Array2d::new(gm.size.0 as u32, gm.size.1 as u32, false);
+    let mut largest: Option<(Vec2d, u32)> = None;
+
+    for p in gm.tile.iter_idx() {
+        if visited[p] || !gm.tile[p].is_passable() {
+            continue;
+        }
+        let region = gm.distance_map(p, &EIGHT_DIRS);
+        let n = region.iter_with_idx().filter(|(_, d)| d.is_some()).count() as u32;
+        for (q, d) in region.iter_with_idx() {
+            if d.is_some() {
+                visited[q] = true;
+            }
+        }
+        if largest.map(|(_, m)| n > m).unwrap_or(true) {
+            largest = Some((p, n));
+        }
+    }
+
+    let (start, n) = match largest {
+        Some(largest) => largest,
+        None => return 0,
+    };
+    let region = gm.distance_map(start, &EIGHT_DIRS);
+    for p in gm.tile.iter_idx() {
+        if region[p].is_none() {
+            gm.tile[p] = TileKind::Wall;
+        }
+    }
+    n
+}
+
+/// Place each connected pool if the remaining floor is still connected
+fn place_pools(gm: &mut GeneratedMap, cells: &Array2d<bool>, kind: TileKind) {
+    let n_passable = |gm: &GeneratedMap| gm.tile.iter().filter(|t| t.is_passable()).count();
+    let mut visited = Array2d::new(gm.size.0 as u32, gm.size.1 as u32, false);
+
+    for p in cells.iter_idx() {
+        if cells[p] || visited[p] || gm.tile[p] != TileKind::Floor {
+            continue;
+        }
+        // Collect one pool
+        let mut pool = vec![p];
+        let mut i = 0;
+        visited[p] = true;
+        while i < pool.len() {
+            let q = pool[i];
+            i += 1;
+            for d in &[(0, -1), (0, 1), (-1, 0), (1, 0)] {
+                let r = q + *d;
+                if cells.get(r) == Some(&false) && !visited[r] && gm.tile[r] == TileKind::Floor {
+                    visited[r] = true;
+                    pool.push(r);
+                }
+            }
+        }
+
+        let n_before = n_passable(gm);
+        for q in &pool {
+            gm.tile[*q] = kind;
+        }
+        let remaining = match gm.tile.iter_with_idx().find(|(_, t)| t.is_passable()) {
+            Some((start, _)) => gm
+                .distance_map(start, &EIGHT_DIRS)
+                .iter()
+                .filter(|d| d.is_some())
+                .count(),
+            None => 0,
+        };
+        if remaining == 0 || remaining != n_before - pool.len() {
+            for q in &pool {
+                gm.tile[*q] = TileKind::Floor;
+            }
+        }
+    }
+}
+
+/// The exit is chosen from the farther half of the cave from the entrance
+fn set_entrance_exit(gm: &mut GeneratedMap) {
+    let passable: Vec<Vec2d> = gm
+        .tile
+        .iter_with_idx()
+        .filter(|(p, t)| **t == TileKind::Floor && gm.deco[*p].is_none())
+        .map(|(p, _)| p)
+        .collect();
+    if passable.is_empty() {
+        return;
+    }
+    gm.entrance = passable[gen_range(0, passable.len())];
+
+    let distance = gm.distance_map(gm.entrance, &EIGHT_DIRS);
+    let max = passable
+        .iter()
+        .filter_map(|p| distance[*p])
+        .max()
+        .unwrap_or(0);
+    let far: Vec<Vec2d> = passable
+        .iter()
+        .filter(|p| {
+            distance[**p]
+                .map(|d| d > 0 && d * 2 >= max)
+                .unwrap_or(false)
+        })
+        .cloned()
+        .collect();
+    if !far.is_empty() {
+        gm.exit = Some(far[gen_range(0, far.len())]);
+    }
+}
@@ -1,3 +1,4 @@
+use super::validate::FOUR_DIRS;
 use super::{GeneratedMap, TileKind};
 use array2d::*;
 use rng::gen_range;
@@ -129,46 +130,12 @@ fn calc_threshold(fractal: &Array2d<f32>, floor_ratio: f32) -> f32 {
     v[(n_tile as f32 * floor_ratio) as usize]
 }
 
-/// Calculate tiles are reacheable from given tile
-fn create_reach_map(map: &GeneratedMap, start: Vec2d) -> (Array2d<bool>, u32) {
-    let mut reachable = Array2d::new(map.size.0 as u32, map.size.1 as u32, false);
-    let mut reachable_tile_count = 0;
-
-    if map.tile[start].is_passable() {
-        reachable[start] = true;
-    } else {
-        return (reachable, 0);
-    }
-
-    loop {
-        let mut new_reachable_tile = false;
-
-        for p in map.tile.iter_idx() {
-            if reachable[p] {
-                let mut try_next_tile = |next_tile: Vec2d| {
-                    if map.tile.in_range(next_tile)
-                        && map.tile[next_tile].is_passable()
-                        && !reachable[next_tile]
-                    {
-                        reachable_tile_count += 1;
-                        reachable[next_tile] = true;
-                        new_reachable_tile = true;
-                    }
-                };
-
-                try_next_tile(p + (-1, 0));
-                try_next_tile(p + (1, 0));
-                try_next_tile(p + (0, -1));
-                try_next_tile(p + (0, 1));
-            }
-        }
-
-        if !new_reachable_tile {
-            break;
-        }
-    }
-
-    (reachable, reachable_tile_count)
+/// Calculate tiles are reacheable from given tile.
+/// Returns the number of reachable tiles except for the given tile.
+pub fn create_reach_map(map: &GeneratedMap, start: Vec2d) -> (Array2d<bool>, u32) {
+    let reachable = map.reach_map(start, &FOUR_DIRS);
+    let reachable_tile_count = reachable.iter().filter(|r| **r).count().saturating_sub(1);
+    (reachable, reachable_tile_count as u32)
 }
 
 /// Pick one passable tile at random
@@ -5,33 +5,135 @@ extern crate rusted_ruins_rng as rng;
 
 use array2d::*;
 
+mod bsp;
+mod cave;
 mod fractal;
 mod lattice;
+mod noise;
+mod prefab;
+mod validate;
+mod wfc;
+
+pub use crate::noise::value_noise;
+pub use crate::validate::{analyze, reach_map, MapReport, EIGHT_DIRS};
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TileKind {
     Floor,
     Wall,
     Door,
+    Water,
+    Chasm,
 }
 
 impl TileKind {
     pub fn is_passable(self) -> bool {
         match self {
             TileKind::Floor | TileKind::Door => true,
-            TileKind::Wall => false,
+            TileKind::Wall | TileKind::Water | TileKind::Chasm => false,
         }
     }
 }
 
+/// Decorations placed on floor tiles
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DecoKind {
+    Rubble,
+}
+
+#[derive(Clone)]
 pub struct GeneratedMap {
     pub size: Vec2d,
     pub tile: Array2d<TileKind>,
+    pub deco: Array2d<Option<DecoKind>>,
     pub entrance: Vec2d,
     pub exit: Option<Vec2d>,
+    /// Rooms in the map. Empty if the algorithm does not create rooms.
+    pub rooms: Vec<Room>,
+    /// Placed prefabs
+    pub prefabs: Vec<PlacedPrefab>,
+    /// Tile ids of examples for generators learning from example maps
+    pub source_tile: Option<Array2d<u32>>,
+}
+
+/// Fixed part of maps placed into generated maps
+#[derive(Clone, Debug)]
+pub struct Prefab {
+    pub tile: Array2d<TileKind>,
+    /// Tiles connected to the outside of the prefab
+    pub connections: Vec<Vec2d>,
+}
+
+impl Prefab {
+    /// Passable tiles on the edges of the prefab are used as connection points
+    pub fn new(tile: Array2d<TileKind>) -> Prefab {
+        let (w, h) = (tile.size().0 as i32, tile.size().1 as i32);
+        let connections = tile
+            .iter_with_idx()
+            .filter(|(p, t)| {
+                t.is_passable() && (p.0 == 0 || p.1 == 0 || p.0 == w - 1 || p.1 == h - 1)
+            })
+            .map(|(p, _)| p)
+            .collect();
+        Prefab { tile, connections }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct PlacedPrefab {
+    /// Index in the given prefabs
+    pub index: usize,
+    pub top_left: Vec2d,
+}
+
+/// Rectangle room in a generated map
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Room {
+    pub top_left: Vec2d,
+    pub size: Vec2d,
+    /// Door positions on the outline of this room
+    pub doors: Vec<Vec2d>,
+    pub tags: Vec<RoomTag>,
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RoomTag {
+    /// The room includes the entrance
+    Entrance,
+    /// The room includes the exit
+    Exit,
+    /// The farthest dead end from the entrance
+    Treasure,
+    /// The room is connected to only one other room
+    DeadEnd,
+}
+
+impl Room {
+    pub fn bottom_right(&self) -> Vec2d {
+        self.top_left + self.size - (1, 1)
+    }
+
+    pub fn center(&self) -> Vec2d {
+        Vec2d(
+            self.top_left.0 + self.size.0 / 2,
+            self.top_left.1 + self.size.1 / 2,
+        )
+    }
+
+    pub fn contains(&self, p: Vec2d) -> bool {
+        let br = self.bottom_right();
+        self.top_left.0 <= p.0 && p.0 <= br.0 && self.top_left.1 <= p.1 && p.1 <= br.1
+    }
+
+    pub fn random_pos(&self) -> Vec2d {
+        Vec2d(
+            rng::gen_range(self.top_left.0, self.top_left.0 + self.size.0),
+            rng::gen_range(self.top_left.1, self.top_left.1 + self.size.1),
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
 enum MapGenParam {
     Flat,
     Lattice {
@@ -42,11 +144,32 @@ enum MapGenParam {
         door_weight: f64,
     },
     Fractal,
+    Bsp {
+        min_room_size: u32,
+        max_room_size: u32,
+        door_weight: f64,
+    },
+    Cave {
+        wall_ratio: f64,
+        n_iteration: u32,
+        water_ratio: f64,
+        chasm_ratio: f64,
+        rubble_ratio: f64,
+    },
+    Wfc {
+        examples: Vec<Array2d<u32>>,
+        kinds: Vec<TileKind>,
+        n: u32,
+        entrance: Option<Vec2d>,
+        exit: Option<Vec2d>,
+    },
 }
 
+#[derive(Clone)]
 pub struct MapGenerator {
     map: GeneratedMap,
     genparam: Option<MapGenParam>,
+    prefabs: Vec<Prefab>,
 }
 
 impl MapGenerator {
@@ -55,12 +178,17 @@ impl MapGenerator {
         let map = GeneratedMap {
             size,
             tile: Array2d::new(size.0 as u32, size.1 as u32, TileKind::Floor),
+            deco: Array2d::new(size.0 as u32, size.1 as u32, None),
             entrance: Vec2d(0, 0),
             exit: None,
+            rooms: Vec::new(),
+            prefabs: Vec::new(),
+            source_tile: None,
         };
         MapGenerator {
             map,
             genparam: None,
+            prefabs: Vec::new(),
         }
     }
 
@@ -98,15 +226,75 @@ impl MapGenerator {
         mg
     }
 
+    /// Create rooms and corridors map by binary space partitioning
+    pub fn bsp(self, min_room_size: u32, max_room_size: u32, door_weight: f64) -> MapGenerator {
+        let mut mg = self;
+        mg.genparam = Some(MapGenParam::Bsp {
+            min_room_size,
+            max_room_size,
+            door_weight,
+        });
+        mg
+    }
+
+    /// Create cave map by cellular automata.
+    /// Ratios for water and chasms are the initial ratios of their seeds,
+    /// and about 0.4 or more is needed to grow pools.
+    pub fn cave(
+        self,
+        wall_ratio: f64,
+        n_iteration: u32,
+        water_ratio: f64,
+        chasm_ratio: f64,
+        rubble_ratio: f64,
+    ) -> MapGenerator {
+        let mut mg = self;
+        mg.genparam = Some(MapGenParam::Cave {
+            wall_ratio,
+            n_iteration,
+            water_ratio,
+            chasm_ratio,
+            rubble_ratio,
+        });
+        mg
+    }
+
+    /// Create map by wave function collapse learning NxN patterns from examples.
+    /// Examples are given as tile ids, and kinds are the tile kinds of each id.
+    /// If entrance or exit is given, the tile at the position is passable.
+    pub fn wfc(
+        self,
+        examples: Vec<Array2d<u32>>,
+        kinds: Vec<TileKind>,
+        n: u32,
+        entrance: Option<Vec2d>,
+        exit: Option<Vec2d>,
+    ) -> MapGenerator {
+        let mut mg = self;
+        mg.genparam = Some(MapGenParam::Wfc {
+            examples,
+            kinds,
+            n,
+            entrance,
+            exit,
+        });
+        mg
+    }
+
+    /// Place prefabs after generation. Prefabs are skipped if no space is found.
+    pub fn prefabs(mut self, prefabs: Vec<Prefab>) -> MapGenerator {
+        self.prefabs = prefabs;
+        self
+    }
+
     /// Generate one map
     pub fn generate(mut self) -> GeneratedMap {
         match self
             .genparam
+            .take()
             .expect("Map generate before giving parameters")
         {
-            MapGenParam::Flat => {
-                return self.map;
-            }
+            MapGenParam::Flat => (),
             MapGenParam::Lattice {
                 nx,
                 ny,
@@ -116,13 +304,47 @@ impl MapGenerator {
             } => {
                 let lattice = lattice::create_lattice(nx, ny, step_min, step_max);
                 lattice.write_to_map(&mut self.map, door_weight);
-                return self.map;
             }
             MapGenParam::Fractal => {
                 fractal::write_to_map(&mut self.map);
-                return self.map;
+            }
+            MapGenParam::Bsp {
+                min_room_size,
+                max_room_size,
+                door_weight,
+            } => {
+                bsp::write_to_map(&mut self.map, min_room_size, max_room_size, door_weight);
+            }
+            MapGenParam::Cave {
+                wall_ratio,
+                n_iteration,
+                water_ratio,
+                chasm_ratio,
+                rubble_ratio,
+            } => {
+                cave::write_to_map(
+                    &mut self.map,
+                    wall_ratio,
+                    n_iteration,
+                    water_ratio,
+                    chasm_ratio,
+                    rubble_ratio,
+                );
+            }
+            MapGenParam::Wfc {
+                examples,
+                kinds,
+                n,
+                entrance,
+                exit,
+            } => {
+                wfc::write_to_map(&mut self.map, &examples, &kinds, n, entrance, exit);
             }
         }
+        if !self.prefabs.is_empty() {
+            prefab::place(&mut self.map, &self.prefabs);
+        }
+        self.map
     }
 }
 
@@ -134,11 +356,15 @@ impl std::fmt::Display for GeneratedMap {
                     '<'
                 } else if self.exit == Some(Vec2d(nx, ny)) {
                     '>'
+                } else if self.deco[(nx, ny)] == Some(DecoKind::Rubble) {
+                    '*'
                 } else {
                     match self.tile[(nx, ny)] {
                         TileKind::Floor => '.',
                         TileKind::Wall => '#',
                         TileKind::Door => 'D',
+                        TileKind::Water => '~',
+                        TileKind::Chasm => ' ',
                     }
                 };
 
@@ -154,6 +380,13 @@ impl std::fmt::Display for GeneratedMap {
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    fn assert_valid(map: &GeneratedMap) {
+        let report = map.validate(false);
+        println!("{}", report);
+        assert!(report.is_valid());
+    }
+
     #[test]
     fn flat_map() {
         let map = MapGenerator::new((10, 10)).flat().generate();
@@ -177,5 +410,210 @@ mod tests {
         println!("Fractal map:");
         let map = MapGenerator::new((30, 30)).fractal().generate();
         println!("{}", map);
+        assert_valid(&map);
+    }
+
+    #[test]
+    fn bsp_map() {
+        println!("BSP map:");
+        let map = MapGenerator::new((40, 30)).bsp(3, 8, 0.8).generate();
+        println!("{}", map);
+        assert_valid(&map);
+
+        assert!(map.rooms.len() >= 2);
+        let entrance_room = map
+            .rooms
+            .iter()
+            .find(|r| r.tags.contains(&RoomTag::Entrance));
+        assert!(entrance_room.unwrap().contains(map.entrance));
+        let exit_room = map.rooms.iter().find(|r| r.tags.contains(&RoomTag::Exit));
+        assert!(exit_room.unwrap().contains(map.exit.unwrap()));
+        for room in &map.rooms {
+            for door in &room.doors {
+                assert_eq!(map.tile[*door], TileKind::Door);
+            }
+        }
+    }
+
+    #[test]
+    fn bsp_small_map() {
+        for size in &[(1, 1), (2, 5), (3, 3), (4, 4), (5, 3), (7, 20)] {
+            let map = MapGenerator::new(*size).bsp(3, 8, 0.8).generate();
+            println!("{}", map);
+            for room in &map.rooms {
+                assert!(room.top_left.0 >= 1 && room.top_left.1 >= 1);
+                assert!(room.bottom_right().0 < map.size.0 - 1);
+                assert!(room.bottom_right().1 < map.size.1 - 1);
+            }
+            assert!(map.tile[map.entrance].is_passable());
+        }
+    }
+
+    #[test]
+    fn cave_map() {
+        println!("Cave map:");
+        let map = MapGenerator::new((40, 30))
+            .cave(0.45, 4, 0.45, 0.42, 0.05)
+            .generate();
+        println!("{}", map);
+
+        assert!(map.exit.is_some());
+        assert_valid(&map);
+
+        // Too many walls to generate caves
+        let map = MapGenerator::new((40, 30))
+            .cave(0.99, 4, 0.0, 0.0, 0.0)
+            .generate();
+        assert!(map.exit.is_some());
+        assert_valid(&map);
+    }
+
+    #[test]
+    fn prefab_map() {
+        let vault = Array2d::from_fn(7, 5, |p| {
+            if p.0 == 3 && p.1 == 4 {
+                TileKind::Door
+            } else if p.0 == 0 || p.1 == 0 || p.0 == 6 || p.1 == 4 {
+                TileKind::Wall
+            } else {
+                TileKind::Floor
+            }
+        });
+        let map = MapGenerator::new((40, 30))
+            .cave(0.45, 4, 0.0, 0.0, 0.0)
+            .prefabs(vec![Prefab::new(vault.clone())])
+            .generate();
+        println!("Prefab map:");
+        println!("{}", map);
+        assert_valid(&map);
+
+        assert!(!map.prefabs.is_empty());
+        let passable = Array2d::from_fn(40, 30, |p| map.tile[p].is_passable());
+        let reach = reach_map(&passable, map.entrance);
+        for placed in &map.prefabs {
+            for (p, t) in vault.iter_with_idx() {
+                assert_eq!(map.tile[placed.top_left + p], *t);
+                if t.is_passable() {
+                    assert!(reach[placed.top_left + p]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wfc_map() {
+        // 0: floor, 1: wall, 2: water
+        let example = "\
+1111111111111
+1000001000001
+1000001000001
+1000000000221
+1000001000221
+1111011110001
+1000000010001
+1000000000001
+1111111111111";
+        let example: Vec<Vec<u32>> = example
+            .lines()
+            .map(|l| l.chars().map(|c| c.to_digit(10).unwrap()).collect())
+            .collect();
+        let example = Array2d::from_fn(13, 9, |(x, y)| example[y as usize][x as usize]);
+        let kinds = vec![TileKind::Floor, TileKind::Wall, TileKind::Water];
+
+        let map = MapGenerator::new((30, 20))
+            .wfc(vec![example.clone()], kinds, 3, Some(Vec2d(1, 1)), None)
+            .generate();
+        println!("WFC map:");
+        println!("{}", map);
+
+        // Every 3x3 area of the result is found in the example
+        let windows = |tile: &Array2d<u32>| {
+            let (w, h) = (tile.size().0 as i32, tile.size().1 as i32);
+            RectIter::new((0, 0), (w - 3, h - 3))
+                .map(|p| {
+                    RectIter::new(p, p + (2, 2))
+                        .map(|q| tile[q])
+                        .collect::<Vec<u32>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let learned = windows(&example);
+        let source_tile = map.source_tile.as_ref().unwrap();
+        assert!(windows(source_tile).iter().all(|w| learned.contains(w)));
+        assert_eq!(map.entrance, Vec2d(1, 1));
+        assert!(map.exit.is_some());
+        assert_valid(&map);
+    }
+
+    #[test]
+    fn wfc_fallback() {
+        let kinds = vec![TileKind::Floor, TileKind::Wall];
+
+        // No patterns to learn
+        let map = MapGenerator::new((10, 10))
+            .wfc(vec![], kinds.clone(), 3, None, None)
+            .generate();
+        assert!(map.exit.is_some());
+        assert_valid(&map);
+
+        // Fixed positions are kept in the fallback
+        let map = MapGenerator::new((10, 10))
+            .wfc(
+                vec![],
+                kinds.clone(),
+                3,
+                Some(Vec2d(1, 2)),
+                Some(Vec2d(8, 7)),
+            )
+            .generate();
+        assert_eq!(map.entrance, Vec2d(1, 2));
+        assert_eq!(map.exit, Some(Vec2d(8, 7)));
+
+        // Positions out of the map are placed randomly
+        let example = Array2d::from_fn(6, 6, |(x, y)| if (x + y) % 5 == 0 { 1 } else { 0 });
+        let map = MapGenerator::new((10, 10))
+            .wfc(
+                vec![example],
+                kinds,
+                3,
+                Some(Vec2d(-1, 3)),
+                Some(Vec2d(3, 10)),
+            )
+            .generate();
+        assert!(map.tile.in_range(map.entrance));
+        assert!(map.tile.in_range(map.exit.unwrap()));
+        assert_valid(&map);
+    }
+
+    #[test]
+    fn validate_map() {
+        // A corridor between two rooms
+        let passable = Array2d::from_fn(9, 3, |(x, y)| x != 4 || y == 1);
+        let report = analyze(&passable, Vec2d(0, 0), &[Vec2d(8, 2)]);
+        assert!(report.is_valid());
+        assert_eq!(report.n_reachable, 25);
+        assert_eq!(report.chokepoints, vec![Vec2d(4, 1)]);
+
+        let passable = Array2d::from_fn(9, 3, |(x, _)| x != 4);
+        let report = analyze(&passable, Vec2d(0, 0), &[Vec2d(8, 2)]);
+        assert!(!report.is_valid());
+        assert_eq!(report.n_reachable, 12);
+
+        // Only the deepest floor can be without the exit
+        let map = MapGenerator::new((10, 10)).flat().generate();
+        assert!(map.validate(true).is_valid());
+        assert!(!map.validate(false).is_valid());
+    }
+
+    #[test]
+    fn noise() {
+        let noise = value_noise(Vec2d(40, 30), 8.0, 4);
+        assert!(noise.iter().all(|v| 0.0 <= *v && *v <= 1.0));
+        // Neighbor values are close
+        for p in noise.iter_idx() {
+            if let Some(v) = noise.get(p + (1, 0)) {
+                assert!((noise[p] - v).abs() < 0.5);
+            }
+        }
     }
 }
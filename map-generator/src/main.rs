@@ -0,0 +1,315 @@
+//! Preview generated maps to tune generator parameters
+
+extern crate rusted_ruins_array2d as array2d;
+extern crate rusted_ruins_map_generator as map_generator;
+extern crate rusted_ruins_rng as rng;
+
+use array2d::*;
+use image::{Rgb, RgbImage};
+use map_generator::{DecoKind, GeneratedMap, MapGenerator, TileKind, EIGHT_DIRS};
+use rng::Rng;
+use std::str::FromStr;
+
+/// Tile kinds of characters in example files for wfc
+const EXAMPLE_CHARS: [(char, TileKind); 5] = [
+    ('.', TileKind::Floor),
+    ('#', TileKind::Wall),
+    ('D', TileKind::Door),
+    ('~', TileKind::Water),
+    (' ', TileKind::Chasm),
+];
+
+fn main() {
+    let matches = create_matches();
+
+    let size = parse_size(matches.value_of("size").unwrap());
+    let count: u64 = parse_arg(matches.value_of("count").unwrap(), "count");
+    let scale: u32 = parse_arg(matches.value_of("scale").unwrap(), "scale");
+    if count == 0 || scale == 0 {
+        eprintln!("count and scale must be positive");
+        std::process::exit(1);
+    }
+    let seed: u64 = if let Some(seed) = matches.value_of("seed") {
+        parse_arg(seed, "seed")
+    } else {
+        rng::thread_rng().gen()
+    };
+    let params: Vec<&str> = matches
+        .value_of("params")
+        .map(|p| p.split(',').collect())
+        .unwrap_or_default();
+    let examples: Vec<Array2d<u32>> = matches
+        .values_of("example")
+        .map(|files| files.map(load_example).collect())
+        .unwrap_or_default();
+    let generator = create_generator(
+        matches.value_of("ALGORITHM").unwrap(),
+        size,
+        &params,
+        examples,
+    );
+
+    let mut maps = Vec::new();
+    for i in 0..count {
+        let seed = seed.wrapping_add(i);
+        rng::reseed_with(seed);
+        let map = generator.clone().generate();
+        if matches.value_of("output").is_none() {
+            println!("{}", map);
+        }
+        println!("seed {}: {}", seed, stats(&map));
+        maps.push(map);
+    }
+
+    if let Some(output) = matches.value_of("output") {
+        if let Err(e) = contact_sheet(&maps, scale).save(output) {
+            eprintln!("Cannot write \"{}\": {}", output, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn create_generator(
+    algorithm: &str,
+    size: Vec2d,
+    params: &[&str],
+    examples: Vec<Array2d<u32>>,
+) -> MapGenerator {
+    let mg = MapGenerator::new(size);
+    let p = |i: usize, default: &'static str| -> &str { params.get(i).cloned().unwrap_or(default) };
+    match algorithm {
+        "flat" => mg.flat(),
+        "lattice" => mg.lattice(
+            parse_arg(p(0, "5"), "nx"),
+            parse_arg(p(1, "4"), "ny"),
+            parse_arg(p(2, "3"), "step_min"),
+            parse_arg(p(3, "7"), "step_max"),
+            parse_arg(p(4, "0.5"), "door_weight"),
+        ),
+        "fractal" => mg.fractal(),
+        "bsp" => mg.bsp(
+            parse_arg(p(0, "3"), "min_room_size"),
+            parse_arg(p(1, "8"), "max_room_size"),
+            parse_arg(p(2, "0.7"), "door_weight"),
+        ),
+        "cave" => mg.cave(
+            parse_arg(p(0, "0.45"), "wall_ratio"),
+            parse_arg(p(1, "4"), "n_iteration"),
+            parse_arg(p(2, "0.42"), "water_ratio"),
+            parse_arg(p(3, "0.4"), "chasm_ratio"),
+            parse_arg(p(4, "0.05"), "rubble_ratio"),
+        ),
+        "wfc" => {
+            if examples.is_empty() {
+                eprintln!("wfc needs example files given by --example");
+                std::process::exit(1);
+            }
+            let kinds = EXAMPLE_CHARS.iter().map(|(_, kind)| *kind).collect();
+            mg.wfc(examples, kinds, parse_arg(p(0, "3"), "n"), None, None)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Floor ratio, the number of rooms and the path length from the entrance to the exit
+fn stats(map: &GeneratedMap) -> String {
+    let n_floor = map.tile.iter().filter(|t| t.is_passable()).count();
+    let floor_ratio = n_floor as f64 / (map.size.0 * map.size.1) as f64;
+    let path_length = map
+        .exit
+        .and_then(|exit| map.distance_map(map.entrance, &EIGHT_DIRS)[exit])
+        .map(|l| l.to_string())
+        .unwrap_or_else(|| "-".to_owned());
+    let report = map.validate(false);
+    let mut s = format!(
+        "floor {:.1}%, {} rooms, {} prefabs, path length {}, {}",
+        floor_ratio * 100.0,
+        map.rooms.len(),
+        map.prefabs.len(),
+        path_length,
+        report
+    );
+    if !report.is_valid() {
+        s.push_str(" (INVALID)");
+    }
+    s
+}
+
+fn tile_color(map: &GeneratedMap, p: Vec2d) -> Rgb<u8> {
+    if map.entrance == p {
+        Rgb([40, 200, 60])
+    } else if map.exit == Some(p) {
+        Rgb([220, 50, 40])
+    } else if map.deco[p] == Some(DecoKind::Rubble) {
+        Rgb([140, 125, 100])
+    } else {
+        match map.tile[p] {
+            TileKind::Floor => Rgb([200, 190, 170]),
+            TileKind::Wall => Rgb([70, 60, 55]),
+            TileKind::Door => Rgb([160, 100, 40]),
+            TileKind::Water => Rgb([60, 110, 210]),
+            TileKind::Chasm => Rgb([0, 0, 0]),
+        }
+    }
+}
+
+/// Render maps into a grid image. Each map is separated by one tile space.
+fn contact_sheet(maps: &[GeneratedMap], scale: u32) -> RgbImage {
+    let n_columns = (maps.len() as f64).sqrt().ceil() as u32;
+    let n_rows = (maps.len() as f64 / n_columns as f64).ceil() as u32;
+    let size = maps[0].size;
+    let cell_w = (size.0 as u32 + 1) * scale;
+    let cell_h = (size.1 as u32 + 1) * scale;
+    let mut img = RgbImage::from_pixel(
+        n_columns * cell_w + scale,
+        n_rows * cell_h + scale,
+        Rgb([30, 30, 30]),
+    );
+
+    for (i, map) in maps.iter().enumerate() {
+        let x0 = (i as u32 % n_columns) * cell_w + scale;
+        let y0 = (i as u32 / n_columns) * cell_h + scale;
+        for p in map.tile.iter_idx() {
+            let color = tile_color(map, p);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let x = x0 + p.0 as u32 * scale + dx;
+                    let y = y0 + p.1 as u32 * scale + dy;
+                    img.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+    img
+}
+
+/// Load an example map for wfc. Tiles are written in the same characters as the ASCII output.
+fn load_example(path: &str) -> Array2d<u32> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            eprintln!("Cannot read \"{}\": {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    let lines: Vec<Vec<char>> = text.lines().map(|l| l.chars().collect()).collect();
+    let w = lines.iter().map(|l| l.len()).max().unwrap_or(0);
+    let h = lines.len();
+    if w == 0 {
+        eprintln!("Example \"{}\" is empty", path);
+        std::process::exit(1);
+    }
+
+    Array2d::from_fn(w as u32, h as u32, |(x, y)| {
+        // Short lines are filled with walls
+        let c = lines[y as usize].get(x as usize).cloned().unwrap_or('#');
+        match EXAMPLE_CHARS
+            .iter()
+            .position(|(example_c, _)| *example_c == c)
+        {
+            Some(id) => id as u32,
+            None => {
+                eprintln!("Unknown tile character '{}' in \"{}\"", c, path);
+                std::process::exit(1);
+            }
+        }
+    })
+}
+
+fn parse_size(s: &str) -> Vec2d {
+    let wh: Vec<&str> = s.split('x').collect();
+    if wh.len() != 2 {
+        eprintln!("Invalid size \"{}\", expected WIDTHxHEIGHT", s);
+        std::process::exit(1);
+    }
+    Vec2d(parse_arg(wh[0], "width"), parse_arg(wh[1], "height"))
+}
+
+fn parse_arg<T: FromStr>(s: &str, name: &str) -> T {
+    match s.trim().parse() {
+        Ok(value) => value,
+        Err(_) => {
+            eprintln!("Invalid value \"{}\" for {}", s, name);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn create_matches() -> clap::ArgMatches<'static> {
+    use clap::{App, Arg};
+
+    App::new("rusted-ruins-map-preview")
+        .about("Preview maps created by the map generator of Rusted Ruins")
+        .after_help(
+            "PARAMETERS (comma separated, omitted ones use defaults):\n    \
+             lattice: nx,ny,step_min,step_max,door_weight (5,4,3,7,0.5)\n    \
+             bsp: min_room_size,max_room_size,door_weight (3,8,0.7)\n    \
+             cave: wall_ratio,n_iteration,water_ratio,chasm_ratio,rubble_ratio \
+             (0.45,4,0.42,0.4,0.05)\n    \
+             wfc: n (3)",
+        )
+        .arg(
+            Arg::with_name("size")
+                .short("s")
+                .long("size")
+                .value_name("WxH")
+                .help("Set map size")
+                .default_value("40x30"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("Set random seed. Batch generation uses the following seeds")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("params")
+                .short("p")
+                .long("params")
+                .value_name("PARAMS")
+                .help("Set comma separated parameters of the algorithm")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("example")
+                .short("e")
+                .long("example")
+                .value_name("FILE")
+                .help("Add an ASCII example map for wfc")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("count")
+                .short("n")
+                .long("count")
+                .value_name("N")
+                .help("Set the number of generated maps")
+                .default_value("1"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("Write maps into a png file instead of printing ASCII maps")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("scale")
+                .long("scale")
+                .value_name("PIXELS")
+                .help("Set the size of one tile in png output")
+                .default_value("4"),
+        )
+        .arg(
+            Arg::with_name("ALGORITHM")
+                .help("Map generation algorithm")
+                .possible_values(&["flat", "lattice", "fractal", "bsp", "cave", "wfc"])
+                .required(true)
+                .index(1),
+        )
+        .get_matches()
+}
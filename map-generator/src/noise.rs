@@ -0,0 +1,52 @@
+//! Fractal value noise to create natural terrain such as height maps
+
+use array2d::*;
+use rng::gen_range;
+
+/// Create noise in [0, 1]. Scale is the size of the largest features in tiles,
+/// and each octave adds features of the half size with the half amplitude.
+pub fn value_noise(size: Vec2d, scale: f64, n_octave: u32) -> Array2d<f64> {
+    let (w, h) = (size.0 as u32, size.1 as u32);
+    let mut noise = Array2d::new(w, h, 0.0);
+    let mut scale = scale.max(1.0);
+    let mut amplitude = 1.0;
+
+    for _ in 0..n_octave {
+        // Random values on the lattice points, interpolated between them
+        let lw = (w as f64 / scale).ceil() as u32 + 2;
+        let lh = (h as f64 / scale).ceil() as u32 + 2;
+        let lattice = Array2d::from_fn(lw, lh, |_| gen_range(0.0, 1.0));
+
+        for p in noise.iter_idx() {
+            let x = p.0 as f64 / scale;
+            let y = p.1 as f64 / scale;
+            let (x0, y0) = (x.floor() as i32, y.floor() as i32);
+            let fx = smoothstep(x - x0 as f64);
+            let fy = smoothstep(y - y0 as f64);
+            let v0 = lerp(lattice[(x0, y0)], lattice[(x0 + 1, y0)], fx);
+            let v1 = lerp(lattice[(x0, y0 + 1)], lattice[(x0 + 1, y0 + 1)], fx);
+            noise[p] += amplitude * lerp(v0, v1, fy);
+        }
+
+        amplitude *= 0.5;
+        scale = (scale * 0.5).max(1.0);
+    }
+
+    // Normalize to [0, 1]
+    let min = noise.iter().cloned().fold(std::f64::MAX, f64::min);
+    let max = noise.iter().cloned().fold(std::f64::MIN, f64::max);
+    if max > min {
+        for p in noise.iter_idx() {
+            noise[p] = (noise[p] - min) / (max - min);
+        }
+    }
+    noise
+}
+
+fn smoothstep(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
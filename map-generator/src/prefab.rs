@@ -0,0 +1,132 @@
+//! Place prefabs into generated maps.
+//! Prefab tiles are not changed after placing. Connection points of prefabs are
+//! connected to the map by corridors, and regions divided by prefabs are reconnected.
+
+use super::validate::FOUR_DIRS;
+use super::{GeneratedMap, PlacedPrefab, Prefab, TileKind};
+use array2d::*;
+use rng::gen_range;
+use std::collections::VecDeque;
+
+/// The number of trials to find the position of one prefab
+const MAX_TRY: usize = 20;
+
+pub fn place(gm: &mut GeneratedMap, prefabs: &[Prefab]) {
+    let mut protected = Array2d::new(gm.size.0 as u32, gm.size.1 as u32, false);
+
+    for (index, prefab) in prefabs.iter().enumerate() {
+        let top_left = match find_position(gm, prefab, &protected) {
+            Some(top_left) => top_left,
+            None => continue,
+        };
+        for (p, kind) in prefab.tile.iter_with_idx() {
+            gm.tile[top_left + p] = *kind;
+            gm.deco[top_left + p] = None;
+            protected[top_left + p] = true;
+        }
+        gm.prefabs.push(PlacedPrefab { index, top_left });
+    }
+
+    for placed in &gm.prefabs.clone() {
+        for c in &prefabs[placed.index].connections {
+            let c = placed.top_left + *c;
+            if gm.tile[c].is_passable() {
+                connect(gm, &[c], &protected);
+            }
+        }
+    }
+
+    // Reconnect regions divided by prefabs
+    loop {
+        let reach = gm.reach_map(gm.entrance, &FOUR_DIRS);
+        let isolated = gm
+            .tile
+            .iter_with_idx()
+            .find(|(p, t)| t.is_passable() && !reach[*p] && !protected[*p])
+            .map(|(p, _)| p);
+        let isolated = if let Some(isolated) = isolated {
+            isolated
+        } else {
+            break;
+        };
+        let region = gm.reach_map(isolated, &FOUR_DIRS);
+        let starts: Vec<Vec2d> = region
+            .iter_with_idx()
+            .filter(|(_, r)| **r)
+            .map(|(p, _)| p)
+            .collect();
+        if !connect(gm, &starts, &protected) {
+            // Fill the region if it cannot be connected
+            for p in starts {
+                if !protected[p] {
+                    gm.tile[p] = TileKind::Wall;
+                }
+            }
+        }
+    }
+}
+
+/// Find a position which does not overlap with other prefabs, the entrance and the exit
+fn find_position(gm: &GeneratedMap, prefab: &Prefab, protected: &Array2d<bool>) -> Option<Vec2d> {
+    let size = Vec2d(prefab.tile.size().0 as i32, prefab.tile.size().1 as i32);
+    if size.0 + 2 > gm.size.0 || size.1 + 2 > gm.size.1 {
+        return None;
+    }
+
+    for _ in 0..MAX_TRY {
+        let top_left = Vec2d(
+            gen_range(1, gm.size.0 - size.0),
+            gen_range(1, gm.size.1 - size.1),
+        );
+        let bottom_right = top_left + size - (1, 1);
+        // Keep one tile space around prefabs
+        let rect = RectIter::new(top_left - (1, 1), bottom_right + (1, 1));
+        let is_valid = rect.into_iter().all(|p| {
+            !protected.get(p).cloned().unwrap_or(false) && gm.entrance != p && gm.exit != Some(p)
+        });
+        if is_valid {
+            return Some(top_left);
+        }
+    }
+    None
+}
+
+/// Dig the shortest corridor from the start tiles to the region including the entrance.
+/// Returns false if the path is not found.
+fn connect(gm: &mut GeneratedMap, starts: &[Vec2d], protected: &Array2d<bool>) -> bool {
+    let reach = gm.reach_map(gm.entrance, &FOUR_DIRS);
+    let mut prev: Array2d<Option<Vec2d>> = Array2d::new(gm.size.0 as u32, gm.size.1 as u32, None);
+    let mut queue = VecDeque::new();
+    for p in starts {
+        if reach[*p] {
+            return true;
+        }
+        prev[*p] = Some(*p);
+        queue.push_back(*p);
+    }
+
+    while let Some(p) = queue.pop_front() {
+        for d in &FOUR_DIRS {
+            let q = p + *d;
+            let is_edge = q.0 <= 0 || q.1 <= 0 || q.0 >= gm.size.0 - 1 || q.1 >= gm.size.1 - 1;
+            if is_edge || prev[q].is_some() || protected[q] {
+                continue;
+            }
+            prev[q] = Some(p);
+            if !reach[q] {
+                queue.push_back(q);
+                continue;
+            }
+            // Dig back to the start
+            let mut p = p;
+            while prev[p] != Some(p) {
+                if !gm.tile[p].is_passable() {
+                    gm.tile[p] = TileKind::Floor;
+                }
+                p = prev[p].unwrap();
+            }
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,218 @@
+//! Validation of maps. Characters can move to the eight neighbor tiles.
+
+use super::GeneratedMap;
+use array2d::*;
+use std::collections::VecDeque;
+use std::fmt;
+
+/// Directions to the four neighbor tiles
+pub(crate) const FOUR_DIRS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+/// Directions to the eight neighbor tiles
+pub const EIGHT_DIRS: [(i32, i32); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// Problems and statistics of a map
+#[derive(Clone, Default, Debug)]
+pub struct MapReport {
+    pub n_passable: u32,
+    /// The number of passable tiles reachable from the entrance
+    pub n_reachable: u32,
+    /// Reachable tiles which have only one passable neighbor
+    pub n_dead_end: u32,
+    /// Reachable tiles which divide the map if they are blocked
+    pub chokepoints: Vec<Vec2d>,
+    pub problems: Vec<String>,
+}
+
+impl MapReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl fmt::Display for MapReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for problem in &self.problems {
+            writeln!(f, "{}", problem)?;
+        }
+        write!(
+            f,
+            "{}/{} tiles reachable, {} dead ends, {} chokepoints",
+            self.n_reachable,
+            self.n_passable,
+            self.n_dead_end,
+            self.chokepoints.len()
+        )
+    }
+}
+
+/// Check targets are reachable from the entrance, and collect statistics
+pub fn analyze(passable: &Array2d<bool>, entrance: Vec2d, targets: &[Vec2d]) -> MapReport {
+    let mut report = MapReport {
+        n_passable: passable.iter().filter(|p| **p).count() as u32,
+        ..MapReport::default()
+    };
+
+    if !passable.get(entrance).cloned().unwrap_or(false) {
+        report
+            .problems
+            .push(format!("entrance {} is not passable", entrance));
+        return report;
+    }
+
+    let reach = reach_map(passable, entrance);
+    report.n_reachable = reach.iter().filter(|r| **r).count() as u32;
+    for target in targets {
+        if !reach.get(*target).cloned().unwrap_or(false) {
+            report
+                .problems
+                .push(format!("{} is not reachable from the entrance", target));
+        }
+    }
+
+    report.n_dead_end = reach
+        .iter_with_idx()
+        .filter(|(p, r)| **r && neighbors(&reach, *p).count() == 1)
+        .count() as u32;
+    report.chokepoints = articulation_points(&reach, entrance);
+    report
+}
+
+impl GeneratedMap {
+    /// Check the exit is reachable from the entrance.
+    /// Maps except for the deepest floor must have the exit.
+    pub fn validate(&self, deepest_floor: bool) -> MapReport {
+        let passable = Array2d::from_fn(self.size.0 as u32, self.size.1 as u32, |p| {
+            self.tile[p].is_passable()
+        });
+        let targets: Vec<Vec2d> = self.exit.iter().cloned().collect();
+        let mut report = analyze(&passable, self.entrance, &targets);
+        if !deepest_floor && self.exit.is_none() {
+            report.problems.push("no exit".to_owned());
+        }
+        report
+    }
+
+    /// Walking distance from the start to each tile on passable tiles
+    pub fn distance_map(&self, start: Vec2d, dirs: &[(i32, i32)]) -> Array2d<Option<u32>> {
+        let size = (self.size.0 as u32, self.size.1 as u32);
+        distance_map(size, start, dirs, |p| {
+            self.tile.get(p).map(|t| t.is_passable()).unwrap_or(false)
+        })
+    }
+
+    /// Tiles reachable from the start tile
+    pub(crate) fn reach_map(&self, start: Vec2d, dirs: &[(i32, i32)]) -> Array2d<bool> {
+        let distance = self.distance_map(start, dirs);
+        Array2d::from_fn(self.size.0 as u32, self.size.1 as u32, |p| {
+            distance[p].is_some()
+        })
+    }
+}
+
+fn neighbors<'a>(passable: &'a Array2d<bool>, p: Vec2d) -> impl Iterator<Item = Vec2d> + 'a {
+    Direction::EIGHT_DIRS
+        .iter()
+        .map(move |dir| p + dir.as_vec())
+        .filter(move |q| passable.get(*q).cloned().unwrap_or(false))
+}
+
+/// Calculate tiles reachable from the start tile
+pub fn reach_map(passable: &Array2d<bool>, start: Vec2d) -> Array2d<bool> {
+    let (w, h) = passable.size();
+    let distance = distance_map(passable.size(), start, &EIGHT_DIRS, |p| {
+        passable.get(p).cloned().unwrap_or(false)
+    });
+    Array2d::from_fn(w, h, |p| distance[p].is_some())
+}
+
+/// Walking distance from the start to each tile by moving in the given directions.
+/// Unreachable tiles are None. Nothing is reachable if the start is not passable.
+pub(crate) fn distance_map<F: Fn(Vec2d) -> bool>(
+    size: (u32, u32),
+    start: Vec2d,
+    dirs: &[(i32, i32)],
+    passable: F,
+) -> Array2d<Option<u32>> {
+    let mut distance = Array2d::new(size.0, size.1, None);
+    if !passable(start) {
+        return distance;
+    }
+    let mut queue = VecDeque::new();
+    distance[start] = Some(0);
+    queue.push_back(start);
+
+    while let Some(p) = queue.pop_front() {
+        let d = distance[p].unwrap();
+        for dir in dirs {
+            let q = p + *dir;
+            if passable(q) && distance[q].is_none() {
+                distance[q] = Some(d + 1);
+                queue.push_back(q);
+            }
+        }
+    }
+    distance
+}
+
+/// Find articulation points by depth first search without recursion
+fn articulation_points(reach: &Array2d<bool>, root: Vec2d) -> Vec<Vec2d> {
+    let (w, h) = reach.size();
+    let mut order: Array2d<Option<u32>> = Array2d::new(w, h, None);
+    let mut low = Array2d::new(w, h, 0);
+    let mut is_articulation = Array2d::new(w, h, false);
+    let mut n_root_children = 0;
+    let mut count = 0;
+
+    // (tile, parent, neighbors not visited yet)
+    let mut stack: Vec<(Vec2d, Option<Vec2d>, Vec<Vec2d>)> = Vec::new();
+    order[root] = Some(count);
+    low[root] = count;
+    stack.push((root, None, neighbors(reach, root).collect()));
+
+    while let Some((p, parent, remaining)) = stack.last_mut() {
+        let p = *p;
+        let parent = *parent;
+        if let Some(q) = remaining.pop() {
+            if Some(q) == parent {
+                continue;
+            }
+            if let Some(o) = order[q] {
+                low[p] = std::cmp::min(low[p], o);
+            } else {
+                count += 1;
+                order[q] = Some(count);
+                low[q] = count;
+                stack.push((q, Some(p), neighbors(reach, q).collect()));
+            }
+            continue;
+        }
+
+        stack.pop();
+        if let Some(parent) = parent {
+            low[parent] = std::cmp::min(low[parent], low[p]);
+            if parent == root {
+                n_root_children += 1;
+            } else if low[p] >= order[parent].unwrap() {
+                is_articulation[parent] = true;
+            }
+        }
+    }
+    if n_root_children > 1 {
+        is_articulation[root] = true;
+    }
+
+    is_articulation
+        .iter_with_idx()
+        .filter(|(_, a)| **a)
+        .map(|(p, _)| p)
+        .collect()
+}
@@ -0,0 +1,398 @@
+//! Map generation by wave function collapse with the overlapping model.
+//! NxN patterns and their overlaps are learned from example maps,
+//! and new maps are synthesized so that every NxN area is one of the patterns.
+
+use super::fractal::create_reach_map;
+use super::{GeneratedMap, TileKind};
+use array2d::*;
+use rng::gen_range;
+use std::collections::HashMap;
+
+/// The number of retries when contradiction occurs or the result is not connected
+const MAX_TRY: usize = 20;
+
+const DIRS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+fn opposite(d: usize) -> usize {
+    d ^ 1
+}
+
+pub struct Patterns {
+    n: i32,
+    /// Tile ids in each pattern
+    tiles: Vec<Vec<u32>>,
+    weights: Vec<f64>,
+    /// Compatible patterns for each pattern and direction
+    propagator: Vec<[Vec<usize>; 4]>,
+}
+
+impl Patterns {
+    /// Extract all NxN patterns in the examples
+    pub fn learn(examples: &[Array2d<u32>], n: u32) -> Patterns {
+        let n = n as i32;
+        let mut index: HashMap<Vec<u32>, usize> = HashMap::new();
+        let mut tiles = Vec::new();
+        let mut weights = Vec::new();
+
+        for example in examples {
+            let (w, h) = (example.size().0 as i32, example.size().1 as i32);
+            for y in 0..=(h - n) {
+                for x in 0..=(w - n) {
+                    let pattern: Vec<u32> = RectIter::new((x, y), (x + n - 1, y + n - 1))
+                        .map(|p| example[p])
+                        .collect();
+                    if let Some(&i) = index.get(&pattern) {
+                        weights[i] += 1.0;
+                    } else {
+                        index.insert(pattern.clone(), tiles.len());
+                        tiles.push(pattern);
+                        weights.push(1.0);
+                    }
+                }
+            }
+        }
+
+        let mut patterns = Patterns {
+            n,
+            tiles,
+            weights,
+            propagator: Vec::new(),
+        };
+        patterns.propagator = (0..patterns.len())
+            .map(|a| {
+                let mut compatible: [Vec<usize>; 4] = Default::default();
+                for (d, dir) in DIRS.iter().enumerate() {
+                    compatible[d] = (0..patterns.len())
+                        .filter(|b| patterns.agrees(a, *b, Vec2d::from(*dir)))
+                        .collect();
+                }
+                compatible
+            })
+            .collect();
+        patterns
+    }
+
+    fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    fn tile(&self, pattern: usize, p: Vec2d) -> u32 {
+        self.tiles[pattern][(p.1 * self.n + p.0) as usize]
+    }
+
+    /// Pattern b can be placed at the offset d from pattern a
+    fn agrees(&self, a: usize, b: usize, d: Vec2d) -> bool {
+        let n = self.n;
+        for y in 0..n {
+            for x in 0..n {
+                let q = Vec2d(x, y) - d;
+                if 0 <= q.0
+                    && q.0 < n
+                    && 0 <= q.1
+                    && q.1 < n
+                    && self.tile(a, Vec2d(x, y)) != self.tile(b, q)
+                {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+struct Wave<'a> {
+    patterns: &'a Patterns,
+    size: Vec2d,
+    possible: Vec<Vec<bool>>,
+    /// The number of supporting patterns in the neighbor for each cell, pattern and direction
+    support: Vec<Vec<[u32; 4]>>,
+    n_possible: Vec<usize>,
+    /// Sums of w and w*log(w) of possible patterns to calculate entropy
+    sum_weight: Vec<f64>,
+    sum_weight_log: Vec<f64>,
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'a> Wave<'a> {
+    fn new(patterns: &'a Patterns, size: Vec2d) -> Wave<'a> {
+        let n_cell = (size.0 * size.1) as usize;
+        let t = patterns.len();
+        let init_support: Vec<[u32; 4]> = (0..t)
+            .map(|a| {
+                let mut s = [0; 4];
+                for d in 0..4 {
+                    s[d] = patterns.propagator[a][opposite(d)].len() as u32;
+                }
+                s
+            })
+            .collect();
+        let sum_weight: f64 = patterns.weights.iter().sum();
+        let sum_weight_log: f64 = patterns.weights.iter().map(|w| w * w.ln()).sum();
+        let mut wave = Wave {
+            patterns,
+            size,
+            possible: vec![vec![true; t]; n_cell],
+            support: vec![init_support; n_cell],
+            n_possible: vec![t; n_cell],
+            sum_weight: vec![sum_weight; n_cell],
+            sum_weight_log: vec![sum_weight_log; n_cell],
+            stack: Vec::new(),
+        };
+
+        // Patterns at the edges of examples cannot have neighbors in some directions
+        for p in size.iter_from_zero() {
+            let cell = wave.cell(p);
+            for (d, dir) in DIRS.iter().enumerate() {
+                let q = p - *dir;
+                if q.0 < 0 || q.1 < 0 || q.0 >= size.0 || q.1 >= size.1 {
+                    continue;
+                }
+                for b in 0..t {
+                    if wave.support[cell][b][d] == 0 {
+                        wave.ban(cell, b);
+                    }
+                }
+            }
+        }
+        wave
+    }
+
+    fn cell(&self, p: Vec2d) -> usize {
+        (p.1 * self.size.0 + p.0) as usize
+    }
+
+    fn ban(&mut self, cell: usize, pattern: usize) {
+        if !self.possible[cell][pattern] {
+            return;
+        }
+        self.possible[cell][pattern] = false;
+        self.n_possible[cell] -= 1;
+        let w = self.patterns.weights[pattern];
+        self.sum_weight[cell] -= w;
+        self.sum_weight_log[cell] -= w * w.ln();
+        self.stack.push((cell, pattern));
+    }
+
+    /// Returns false if contradiction occurs
+    fn propagate(&mut self) -> bool {
+        let patterns = self.patterns;
+        while let Some((cell, a)) = self.stack.pop() {
+            let p = Vec2d(cell as i32 % self.size.0, cell as i32 / self.size.0);
+            for (d, dir) in DIRS.iter().enumerate() {
+                let q = p + *dir;
+                if q.0 < 0 || q.1 < 0 || q.0 >= self.size.0 || q.1 >= self.size.1 {
+                    continue;
+                }
+                let neighbor = self.cell(q);
+                for &b in &patterns.propagator[a][d] {
+                    let s = &mut self.support[neighbor][b][d];
+                    *s -= 1;
+                    if *s == 0 {
+                        self.ban(neighbor, b);
+                    }
+                }
+            }
+            if self.n_possible[cell] == 0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Collapse the cell with the lowest entropy. Returns false if all cells are collapsed.
+    fn observe(&mut self) -> bool {
+        let patterns = self.patterns;
+        let weights = &patterns.weights;
+        let mut min: Option<(usize, f64)> = None;
+        for cell in 0..self.possible.len() {
+            if self.n_possible[cell] <= 1 {
+                continue;
+            }
+            let sum = self.sum_weight[cell];
+            let entropy = sum.ln() - self.sum_weight_log[cell] / sum + gen_range(0.0, 1e-6);
+            if min.map(|(_, e)| entropy < e).unwrap_or(true) {
+                min = Some((cell, entropy));
+            }
+        }
+        let cell = if let Some((cell, _)) = min {
+            cell
+        } else {
+            return false;
+        };
+
+        let mut r = gen_range(0.0, self.sum_weight[cell]);
+        let mut chosen = 0;
+        for t in (0..weights.len()).filter(|t| self.possible[cell][*t]) {
+            chosen = t;
+            r -= weights[t];
+            if r < 0.0 {
+                break;
+            }
+        }
+        for t in 0..weights.len() {
+            if t != chosen {
+                self.ban(cell, t);
+            }
+        }
+        true
+    }
+
+    /// Allow only patterns which have passable tiles at the given position
+    fn require_passable(&mut self, p: Vec2d, kinds: &[TileKind]) {
+        let n = self.patterns.n;
+        let wave_p = Vec2d(
+            std::cmp::min(p.0, self.size.0 - 1),
+            std::cmp::min(p.1, self.size.1 - 1),
+        );
+        let offset = p - wave_p;
+        let cell = self.cell(wave_p);
+        debug_assert!(offset.0 < n && offset.1 < n);
+        for t in 0..self.patterns.len() {
+            let id = self.patterns.tile(t, offset);
+            if !kind_of(kinds, id).is_passable() {
+                self.ban(cell, t);
+            }
+        }
+    }
+
+    /// Tile ids of the result. Cells near the right and bottom edges are taken from the last patterns.
+    fn result(&self, map_size: Vec2d) -> Array2d<u32> {
+        Array2d::from_fn(map_size.0 as u32, map_size.1 as u32, |(x, y)| {
+            let p = Vec2d(x as i32, y as i32);
+            let wave_p = Vec2d(
+                std::cmp::min(p.0, self.size.0 - 1),
+                std::cmp::min(p.1, self.size.1 - 1),
+            );
+            let cell = self.cell(wave_p);
+            let t = self.possible[cell].iter().position(|a| *a).unwrap_or(0);
+            self.patterns.tile(t, p - wave_p)
+        })
+    }
+}
+
+fn kind_of(kinds: &[TileKind], id: u32) -> TileKind {
+    kinds.get(id as usize).cloned().unwrap_or(TileKind::Wall)
+}
+
+pub fn write_to_map(
+    gm: &mut GeneratedMap,
+    examples: &[Array2d<u32>],
+    kinds: &[TileKind],
+    n: u32,
+    entrance: Option<Vec2d>,
+    exit: Option<Vec2d>,
+) {
+    // Positions out of the map are chosen randomly instead
+    let entrance = entrance.filter(|p| gm.tile.in_range(*p));
+    let exit = exit.filter(|p| gm.tile.in_range(*p) && entrance != Some(*p));
+
+    let patterns = Patterns::learn(examples, n);
+    let wave_size = Vec2d(gm.size.0 - n as i32 + 1, gm.size.1 - n as i32 + 1);
+    if patterns.len() == 0 || wave_size.0 <= 0 || wave_size.1 <= 0 {
+        fill_flat(gm, entrance, exit);
+        return;
+    }
+
+    for _ in 0..MAX_TRY {
+        let mut wave = Wave::new(&patterns, wave_size);
+        for p in entrance.iter().chain(exit.iter()) {
+            wave.require_passable(*p, kinds);
+        }
+        let mut ok = wave.propagate();
+        while ok && wave.observe() {
+            ok = wave.propagate();
+        }
+        if !ok {
+            continue;
+        }
+
+        let ids = wave.result(gm.size);
+        for p in gm.tile.iter_idx() {
+            gm.tile[p] = kind_of(kinds, ids[p]);
+        }
+        gm.source_tile = Some(ids);
+        if set_entrance_exit(gm, entrance, exit) {
+            return;
+        }
+    }
+    fill_flat(gm, entrance, exit);
+}
+
+/// Set the entrance and exit, and check they are connected
+fn set_entrance_exit(gm: &mut GeneratedMap, entrance: Option<Vec2d>, exit: Option<Vec2d>) -> bool {
+    let passable: Vec<Vec2d> = gm
+        .tile
+        .iter_with_idx()
+        .filter(|(_, t)| t.is_passable())
+        .map(|(p, _)| p)
+        .collect();
+    if passable.len() < 2 {
+        return false;
+    }
+    gm.entrance = entrance.unwrap_or_else(|| passable[gen_range(0, passable.len())]);
+
+    let (reach_map, n_reachable) = create_reach_map(gm, gm.entrance);
+    // Too small region from the entrance
+    if (n_reachable as usize) < passable.len() / 2 {
+        return false;
+    }
+    let exit = if let Some(exit) = exit {
+        exit
+    } else {
+        let reachable: Vec<&Vec2d> = passable
+            .iter()
+            .filter(|p| reach_map[**p] && **p != gm.entrance)
+            .collect();
+        if reachable.is_empty() {
+            return false;
+        }
+        *reachable[gen_range(0, reachable.len())]
+    };
+    if !reach_map[exit] {
+        return false;
+    }
+    gm.exit = Some(exit);
+
+    // Wall off unreachable tiles
+    for p in gm.tile.iter_idx() {
+        if !reach_map[p] && gm.tile[p].is_passable() {
+            gm.tile[p] = TileKind::Wall;
+        }
+    }
+    true
+}
+
+/// Fallback if generation failed
+fn fill_flat(gm: &mut GeneratedMap, entrance: Option<Vec2d>, exit: Option<Vec2d>) {
+    for p in gm.tile.iter_idx() {
+        gm.tile[p] = TileKind::Floor;
+    }
+    gm.source_tile = None;
+
+    let size = gm.size;
+    let random_pos = || Vec2d(gen_range(0, size.0), gen_range(0, size.1));
+    gm.entrance = entrance.unwrap_or_else(random_pos);
+    gm.exit = if exit.is_some() {
+        exit
+    } else if size.0 * size.1 >= 2 {
+        loop {
+            let p = random_pos();
+            if p != gm.entrance {
+                break Some(p);
+            }
+        }
+    } else {
+        None
+    };
+}
+
+#[test]
+fn isolated_entrance() {
+    use super::MapGenerator;
+
+    // Two floor tiles separated by a wall
+    let mut gm = MapGenerator::new((3, 1)).flat().generate();
+    gm.tile[Vec2d(1, 0)] = TileKind::Wall;
+    assert!(!set_entrance_exit(&mut gm, Some(Vec2d(0, 0)), None));
+    assert!(!set_entrance_exit(&mut gm, None, None));
+}
@@ -49,6 +49,23 @@ pub fn reseed() {
     })
 }
 
+/// Reseed with the given seed to reproduce the same random sequence
+pub fn reseed_with(seed: u64) {
+    XORSHIFT_RNG.with(|xorshift_rng| {
+        xorshift_rng.replace(XorShiftRng::seed_from_u64(seed));
+    })
+}
+
+/// Call the function with the given seed, and restore the previous random state after that.
+/// Used to regenerate the same result from a stored seed.
+pub fn with_seed<T, F: FnOnce() -> T>(seed: u64, f: F) -> T {
+    let prev =
+        XORSHIFT_RNG.with(|xorshift_rng| xorshift_rng.replace(XorShiftRng::seed_from_u64(seed)));
+    let result = f();
+    XORSHIFT_RNG.with(|xorshift_rng| xorshift_rng.replace(prev));
+    result
+}
+
 pub fn next_u32() -> u32 {
     let mut rng = GameRng;
     rng.next_u32()
@@ -6,6 +6,10 @@ use std::collections::HashMap;
 pub struct Chara {
     /// Attribute revisions by class
     pub class_revision: HashMap<CharaClass, CharaAttrRevision>,
+    /// Attribute increase per level-up by class
+    pub level_up_revision: HashMap<CharaClass, CharaAttrRevision>,
+    /// Skill exp is multiplied by these values by class
+    pub skill_aptitude: HashMap<CharaClass, Vec<(SkillKind, f32)>>,
     /// Default value of CharaParams::view_range.
     /// The actual value will be adjusted by character traits, and map attributes, etc.
     pub default_view_range: i32,
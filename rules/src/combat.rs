@@ -0,0 +1,6 @@
+/// Rules for combat
+#[derive(Serialize, Deserialize)]
+pub struct Combat {
+    /// The probability that shot ammunition is dropped and can be picked up again
+    pub ammo_recover_probability: f64,
+}
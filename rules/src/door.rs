@@ -0,0 +1,6 @@
+/// Rules for doors
+#[derive(Serialize, Deserialize)]
+pub struct Door {
+    /// Lock level is floor number + this value
+    pub base_lock_level: u32,
+}
@@ -1,5 +1,6 @@
 use array2d::Vec2d;
 use common::gamedata::*;
+use common::regiongen::Biome;
 use std::collections::HashMap;
 
 /// Rules for map generation
@@ -9,12 +10,112 @@ pub type DungeonGen = HashMap<DungeonKind, DungeonGenParams>;
 pub struct DungeonGenParams {
     /// Default map size
     pub map_size: Vec2d,
+    /// Map generation algorithm and its parameters
+    #[serde(default)]
+    pub map_gen: MapGenParams,
     /// The probability of npc generation for each race
     pub npc_race_probability: HashMap<Race, f32>,
-    /// Tile and wall ids
-    pub terrain: Vec<[String; 2]>,
+    /// Terrain sets. One of them is chosen for each floor.
+    pub terrain: Vec<Terrain>,
     /// Items generatation probability on each tile
     pub item_gen_probability: f64,
     /// The range of number of floor of auto generated dungeons
     pub floor_range: [u32; 2],
+    /// The range of number of traps on each floor
+    pub trap_range: [u32; 2],
+    /// The probability of trap generation for each kind
+    pub trap_kind_probability: HashMap<TrapKind, f32>,
+    /// The probability of each door is locked
+    pub locked_door_probability: f64,
+    /// Prefabs placed into generated maps
+    #[serde(default)]
+    pub prefabs: Vec<PrefabParams>,
+    /// Biomes where dungeons of this kind appear on generated regions. Any land biome if empty.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    /// Days until a cleared dungeon disappears. Cleared dungeons remain if not given.
+    #[serde(default)]
+    pub expire_days: Option<u32>,
+    /// Enemies are generated again on floors the player has not visited for these days
+    #[serde(default)]
+    pub repopulate_days: Option<u32>,
+}
+
+/// Map template placed into generated maps
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PrefabParams {
+    /// Map template id
+    pub template: String,
+    /// At most one prefab is placed for each tag on a floor
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Placed on this floor or deeper
+    #[serde(default)]
+    pub min_floor: u32,
+    /// Placed once in this number of floors on average
+    pub rarity: u32,
+    /// Connection points in the template. Passable tiles on the edges are used if empty.
+    #[serde(default)]
+    pub connections: Vec<Vec2d>,
+}
+
+/// Object ids used for generated tiles
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Terrain {
+    pub tile: String,
+    pub wall: String,
+    /// Tile for water pools
+    #[serde(default)]
+    pub water: Option<String>,
+    /// Tile for chasms
+    #[serde(default)]
+    pub chasm: Option<String>,
+    /// Decos for rubble
+    #[serde(default)]
+    pub rubble: Vec<String>,
+}
+
+/// Map generation algorithm and its parameters
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum MapGenParams {
+    Flat,
+    Lattice {
+        nx: u32,
+        ny: u32,
+        step_min: u32,
+        step_max: u32,
+        door_weight: f64,
+    },
+    Fractal,
+    /// Rooms and corridors by binary space partitioning
+    Bsp {
+        min_room_size: u32,
+        max_room_size: u32,
+        door_weight: f64,
+    },
+    /// Caves by cellular automata with water pools, chasms and rubble
+    Cave {
+        wall_ratio: f64,
+        n_iteration: u32,
+        water_ratio: f64,
+        chasm_ratio: f64,
+        rubble_ratio: f64,
+    },
+    /// Wave function collapse learning NxN patterns from map templates
+    Wfc {
+        templates: Vec<String>,
+        n: u32,
+        /// Fixed entrance position
+        #[serde(default)]
+        entrance: Option<Vec2d>,
+        /// Fixed exit position
+        #[serde(default)]
+        exit: Option<Vec2d>,
+    },
+}
+
+impl Default for MapGenParams {
+    fn default() -> MapGenParams {
+        MapGenParams::Fractal
+    }
 }
@@ -0,0 +1,48 @@
+use crate::dungeon_gen::{MapGenParams, Terrain};
+use array2d::Vec2d;
+use common::gamedata::*;
+use common::regiongen::Biome;
+use std::collections::HashMap;
+
+/// Rules for random encounters on region maps
+#[derive(Serialize, Deserialize)]
+pub struct Encounter {
+    /// The probability of an encounter for each move on region maps
+    pub probability: f64,
+    /// The probability is increased by this ratio for each danger level of the region
+    pub danger_factor: f64,
+    /// The probability is multiplied by this value at night
+    pub night_factor: f64,
+    /// Night starts at the first hour and ends at the second hour
+    pub night_hours: [u16; 2],
+    /// Enemy level is this value + danger level * level_per_danger
+    pub base_level: u32,
+    pub level_per_danger: u32,
+    /// Size of encounter maps
+    pub map_size: Vec2d,
+    /// Encounter tables. One of them is chosen by the terrain, danger level and time of day.
+    pub tables: HashMap<String, EncounterTable>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EncounterTable {
+    /// Biomes where this encounter happens. Any biome if empty.
+    #[serde(default)]
+    pub biomes: Vec<Biome>,
+    /// Weight to choose this table in the daytime
+    pub weight: f32,
+    /// Weight to choose this table at night
+    pub night_weight: f32,
+    /// This encounter happens in regions of this danger level or higher
+    #[serde(default)]
+    pub min_danger_level: u32,
+    /// The probability of npc generation for each race
+    pub npc_race_probability: HashMap<Race, f32>,
+    /// The range of number of enemies
+    pub n_npc: [u32; 2],
+    /// Map generation algorithm and its parameters
+    #[serde(default)]
+    pub map_gen: MapGenParams,
+    /// Terrain sets. One of them is chosen for each encounter.
+    pub terrain: Vec<Terrain>,
+}
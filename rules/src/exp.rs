@@ -13,4 +13,14 @@ pub struct Exp {
     pub endurance: u32,
     /// Base exp to Evasion skill when attacked
     pub evasion: u32,
+    /// Base exp to Searching skill when a trap is detected
+    pub detect_trap: u32,
+    /// Base exp to Searching skill when a trap is disarmed
+    pub disarm_trap: u32,
+    /// Base exp to Lockpicking skill when a lock is picked
+    pub lockpick: u32,
+    /// Base character exp when killing an enemy. Multiplied by the enemy's level
+    pub kill: u32,
+    /// Character exp required to level up is this value * current level
+    pub chara_lvup: u32,
 }
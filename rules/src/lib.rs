@@ -7,13 +7,18 @@ extern crate rusted_ruins_common as common;
 
 pub mod chara;
 pub mod charagen;
+pub mod combat;
+pub mod door;
 pub mod dungeon_gen;
+pub mod encounter;
 pub mod exp;
 pub mod newgame;
 pub mod params;
 pub mod quest;
 pub mod town;
+pub mod trap;
 
+use common::reloadable::Reloadable;
 use lazy_static::lazy_static;
 use serde::de::Deserialize;
 use std::fs;
@@ -24,62 +29,194 @@ use std::sync::Mutex;
 pub struct Rules {
     pub chara: chara::Chara,
     pub chara_gen: charagen::CharaGen,
+    pub combat: combat::Combat,
+    pub door: door::Door,
     pub dungeon_gen: dungeon_gen::DungeonGen,
+    pub encounter: encounter::Encounter,
     pub exp: exp::Exp,
     pub newgame: newgame::NewGame,
     pub params: params::Params,
     pub quest: quest::Quest,
     pub town: town::Town,
+    pub trap: trap::Trap,
 }
 
 impl Rules {
-    fn load_from_dir(rules_dir: &Path) -> Rules {
-        Rules {
-            chara: read_from_json(&rules_dir.join("chara.json")),
-            chara_gen: read_from_json(&rules_dir.join("charagen.json")),
-            dungeon_gen: read_from_json(&rules_dir.join("dungeon_gen.json")),
-            exp: read_from_json(&rules_dir.join("exp.json")),
-            newgame: read_from_json(&rules_dir.join("newgame.json")),
-            params: read_from_json(&rules_dir.join("params.json")),
-            quest: read_from_json(&rules_dir.join("quest.json")),
-            town: read_from_json(&rules_dir.join("town.json")),
+    fn load_from_dirs(rules_dirs: &[PathBuf]) -> Result<Rules, RulesLoadError> {
+        Ok(Rules {
+            chara: read_from_json(rules_dirs, "chara.json")?,
+            chara_gen: read_from_json(rules_dirs, "charagen.json")?,
+            combat: read_from_json(rules_dirs, "combat.json")?,
+            door: read_from_json(rules_dirs, "door.json")?,
+            dungeon_gen: read_from_json(rules_dirs, "dungeon_gen.json")?,
+            encounter: read_from_json(rules_dirs, "encounter.json")?,
+            exp: read_from_json(rules_dirs, "exp.json")?,
+            newgame: read_from_json(rules_dirs, "newgame.json")?,
+            params: read_from_json(rules_dirs, "params.json")?,
+            quest: read_from_json(rules_dirs, "quest.json")?,
+            town: read_from_json(rules_dirs, "town.json")?,
+            trap: read_from_json(rules_dirs, "trap.json")?,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct RulesLoadError {
+    pub file_path: PathBuf,
+    pub description: String,
+}
+
+impl std::fmt::Display for RulesLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {}",
+            self.file_path.to_string_lossy(),
+            self.description
+        )
+    }
+}
+
+impl std::error::Error for RulesLoadError {}
+
+/// Read the rule file in the first directory,
+/// and apply the files in the other directories as JSON merge patches if exist.
+fn read_from_json<T: for<'de> Deserialize<'de>>(
+    rules_dirs: &[PathBuf],
+    file_name: &str,
+) -> Result<T, RulesLoadError> {
+    let mut value = read_json_value(&rules_dirs[0].join(file_name))?;
+
+    for dir in &rules_dirs[1..] {
+        let patch_path = dir.join(file_name);
+        if patch_path.exists() {
+            let patch = read_json_value(&patch_path)?;
+            merge_patch(&mut value, patch);
         }
     }
+
+    serde_json::from_value(value).map_err(|e| RulesLoadError {
+        file_path: PathBuf::from(file_name),
+        description: e.to_string(),
+    })
 }
 
-fn read_from_json<T: for<'de> Deserialize<'de>>(file_path: &Path) -> T {
+fn read_json_value(file_path: &Path) -> Result<serde_json::Value, RulesLoadError> {
     info!("Rule file loading: \"{}\"", file_path.to_string_lossy());
-    let file = match fs::File::open(file_path) {
-        Ok(o) => o,
-        Err(e) => {
-            error!("{}", e);
-            exit_err()
-        }
+    let error = |description: String| RulesLoadError {
+        file_path: file_path.to_owned(),
+        description,
     };
-    match serde_json::from_reader(file) {
-        Ok(o) => o,
+    let file = fs::File::open(file_path).map_err(|e| error(e.to_string()))?;
+    serde_json::from_reader(file).map_err(|e| error(e.to_string()))
+}
+
+/// Apply JSON merge patch (RFC 7396). Null values in the patch remove fields.
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    use serde_json::Value;
+
+    if let Value::Object(patch) = patch {
+        if !target.is_object() {
+            *target = Value::Object(serde_json::Map::new());
+        }
+        let target = target.as_object_mut().unwrap();
+        for (k, v) in patch {
+            if v.is_null() {
+                target.remove(&k);
+            } else {
+                merge_patch(target.entry(k).or_insert(Value::Null), v);
+            }
+        }
+    } else {
+        *target = patch;
+    }
+}
+
+lazy_static! {
+    static ref RULES_DIRS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+}
+
+/// Global state rules holder
+pub static RULES: Reloadable<Rules> = Reloadable::new();
+
+/// Initialize Rules.
+/// The first directory is the application directory, and the others are addon directories.
+pub fn init<P: AsRef<Path>>(data_dirs: &[P]) {
+    let rules_dirs: Vec<PathBuf> = data_dirs
+        .iter()
+        .map(|dir| dir.as_ref().join("rules"))
+        .collect();
+
+    match Rules::load_from_dirs(&rules_dirs) {
+        Ok(rules) => {
+            RULES.set(rules);
+        }
         Err(e) => {
             error!("{}", e);
-            exit_err()
+            std::process::exit(1);
         }
     }
-}
 
-fn exit_err() -> ! {
-    std::process::exit(1)
+    *RULES_DIRS.lock().unwrap() = rules_dirs;
 }
 
-lazy_static! {
-    static ref RULES_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
-    /// Global state rules holder
-    pub static ref RULES: Rules = Rules::load_from_dir(&RULES_DIR.lock().unwrap().as_ref().unwrap());
+/// Reload rules from the directories given at initialization.
+/// The current rules are kept if loading fails.
+pub fn reload() -> Result<(), RulesLoadError> {
+    let rules = Rules::load_from_dirs(&RULES_DIRS.lock().unwrap())?;
+    RULES.set(rules);
+    Ok(())
 }
 
-/// Initialize Rules
-pub fn init<P: AsRef<Path>>(app_dirs: P) {
-    let rules_dir = app_dirs.as_ref().join("rules");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merge_patch_nested() {
+        let mut target = json!({
+            "a": 1,
+            "b": { "c": 2, "d": 3 },
+            "e": [1, 2],
+        });
+        merge_patch(
+            &mut target,
+            json!({
+                "b": { "c": 4, "f": 5 },
+                "e": [3],
+                "g": { "h": 6 },
+            }),
+        );
+        assert_eq!(
+            target,
+            json!({
+                "a": 1,
+                "b": { "c": 4, "d": 3, "f": 5 },
+                "e": [3],
+                "g": { "h": 6 },
+            })
+        );
+    }
+
+    #[test]
+    fn merge_patch_null_removes() {
+        let mut target = json!({ "a": 1, "b": { "c": 2, "d": 3 } });
+        merge_patch(
+            &mut target,
+            json!({ "a": null, "b": { "c": null }, "x": null }),
+        );
+        assert_eq!(target, json!({ "b": { "d": 3 } }));
+    }
 
-    *RULES_DIR.lock().unwrap() = Some(rules_dir);
+    #[test]
+    fn merge_patch_non_object() {
+        let mut target = json!({ "a": 1 });
+        merge_patch(&mut target, json!(2));
+        assert_eq!(target, json!(2));
 
-    lazy_static::initialize(&RULES);
+        let mut target = json!(2);
+        merge_patch(&mut target, json!({ "a": { "b": null } }));
+        assert_eq!(target, json!({ "a": {} }));
+    }
 }
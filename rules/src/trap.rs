@@ -0,0 +1,20 @@
+/// Rules for traps
+#[derive(Serialize, Deserialize)]
+pub struct Trap {
+    /// The range (manhattan distance) in which the player searches traps every turn
+    pub search_range: i32,
+    /// The probability of detection is multiplied by this value
+    pub detect_factor: f64,
+    /// Trap level is floor number + this value
+    pub base_level: u32,
+    /// The probability of triggering a trap when disarming is failed
+    pub disarm_failure_trigger: f64,
+    /// Dice (n, x) of damage trap
+    pub damage_dice: [i32; 2],
+    /// Additional damage per trap level
+    pub damage_per_level: i32,
+    /// Turns to sleep by sleep gas trap
+    pub sleep_turn: u16,
+    /// The number of monsters called by alarm trap
+    pub alarm_n_npc: u32,
+}
@@ -3,6 +3,7 @@ pub mod input;
 pub mod visual;
 
 use crate::util::read_file_as_string;
+use common::addon::{self, Addon};
 use common::basic;
 use std::env;
 use std::path::PathBuf;
@@ -54,7 +55,18 @@ pub fn init() {
 lazy_static! {
     pub static ref APP_DIR: PathBuf = get_app_dir().expect("Cannot get data directory path");
     pub static ref USER_DIR: PathBuf = get_user_dir();
-    pub static ref ADDON_DIR: Option<PathBuf> = get_addon_dir();
+    pub static ref ADDON_DIR: PathBuf = get_addon_dir();
+    /// Enabled addon directories in the load order
+    pub static ref ADDON_DIRS: Vec<PathBuf> = {
+        let addons = load_addon_list();
+        addon::load_order(&addons)
+            .into_iter()
+            .map(|a| {
+                info!("Addon \"{}\" {} is enabled", a.manifest.name, a.manifest.version);
+                a.dir.clone()
+            })
+            .collect()
+    };
     pub static ref CONFIG: Config = load_config_file!("config.toml");
     pub static ref SCREEN_CFG: visual::ScreenConfig = load_config_file!("screen/800x600.toml");
     pub static ref UI_CFG: visual::UIConfig = load_config_file!("ui.toml");
@@ -92,25 +104,61 @@ fn get_user_dir() -> PathBuf {
     path
 }
 
-/// Get addon directory
-fn get_addon_dir() -> Option<PathBuf> {
+/// Get the directory including addon directories
+fn get_addon_dir() -> PathBuf {
     if let Some(e) = env::var_os("RUSTED_RUINS_ADDON_DIR") {
-        return Some(PathBuf::from(e));
+        return PathBuf::from(e);
     }
-    None
+    USER_DIR.join("addons")
 }
 
 /// Get application and each addon's directories
 /// They will be the root path for searching pak or text, and other data files.
+/// Data in the later directories overrides the former.
 pub fn get_data_dirs() -> Vec<PathBuf> {
     let mut v = Vec::new();
     v.push(APP_DIR.clone());
+    v.extend(ADDON_DIRS.iter().cloned());
+    v
+}
+
+/// Names of disabled addons are saved in this file
+const ADDON_LIST_FILE: &str = "addon_list.toml";
+
+#[derive(Default, Serialize, Deserialize)]
+struct AddonList {
+    disabled: Vec<String>,
+}
+
+/// Find addons, and set enabled states by the user's addon list
+pub fn load_addon_list() -> Vec<Addon> {
+    let mut addons = addon::find_addons(&ADDON_DIR);
+    let list: AddonList = read_file_as_string(USER_DIR.join(ADDON_LIST_FILE))
+        .ok()
+        .and_then(|s| toml::de::from_str(&s).ok())
+        .unwrap_or_default();
 
-    if ADDON_DIR.is_some() {
-        v.push(ADDON_DIR.clone().unwrap());
+    for a in addons.iter_mut() {
+        a.enabled = !list.disabled.contains(&a.manifest.name);
     }
+    addons
+}
 
-    v
+/// Save enabled states of addons. They are applied at the next start.
+pub fn save_addon_list(addons: &[Addon]) {
+    let list = AddonList {
+        disabled: addons
+            .iter()
+            .filter(|a| !a.enabled)
+            .map(|a| a.manifest.name.clone())
+            .collect(),
+    };
+    let s = toml::ser::to_string(&list).expect("addon list serialization failed");
+    if let Err(e) = std::fs::create_dir_all(&*USER_DIR)
+        .and_then(|_| std::fs::write(USER_DIR.join(ADDON_LIST_FILE), s))
+    {
+        warn!("Failed to save addon list: {}", e);
+    }
 }
 
 /// Create absolute path from relative path which root is application directory
@@ -158,4 +206,7 @@ pub struct Config {
     pub second_lang: String,
     pub screen_config: String,
     pub hardware_acceleration: bool,
+    /// Reload paks, rules and text files when they are changed
+    #[serde(default)]
+    pub developer_mode: bool,
 }
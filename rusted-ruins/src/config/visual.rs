@@ -37,6 +37,7 @@ pub struct UIConfig {
     pub exit_window: ExitWindowConfig,
     pub talk_window: TalkWindowConfig,
     pub start_dialog: StartDialogConfig,
+    pub addon_list_dialog: AddonListDialogConfig,
     pub msg_dialog: MsgDialogConfig,
     pub text_input_dialog: TextInputDialogConfig,
     pub newgame_dialog: NewGameDialogConfig,
@@ -127,6 +128,11 @@ pub struct StartDialogConfig {
     pub rect: CfgRect,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct AddonListDialogConfig {
+    pub rect: CfgRect,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct TextInputDialogConfig {
     pub rect: CfgRect,
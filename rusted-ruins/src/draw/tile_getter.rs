@@ -38,10 +38,10 @@ impl<'a> BackgroundDrawInfo<'a> {
         di.tile = tile;
 
         if map.is_inside(pos) {
-            if let Some(special_tile_id) = map.observed_tile[pos].special.obj_id() {
-                let special_tile_obj: &'static SpecialTileObject = gobj::get_by_id(special_tile_id);
+            if let Some((special_tile_obj, special_tile_idx)) =
+                special_tile(&map.observed_tile[pos].special)
+            {
                 if special_tile_obj.always_background {
-                    let special_tile_idx: SpecialTileIdx = gobj::id_to_idx(special_tile_id);
                     di.special = Some(special_tile_idx);
                 }
             }
@@ -51,6 +51,13 @@ impl<'a> BackgroundDrawInfo<'a> {
     }
 }
 
+/// Object of the special tile. Returns None if it is not found in loaded paks.
+fn special_tile(special: &SpecialTileKind) -> Option<(&'static SpecialTileObject, SpecialTileIdx)> {
+    let id = special.obj_id()?;
+    let obj = gobj::get_by_id_checked(id)?;
+    Some((obj, gobj::id_to_idx(id)))
+}
+
 /// Needed infomation to draw foreground parts of an tile
 /// "Foreground" means that they are drawed infront characters
 /// whose are on the prev row
@@ -68,10 +75,10 @@ impl<'a> ForegroundDrawInfo<'a> {
         let mut di = ForegroundDrawInfo::default();
 
         if map.is_inside(pos) {
-            if let Some(special_tile_id) = map.observed_tile[pos].special.obj_id() {
-                let special_tile_obj: &'static SpecialTileObject = gobj::get_by_id(special_tile_id);
+            if let Some((special_tile_obj, special_tile_idx)) =
+                special_tile(&map.observed_tile[pos].special)
+            {
                 if !special_tile_obj.always_background {
-                    let special_tile_idx: SpecialTileIdx = gobj::id_to_idx(special_tile_id);
                     di.special = Some(special_tile_idx);
                 }
             }
@@ -13,6 +13,20 @@ pub fn try_move(game: &mut Game, chara_id: CharaId, dir: Direction) -> bool {
     } // Move to current tile always success
     let dest_tile = game.gd.get_current_map().chara_pos(chara_id).unwrap() + dir.as_vec();
 
+    // Moving to a detected trap means trying to disarm it.
+    // If a character stands on the trap, moving there is handled as usual.
+    if chara_id == CharaId::Player && game.gd.get_current_map().get_chara(dest_tile).is_none() {
+        if let Some((kind, true)) = game.gd.get_current_map().get_trap(dest_tile) {
+            super::trap::try_disarm(game, chara_id, dest_tile, kind);
+            return true;
+        }
+    }
+
+    // Moving to a closed door means trying to open it
+    if super::door::try_open(game, chara_id, dest_tile) {
+        return true;
+    }
+
     if !game
         .gd
         .get_current_map()
@@ -23,9 +37,13 @@ pub fn try_move(game: &mut Game, chara_id: CharaId, dir: Direction) -> bool {
 
     let other_chara = game.gd.get_current_map().get_chara(dest_tile);
     if other_chara.is_none() {
-        game.gd.get_current_map_mut().move_chara(chara_id, dir);
+        let trap = game.gd.get_current_map_mut().move_chara(chara_id, dir);
         if chara_id == CharaId::Player {
             game.anim_queue.push_player_move(dir);
+            super::trigger::step_on_tile(game, dest_tile);
+        }
+        if let Some(trap) = trap {
+            super::trap::trigger_trap(game, chara_id, trap);
         }
     } else {
         let rel = {
@@ -35,12 +53,16 @@ pub fn try_move(game: &mut Game, chara_id: CharaId, dir: Direction) -> bool {
         };
         match rel {
             Relationship::ALLY | Relationship::FRIENDLY | Relationship::NEUTRAL => {
-                {
+                let trap = {
                     let current_map = game.gd.get_current_map_mut();
-                    current_map.move_chara(chara_id, dir);
-                }
+                    current_map.move_chara(chara_id, dir)
+                };
                 if chara_id == CharaId::Player {
                     game.anim_queue.push_player_move(dir);
+                    super::trigger::step_on_tile(game, dest_tile);
+                }
+                if let Some(trap) = trap {
+                    super::trap::trigger_trap(game, chara_id, trap);
                 }
             }
             Relationship::HOSTILE => {
@@ -81,6 +103,53 @@ pub fn eat_item(gd: &mut GameData, il: ItemLocation, cid: CharaId) {
     apply_medical_effect(chara, item_obj.medical_effect, eff);
 }
 
+/// Throw one item to target position
+pub fn throw_item(game: &mut Game, il: ItemLocation, cid: CharaId, target: Vec2d) {
+    let item = game.gd.remove_item_and_get(il, 1); // Decrease the number of item by 1
+    let item_obj = gobj::get_obj(item.idx);
+    let start = game.gd.get_current_map().chara_pos(cid).unwrap();
+
+    {
+        let chara = game.gd.chara.get(cid);
+        game_log!("throw-item"; chara=chara, item=item);
+    }
+
+    let (end, hit) = combat::trace_projectile(game.gd.get_current_map(), start, target);
+    if end != start {
+        game.anim_queue.push_shot(start, end);
+    }
+
+    match item.kind {
+        ItemKind::Potion => {
+            // Potions shatter and affect characters around
+            game_log!("item-shatter"; item=item);
+            let eff: i32 = item_obj.eff.into();
+            let map = game.gd.get_current_map();
+            let cids: Vec<CharaId> = MDistRangeIter::new(end, 1)
+                .filter_map(|(_, pos)| map.get_chara(pos))
+                .collect();
+            for cid in cids {
+                let chara = game.gd.chara.get_mut(cid);
+                apply_medical_effect(chara, item_obj.medical_effect, eff);
+            }
+            return;
+        }
+        ItemKind::Weapon(_) => {
+            if let Some(target_id) = hit {
+                combat::throw_attack(game, cid, target_id, &item);
+            }
+        }
+        _ => (),
+    }
+
+    if hit.is_none() && end != target {
+        game_log_i!("projectile-blocked");
+    }
+
+    // Thrown item falls on the tile where it stops
+    game.gd.get_current_map_mut().locate_item(item, end, 1);
+}
+
 fn apply_medical_effect(chara: &mut Chara, me: MedicalEffect, eff: i32) {
     match me {
         MedicalEffect::None => (),
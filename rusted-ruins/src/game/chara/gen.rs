@@ -18,6 +18,8 @@ pub fn create_chara(chara_template_idx: CharaTemplateIdx, lv: u32) -> Chara {
         template: chara_template_idx,
         class: CharaClass::Civilian,
         level: lv,
+        exp: 0,
+        attr_growth: CharaAttrRevision::default(),
         item_list: ItemList::new(),
         equip: EquipItemList::new(&[]),
         wait_time: WAIT_TIME_NUMERATOR,
@@ -28,6 +30,7 @@ pub fn create_chara(chara_template_idx: CharaTemplateIdx, lv: u32) -> Chara {
         skills: gen_skill_list(ct, lv),
         rel: Relationship::NEUTRAL,
         trigger_talk: None,
+        trigger_death: ct.trigger_death.clone(),
     };
 
     chara.update();
@@ -37,15 +40,8 @@ pub fn create_chara(chara_template_idx: CharaTemplateIdx, lv: u32) -> Chara {
 }
 
 /// Create npc character from the race
-pub fn create_npc_chara(dungeon: DungeonKind, floor_level: u32) -> Chara {
-    let idx = choose_npc_chara_template(
-        &RULES
-            .dungeon_gen
-            .get(&dungeon)
-            .expect("No rule for npc generation")
-            .npc_race_probability,
-        floor_level,
-    );
+pub fn create_npc_chara(npc_race_probability: &HashMap<Race, f32>, floor_level: u32) -> Chara {
+    let idx = choose_npc_chara_template(npc_race_probability, floor_level);
     let ct = gobj::get_obj(idx);
     let mut chara = create_chara(idx, ct.gen_level);
     set_skill(&mut chara);
@@ -0,0 +1,67 @@
+//! Character level and exp handlings
+
+use super::CharaEx;
+use crate::game::{DialogOpenRequest, Game};
+use common::gamedata::*;
+use rules::RULES;
+
+/// Character exp required to level up from given level
+pub fn required_exp(level: u32) -> u32 {
+    RULES.exp.chara_lvup * std::cmp::max(level, 1)
+}
+
+/// Skill exp multiplier by character class
+pub fn skill_aptitude(class: CharaClass, kind: SkillKind) -> f32 {
+    if let Some(aptitude) = RULES.chara.skill_aptitude.get(&class) {
+        for &(k, a) in aptitude {
+            if k == kind {
+                return a;
+            }
+        }
+    }
+    1.0
+}
+
+/// Add character exp. Returns the number of gained levels.
+pub fn add_chara_exp(chara: &mut Chara, add_exp: u32) -> u32 {
+    let mut n_level_up = 0;
+    chara.exp += add_exp;
+
+    while chara.exp >= required_exp(chara.level) {
+        chara.exp -= required_exp(chara.level);
+        chara.level += 1;
+        n_level_up += 1;
+        if let Some(&r) = RULES.chara.level_up_revision.get(&chara.class) {
+            chara.attr_growth = chara.attr_growth + r;
+        }
+    }
+
+    if n_level_up > 0 {
+        let old_max_hp = chara.attr.max_hp;
+        chara.update();
+        chara.hp += chara.attr.max_hp - old_max_hp;
+        game_log!("chara-level-up"; chara=chara, level=chara.level);
+    }
+    n_level_up
+}
+
+/// Character exp gained by killing given character
+pub fn kill_exp(target: &Chara) -> u32 {
+    RULES.exp.kill * std::cmp::max(target.level, 1)
+}
+
+/// Add character exp, and open the summary dialog if the player levels up
+pub fn gain_exp(game: &mut Game, cid: CharaId, add_exp: u32) {
+    let chara = game.gd.chara.get_mut(cid);
+    let old_attr = chara.attr.clone();
+
+    if add_chara_exp(chara, add_exp) > 0 && cid == CharaId::Player {
+        let level = chara.level;
+        let new_attr = chara.attr.clone();
+        game.request_dialog_open(DialogOpenRequest::LevelUp {
+            level,
+            old_attr,
+            new_attr,
+        });
+    }
+}
@@ -1,4 +1,5 @@
 pub mod gen;
+pub mod level;
 pub mod preturn;
 pub mod status;
 mod update;
@@ -21,6 +22,12 @@ pub trait CharaEx {
     fn add_damage_exp(&mut self, damage: i32, attacker_level: u32);
     /// Add exp when attacked.
     fn add_evasion_exp(&mut self, attacker_level: u32);
+    /// Add exp when this character detects a trap.
+    fn add_detect_trap_exp(&mut self, trap_level: u32);
+    /// Add exp when this character disarms a trap.
+    fn add_disarm_trap_exp(&mut self, trap_level: u32);
+    /// Add exp when this character picks a lock.
+    fn add_lockpick_exp(&mut self, lock_level: u32);
     /// sp increase/decrease.
     fn add_sp(&mut self, v: i32, cid: CharaId);
     /// Update character parameters by its status
@@ -31,6 +38,7 @@ pub trait CharaEx {
 
 impl CharaEx for Chara {
     fn add_skill_exp(&mut self, kind: SkillKind, add_exp: u32, base_level: u32) {
+        let add_exp = (add_exp as f32 * level::skill_aptitude(self.class, kind)) as u32;
         let result = self.skills.add_exp(kind, add_exp, base_level);
         trace!("{} gains {} exp for {:?}", self.to_text(), result.1, kind);
         if result.0 {
@@ -55,6 +63,18 @@ impl CharaEx for Chara {
         self.add_skill_exp(SkillKind::Evasion, RULES.exp.evasion, attacker_level);
     }
 
+    fn add_detect_trap_exp(&mut self, trap_level: u32) {
+        self.add_skill_exp(SkillKind::Searching, RULES.exp.detect_trap, trap_level);
+    }
+
+    fn add_disarm_trap_exp(&mut self, trap_level: u32) {
+        self.add_skill_exp(SkillKind::Searching, RULES.exp.disarm_trap, trap_level);
+    }
+
+    fn add_lockpick_exp(&mut self, lock_level: u32) {
+        self.add_skill_exp(SkillKind::Lockpicking, RULES.exp.lockpick, lock_level);
+    }
+
     fn add_sp(&mut self, v: i32, cid: CharaId) {
         let old_sp = self.sp;
         let new_sp = self.sp + v;
@@ -118,6 +138,9 @@ pub fn damage(game: &mut Game, cid: CharaId, damage: i32, damage_kind: DamageKin
             DamageKind::Poison => {
                 game_log!("killed-by-poison-damage"; chara=chara);
             }
+            DamageKind::Trap => {
+                game_log!("killed-by-trap"; chara=chara);
+            }
         }
     }
     chara.hp
@@ -1,6 +1,5 @@
 use common::gamedata::*;
 use common::gobj;
-use common::obj::CharaTemplateObject;
 use rules::RULES;
 
 /// Update character attributes by its status
@@ -12,8 +11,9 @@ pub fn update_attributes(chara: &mut Chara) {
     } else {
         ct.base_attr
     };
+    let base_attr = base_attr.revise(chara.attr_growth);
 
-    chara.attr.max_hp = calc_max_hp(chara, ct);
+    chara.attr.max_hp = calc_max_hp(chara, base_attr);
     chara.attr.str = base_attr.str as u16;
     chara.attr.vit = base_attr.vit as u16;
     chara.attr.dex = base_attr.dex as u16;
@@ -24,6 +24,6 @@ pub fn update_attributes(chara: &mut Chara) {
     chara.attr.view_range = RULES.chara.default_view_range;
 }
 
-fn calc_max_hp(chara: &mut Chara, ct: &CharaTemplateObject) -> i32 {
-    (chara.skills.get(SkillKind::Endurance) as i32 + 8) * ct.base_attr.base_hp / 8
+fn calc_max_hp(chara: &mut Chara, base_attr: CharaBaseAttr) -> i32 {
+    (chara.skills.get(SkillKind::Endurance) as i32 + 8) * base_attr.base_hp / 8
 }
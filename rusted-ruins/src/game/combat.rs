@@ -1,6 +1,7 @@
 use super::chara::CharaEx;
 use super::Game;
 use crate::rng;
+use array2d::*;
 use common::gamedata::*;
 use common::gobj;
 use rng::Rng;
@@ -10,6 +11,7 @@ pub enum DamageKind {
     MeleeAttack,
     RangedAttack,
     Poison,
+    Trap,
 }
 
 pub struct AttackParams {
@@ -102,9 +104,44 @@ pub fn shot_target(game: &mut Game, attacker_id: CharaId, target_id: CharaId) ->
         game_log_i!("no-ranged-weapon-equipped");
         return false;
     };
+    let weapon_obj = gobj::get_obj(weapon.idx);
+    let weapon_kind = get_weapon_kind(weapon_obj);
+
+    // Check equipped ammunition is compatible with the weapon
+    let ammo_obj: Option<&ItemObject> = if let Some(ammo_kind) = weapon_kind.ammo_kind() {
+        match attacker.equip.item(EquipSlotKind::Ammo, 0) {
+            Some(ammo) if ammo.kind == ItemKind::Ammo(ammo_kind) => Some(gobj::get_obj(ammo.idx)),
+            Some(ammo) => {
+                game_log_i!("ammo-incompatible"; item=ammo, weapon=weapon);
+                return false;
+            }
+            None => {
+                game_log_i!("no-ammo-equipped"; weapon=weapon);
+                return false;
+            }
+        }
+    } else {
+        None
+    };
+
     let attacker_pos = game.gd.get_current_map().chara_pos(attacker_id).unwrap();
     let target_pos = game.gd.get_current_map().chara_pos(target_id).unwrap();
 
+    // The shot hits the first character on the line of fire
+    let (target_id, target_pos) =
+        match trace_projectile(game.gd.get_current_map(), attacker_pos, target_pos) {
+            (pos, Some(cid)) => (cid, pos),
+            (pos, None) => {
+                game_log_i!("projectile-blocked");
+                if pos != attacker_pos {
+                    game.anim_queue.push_shot(attacker_pos, pos);
+                }
+                crate::audio::play_sound("arrow");
+                consume_ammo(game, attacker_id, pos);
+                return true;
+            }
+        };
+
     // Judges hit or miss
     {
         let attacker = game.gd.chara.get(attacker_id);
@@ -123,30 +160,34 @@ pub fn shot_target(game: &mut Game, attacker_id: CharaId, target_id: CharaId) ->
                 .add_evasion_exp(attacker_level);
             game.anim_queue.push_shot(attacker_pos, target_pos);
             crate::audio::play_sound("attack-miss");
+            consume_ammo(game, attacker_id, target_pos);
             return true;
         }
     }
 
     // Damage calculation
-    let (attack_params, weapon_kind, attacker_pos) = {
-        let weapon_obj = gobj::get_obj(weapon.idx);
-        let weapon_kind = get_weapon_kind(weapon_obj);
-        let dice_result = rng::dice(weapon_obj.dice_n as i32, weapon_obj.dice_x as i32);
+    let attack_params = {
+        let attacker = game.gd.chara.get(attacker_id);
+        let mut dice_result = rng::dice(weapon_obj.dice_n as i32, weapon_obj.dice_x as i32);
+        let mut element = Element::Physical;
+        if let Some(ammo_obj) = ammo_obj {
+            dice_result += rng::dice(ammo_obj.dice_n as i32, ammo_obj.dice_x as i32);
+            element = ammo_obj.element;
+        }
 
         let weapon_skill_level = attacker.skills.get(SkillKind::Weapon(weapon_kind));
         let attack_power = calc_attack_power(dice_result, attacker.attr.dex, weapon_skill_level);
 
-        let attack_params = AttackParams {
+        AttackParams {
             attacker_id: Some(attacker_id),
             kind: DamageKind::RangedAttack,
-            element: Element::Physical,
+            element,
             attack_power,
-        };
-
-        (attack_params, weapon_kind, attacker_pos)
+        }
     };
     // Logging
     {
+        let attacker = game.gd.chara.get(attacker_id);
         let target = game.gd.chara.get(target_id);
         game_log!("shot-target"; attacker=attacker, target=target);
     }
@@ -162,9 +203,113 @@ pub fn shot_target(game: &mut Game, attacker_id: CharaId, target_id: CharaId) ->
     game.anim_queue.push_shot(attacker_pos, target_pos);
     // Sound effect
     crate::audio::play_sound("arrow");
+    consume_ammo(game, attacker_id, target_pos);
     true
 }
 
+/// Consume one equipped ammunition after shooting.
+/// Consumed ammunition may be dropped on the tile where it landed.
+fn consume_ammo(game: &mut Game, attacker_id: CharaId, landing_pos: Vec2d) {
+    let attacker = game.gd.chara.get_mut(attacker_id);
+    let uses_ammo = attacker
+        .equip
+        .item(EquipSlotKind::RangedWeapon, 0)
+        .map(|weapon| {
+            get_weapon_kind(gobj::get_obj(weapon.idx))
+                .ammo_kind()
+                .is_some()
+        })
+        .unwrap_or(false);
+    if !uses_ammo {
+        return;
+    }
+    let ammo = if let Some(ammo) = attacker.equip.consume(EquipSlotKind::Ammo, 0, 1) {
+        ammo
+    } else {
+        return;
+    };
+    if attacker.equip.item(EquipSlotKind::Ammo, 0).is_none() {
+        game_log_i!("ammo-run-out"; chara=attacker, item=ammo);
+    }
+
+    if rng::get_rng().gen_bool(RULES.combat.ammo_recover_probability) {
+        game.gd
+            .get_current_map_mut()
+            .locate_item(ammo, landing_pos, 1);
+    }
+}
+
+/// Attack target by a thrown weapon
+pub fn throw_attack(game: &mut Game, attacker_id: CharaId, target_id: CharaId, item: &Item) {
+    let weapon_obj = gobj::get_obj(item.idx);
+    let weapon_kind = get_weapon_kind(weapon_obj);
+
+    // Judges hit or miss
+    {
+        let attacker = game.gd.chara.get(attacker_id);
+        let attacker_level = attacker.level;
+        let skill_level = attacker.skills.get(SkillKind::Weapon(weapon_kind));
+        // Thrown items have no accuracy bonus
+        let accuracy_power = calc_accuracy_power(0, skill_level, attacker.attr.dex);
+        // When miss
+        if !hit_judge(
+            &game.gd,
+            accuracy_power,
+            target_id,
+            DamageKind::RangedAttack,
+        ) {
+            // Exp to target chara
+            game.gd
+                .chara
+                .get_mut(target_id)
+                .add_evasion_exp(attacker_level);
+            crate::audio::play_sound("attack-miss");
+            return;
+        }
+    }
+
+    // Damage calculation
+    let attack_power = {
+        let attacker = game.gd.chara.get(attacker_id);
+        let dice_result = rng::dice(weapon_obj.dice_n as i32, weapon_obj.dice_x as i32);
+        let weapon_skill_level = attacker.skills.get(SkillKind::Weapon(weapon_kind));
+        calc_attack_power(dice_result, attacker.attr.str, weapon_skill_level)
+    };
+    let attack_params = AttackParams {
+        attacker_id: Some(attacker_id),
+        kind: DamageKind::RangedAttack,
+        element: Element::Physical,
+        attack_power,
+    };
+    // Damage target
+    let _damage = attack_target(game, attack_params, target_id);
+    // Exp processing
+    {
+        let target_level = game.gd.chara.get(target_id).level;
+        let attacker = game.gd.chara.get_mut(attacker_id);
+        attacker.add_attack_exp(SkillKind::Weapon(weapon_kind), target_level);
+    }
+    // Sound effect
+    crate::audio::play_sound("punch");
+}
+
+/// Trace a projectile from start to target.
+/// Projectiles stop at walls, closed doors, or the first character on the line.
+/// Returns the position where the projectile stops and the character hit by it.
+pub fn trace_projectile(map: &Map, start: Vec2d, target: Vec2d) -> (Vec2d, Option<CharaId>) {
+    let mut end = start;
+    for pos in LineIter::new(start, target).skip(1) {
+        if !map.is_inside(pos) || map.tile[pos].is_opaque() {
+            return (end, None);
+        }
+        end = pos;
+        if let Some(cid) = map.tile[pos].chara {
+            return (pos, Some(cid));
+        }
+    }
+    (end, None)
+}
+
 /// Routines for targetted character
 fn attack_target(game: &mut Game, attack_params: AttackParams, target_id: CharaId) -> i32 {
     let equip_def = calc_equip_defence(&game.gd, target_id);
@@ -193,6 +338,11 @@ fn attack_target(game: &mut Game, attack_params: AttackParams, target_id: CharaI
         }
     } else {
         super::quest::count_slayed_monster(&mut game.gd, idx);
+        // Character exp for the killer
+        if let Some(attacker_id) = attack_params.attacker_id {
+            let exp = super::chara::level::kill_exp(game.gd.chara.get(target_id));
+            super::chara::level::gain_exp(game, attacker_id, exp);
+        }
     }
 
     damage
@@ -19,8 +19,10 @@ pub enum Command {
     OpenGameInfoWin,
     PickUpItem,
     DropItem,
+    CloseDoor,
     DrinkItem,
     EatItem,
+    ThrowItem,
     TargetingMode,
     TextInput { text: String },
     TextDelete,
@@ -0,0 +1,122 @@
+//! Functions for opening, closing and unlocking doors
+
+use super::chara::CharaEx;
+use super::skill::calc_success_probability;
+use super::Game;
+use crate::rng;
+use array2d::*;
+use common::gamedata::*;
+use rng::Rng;
+
+/// Try to open the door at given position.
+/// Returns true if the character used its turn.
+pub fn try_open(game: &mut Game, cid: CharaId, pos: Vec2d) -> bool {
+    let state = if let Some(state) = game.gd.get_current_map().get_door(pos) {
+        state
+    } else {
+        return false;
+    };
+
+    match state {
+        DoorState::Open => false,
+        DoorState::Closed => {
+            open(game, cid, pos);
+            true
+        }
+        DoorState::Locked { level } => {
+            // NPCs do not have keys or lockpicks
+            if cid != CharaId::Player {
+                return false;
+            }
+            if use_key(game, cid) {
+                open(game, cid, pos);
+                return true;
+            }
+            pick_lock(game, cid, pos, level);
+            true
+        }
+    }
+}
+
+fn open(game: &mut Game, cid: CharaId, pos: Vec2d) {
+    game.gd.get_current_map_mut().tile[pos].special = SpecialTileKind::Door {
+        state: DoorState::Open,
+    };
+    if cid == CharaId::Player {
+        let chara = game.gd.chara.get(cid);
+        game_log!("door-open"; chara=chara);
+    }
+}
+
+/// Consume one key in the character's inventory
+fn use_key(game: &mut Game, cid: CharaId) -> bool {
+    let item_list_location = ItemListLocation::Chara { cid };
+    let i = if let Some(i) = game
+        .gd
+        .get_item_list(item_list_location)
+        .iter()
+        .position(|(item, _)| item.kind == ItemKind::Key)
+    {
+        i
+    } else {
+        return false;
+    };
+
+    let item = game
+        .gd
+        .remove_item_and_get((item_list_location, i as u32), 1);
+    let chara = game.gd.chara.get(cid);
+    game_log!("door-unlock-key"; chara=chara, item=item);
+    true
+}
+
+fn pick_lock(game: &mut Game, cid: CharaId, pos: Vec2d, lock_level: u32) {
+    let skill_level = game.gd.chara.get(cid).skills.get(SkillKind::Lockpicking);
+    let p = calc_success_probability(skill_level, lock_level);
+
+    if rng::get_rng().gen_bool(p) {
+        game.gd.get_current_map_mut().tile[pos].special = SpecialTileKind::Door {
+            state: DoorState::Closed,
+        };
+        let chara = game.gd.chara.get_mut(cid);
+        game_log!("lockpick-success"; chara=chara);
+        chara.add_lockpick_exp(lock_level);
+    } else {
+        let chara = game.gd.chara.get(cid);
+        game_log!("lockpick-failure"; chara=chara);
+    }
+}
+
+/// Close an open door next to the character.
+/// Returns true if the character used its turn.
+pub fn try_close(game: &mut Game, cid: CharaId) -> bool {
+    let center = if let Some(center) = game.gd.get_current_map().chara_pos(cid) {
+        center
+    } else {
+        return false;
+    };
+
+    let pos = {
+        let map = game.gd.get_current_map();
+        Direction::EIGHT_DIRS
+            .iter()
+            .map(|dir| center + dir.as_vec())
+            .find(|pos| {
+                map.get_door(*pos) == Some(DoorState::Open)
+                    && map.tile[*pos].chara.is_none()
+                    && map.tile[*pos].item_list.is_none()
+            })
+    };
+    let pos = if let Some(pos) = pos {
+        pos
+    } else {
+        return false;
+    };
+
+    game.gd.get_current_map_mut().tile[pos].special = SpecialTileKind::Door {
+        state: DoorState::Closed,
+    };
+    let chara = game.gd.chara.get(cid);
+    game_log!("door-close"; chara=chara);
+    true
+}
@@ -1,50 +1,219 @@
 //! This module provides functions for auto generated dungeons
 
 use crate::game::map::builder::MapBuilder;
-use crate::game::saveload::gen_box_id;
+use crate::game::saveload::{gen_box_id, get_map_dir};
+use crate::game::InfoGetter;
 use array2d::*;
 use common::gamedata::*;
 use common::gobj;
 use common::objholder::*;
 use rng;
+use rules::dungeon_gen::PrefabParams;
 use rules::RULES;
 
+/// The number of enemies generated on each floor
+const N_NPC_PER_FLOOR: u32 = 10;
+/// The minimum distance between the player and repopulated enemies
+const REPOPULATE_MIN_DISTANCE: i32 = 10;
+
 /// Add a new dungeon
-pub fn add_dungeon_site(gd: &mut GameData, dungeon_kind: DungeonKind, pos: Vec2d) -> SiteId {
+pub fn add_dungeon_site(
+    gd: &mut GameData,
+    dungeon_kind: DungeonKind,
+    rid: RegionId,
+    pos: Vec2d,
+) -> SiteId {
     let floor_range = &RULES.dungeon_gen[&dungeon_kind].floor_range;
     let mut site = Site::new(rng::gen_range(floor_range[0], floor_range[1]));
-    site.content = SiteContent::AutoGenDungeon { dungeon_kind };
-    gd.add_site(site, SiteKind::AutoGenDungeon, RegionId::default(), pos)
+    site.content = SiteContent::AutoGenDungeon {
+        dungeon_kind,
+        state: DungeonState::default(),
+    };
+    gd.add_site(site, SiteKind::AutoGenDungeon, rid, pos)
         .unwrap()
 }
 
-/// Extend dungion site by one floor
-pub fn extend_site_floor(gd: &mut GameData, sid: SiteId) {
+/// Extend dungion site by one floor.
+/// Returns false if the site is not a dungeon.
+pub fn extend_site_floor(gd: &mut GameData, sid: SiteId) -> bool {
     let floor = gd.region.get_site(sid).floor_num();
     let is_deepest_floor = floor >= gd.region.get_site(sid).max_floor() - 1;
     let map = match gd.region.get_site(sid).content {
-        SiteContent::AutoGenDungeon { dungeon_kind } => {
-            let map_size = RULES.dungeon_gen[&dungeon_kind].map_size;
-            let tile_idx = gobj::id_to_idx(&RULES.dungeon_gen[&dungeon_kind].terrain[0][0]);
-            let wall_idx = gobj::id_to_idx(&RULES.dungeon_gen[&dungeon_kind].terrain[0][1]);
+        SiteContent::AutoGenDungeon { dungeon_kind, .. } => {
+            let params = &RULES.dungeon_gen[&dungeon_kind];
+            let map_size = params.map_size;
+            let terrain = &params.terrain[rng::gen_range(0, params.terrain.len())];
             MapBuilder::new(map_size.0 as u32, map_size.1 as u32)
                 .floor(floor)
-                .tile(tile_idx)
-                .wall(wall_idx)
+                .tile(gobj::id_to_idx(&terrain.tile))
+                .wall(gobj::id_to_idx(&terrain.wall))
+                .water(terrain.water.as_ref().and_then(|id| id_to_idx_warn(id)))
+                .chasm(terrain.chasm.as_ref().and_then(|id| id_to_idx_warn(id)))
+                .rubble(
+                    terrain
+                        .rubble
+                        .iter()
+                        .filter_map(|id| id_to_idx_warn(id))
+                        .collect(),
+                )
+                .map_gen(params.map_gen.clone())
+                .prefabs(choose_prefabs(&params.prefabs, floor))
                 .deepest_floor(is_deepest_floor)
                 .build()
         }
-        _ => MapBuilder::new(40, 40).floor(floor).build(),
+        _ => {
+            warn!("{:?} is not a dungeon, so floors cannot be added", sid);
+            return false;
+        }
     };
 
     let map_random_id = gen_box_id(gd);
     let mid = gd.add_map(map, sid, map_random_id);
-    super::map::gen_npcs(gd, mid, 10, mid.floor());
+    super::map::gen_npcs(gd, mid, N_NPC_PER_FLOOR, mid.floor());
     super::map::gen_items(gd, mid);
+    super::map::gen_traps(gd, mid);
+    super::map::lock_doors(gd, mid);
 
     if is_deepest_floor {
         add_for_deepest_floor(gd, mid);
     }
+
+    let report = super::map::validate::validate_map(gd.region.get_map(mid), is_deepest_floor);
+    if !report.is_valid() {
+        warn!("Problems found in generated map {:?}:\n{}", mid, report);
+    }
+    true
+}
+
+/// Mark the dungeon as cleared if no enemies remain on its deepest floor.
+/// This is called after characters on the current map die.
+pub fn check_cleared(gd: &mut GameData) {
+    let mid = gd.get_current_mapid();
+    if mid.is_region_map() {
+        return;
+    }
+    {
+        let site = gd.region.get_site(mid.sid());
+        match site.content {
+            SiteContent::AutoGenDungeon { ref state, .. } if !state.is_cleared() => (),
+            _ => return,
+        }
+        if mid.floor() + 1 < site.max_floor() || count_enemies(gd) > 0 {
+            return;
+        }
+    }
+
+    let now = gd.time.current_time();
+    let site = gd.region.get_site_mut(mid.sid());
+    if let SiteContent::AutoGenDungeon { ref mut state, .. } = site.content {
+        state.cleared = Some(now);
+    }
+    game_log_i!("dungeon-cleared"; site=site);
+}
+
+/// Record the time when the player leaves the floor
+pub fn leave_floor(gd: &mut GameData, mid: MapId) {
+    let now = gd.time.current_time();
+    if let SiteContent::AutoGenDungeon { ref mut state, .. } =
+        gd.region.get_site_mut(mid.sid()).content
+    {
+        state.set_last_visit(mid.floor(), now);
+    }
+}
+
+/// Generate enemies again on the current floor if the player has not visited it for a long time
+pub fn repopulate(gd: &mut GameData) {
+    let mid = gd.get_current_mapid();
+    if mid.is_region_map() {
+        return;
+    }
+    let now = gd.time.current_time();
+    let needs_repopulation = match gd.region.get_site(mid.sid()).content {
+        SiteContent::AutoGenDungeon {
+            dungeon_kind,
+            ref state,
+        } => match RULES.dungeon_gen[&dungeon_kind].repopulate_days {
+            Some(days) => state.needs_repopulation(mid.floor(), now, days),
+            None => false,
+        },
+        _ => false,
+    };
+    if !needs_repopulation {
+        return;
+    }
+
+    let n_npc = N_NPC_PER_FLOOR.saturating_sub(count_enemies(gd));
+    let player_pos = gd.player_pos();
+    trace!("Repopulate {:?} with {} npcs", mid, n_npc);
+    super::map::gen_npcs_where(gd, mid, n_npc, mid.floor(), |p| {
+        p.mdistance(player_pos) >= REPOPULATE_MIN_DISTANCE
+    });
+}
+
+/// Remove cleared dungeons in the region after their expiration days
+pub fn expire_dungeons(gd: &mut GameData, rid: RegionId) {
+    let now = gd.time.current_time();
+    let current_mid = gd.get_current_mapid();
+    let expired: Vec<SiteId> = gd
+        .region
+        .get(rid)
+        .iter_sites()
+        .filter_map(|(sid, site)| match site.content {
+            SiteContent::AutoGenDungeon {
+                dungeon_kind,
+                ref state,
+            } => {
+                let expire_days = RULES.dungeon_gen[&dungeon_kind].expire_days?;
+                let is_player_inside = !current_mid.is_region_map() && current_mid.sid() == sid;
+                if !is_player_inside && state.is_expired(now, expire_days) {
+                    Some(sid)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .collect();
+
+    let map_dir = get_map_dir(gd);
+    for sid in expired {
+        info!("Cleared dungeon {:?} expired", sid);
+        super::site::remove_site(gd, sid, &map_dir);
+    }
+}
+
+/// The number of characters hostile to the player on the current map
+fn count_enemies(gd: &GameData) -> u32 {
+    let player = gd.chara.get(CharaId::Player);
+    gd.get_charas_on_map()
+        .into_iter()
+        .filter(|cid| player.rel.relative(gd.chara.get(*cid).rel) == Relationship::HOSTILE)
+        .count() as u32
+}
+
+/// Choose prefabs placed on the floor by their rarity
+fn choose_prefabs(prefabs: &[PrefabParams], floor: u32) -> Vec<PrefabParams> {
+    let mut chosen: Vec<PrefabParams> = Vec::new();
+    for prefab in prefabs {
+        if floor < prefab.min_floor || rng::gen_range(0, prefab.rarity.max(1)) != 0 {
+            continue;
+        }
+        let tag_used = chosen
+            .iter()
+            .any(|c| c.tags.iter().any(|tag| prefab.tags.contains(tag)));
+        if !tag_used {
+            chosen.push(prefab.clone());
+        }
+    }
+    chosen
+}
+
+pub fn id_to_idx_warn<T: ObjectIndex>(id: &str) -> Option<T> {
+    let idx = gobj::id_to_idx_checked(id);
+    if idx.is_none() {
+        warn!("unknown object id \"{}\" in dungeon terrain", id);
+    }
+    idx
 }
 
 /// Add items for deepest floor of dungeon
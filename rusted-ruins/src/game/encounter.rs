@@ -0,0 +1,173 @@
+//! Random encounters while travelling on region maps
+
+use super::dungeon_gen::id_to_idx_warn;
+use super::map::builder::MapBuilder;
+use super::saveload::gen_box_id;
+use super::{Game, InfoGetter};
+use array2d::*;
+use common::gamedata::*;
+use common::gobj;
+use common::regiongen::{Biome, RegionGenObject};
+use rng::*;
+use rules::RULES;
+
+/// Roll a random encounter after the player moved on the region map.
+/// If it happens, the player is moved to a temporary map with enemies.
+pub fn try_encounter(game: &mut Game) {
+    let mid = game.gd.get_current_mapid();
+    if !mid.is_region_map() {
+        return;
+    }
+    let rid = mid.rid();
+    let pos = game.gd.player_pos();
+    // Sites and other special tiles are safe
+    if !game.gd.get_current_map().tile[pos].special.is_none() {
+        return;
+    }
+
+    let danger_level = danger_level(game.gd.region.get(rid));
+    let is_night = is_night(&game.gd);
+    let mut probability =
+        RULES.encounter.probability * (1.0 + RULES.encounter.danger_factor * danger_level as f64);
+    if is_night {
+        probability *= RULES.encounter.night_factor;
+    }
+    if !get_rng().gen_bool(probability.max(0.0).min(1.0)) {
+        return;
+    }
+
+    let biome = biome_at(game.gd.region.get(rid), pos);
+    let table = if let Some(table) = choose_table(biome, danger_level, is_night) {
+        table
+    } else {
+        return;
+    };
+    let level = RULES.encounter.base_level + danger_level * RULES.encounter.level_per_danger;
+    let sid = add_encounter_site(&mut game.gd, rid, pos, table, level);
+
+    game_log_i!("encounter"; player=game.gd.chara.get(CharaId::Player));
+    super::map::switch_map(game, MapId::site_first_floor(sid));
+}
+
+fn add_encounter_site(
+    gd: &mut GameData,
+    rid: RegionId,
+    pos: Vec2d,
+    table_id: &str,
+    level: u32,
+) -> SiteId {
+    let table = &RULES.encounter.tables[table_id];
+    let mut site = Site::new(1);
+    site.content = SiteContent::Encounter {
+        table: table_id.to_owned(),
+    };
+    let sid = gd
+        .add_site(site, SiteKind::Encounter, rid, pos)
+        .expect("Failed to add an encounter site");
+
+    let map_size = RULES.encounter.map_size;
+    let terrain = &table.terrain[gen_range(0, table.terrain.len())];
+    let mut map = map_builder(map_size.0 as u32, map_size.1 as u32)
+        .tile(gobj::id_to_idx(&terrain.tile))
+        .wall(gobj::id_to_idx(&terrain.wall))
+        .water(terrain.water.as_ref().and_then(|id| id_to_idx_warn(id)))
+        .chasm(terrain.chasm.as_ref().and_then(|id| id_to_idx_warn(id)))
+        .rubble(
+            terrain
+                .rubble
+                .iter()
+                .filter_map(|id| id_to_idx_warn(id))
+                .collect(),
+        )
+        .map_gen(table.map_gen.clone())
+        .build();
+    // Players can run away from any side
+    map.boundary = MapBoundary {
+        n: BoundaryBehavior::RegionMap,
+        s: BoundaryBehavior::RegionMap,
+        e: BoundaryBehavior::RegionMap,
+        w: BoundaryBehavior::RegionMap,
+    };
+
+    let map_random_id = gen_box_id(gd);
+    let mid = gd.add_map(map, sid, map_random_id);
+    let n_npc = gen_range(table.n_npc[0], table.n_npc[1] + 1);
+    super::map::gen_npcs(gd, mid, n_npc, level);
+    sid
+}
+
+/// Encounter sites have only one floor, so their maps have no stairs to the next floor
+fn map_builder(w: u32, h: u32) -> MapBuilder {
+    MapBuilder::new(w, h).deepest_floor(true)
+}
+
+/// Choose an encounter table by weights. Returns None if no table is available.
+fn choose_table(biome: Biome, danger_level: u32, is_night: bool) -> Option<&'static str> {
+    let tables: Vec<(&'static str, f32)> = RULES
+        .encounter
+        .tables
+        .iter()
+        .filter(|(_, table)| {
+            (table.biomes.is_empty() || table.biomes.contains(&biome))
+                && table.min_danger_level <= danger_level
+        })
+        .map(|(id, table)| {
+            let weight = if is_night {
+                table.night_weight
+            } else {
+                table.weight
+            };
+            (id.as_str(), weight)
+        })
+        .filter(|(_, weight)| *weight > 0.0)
+        .collect();
+
+    tables
+        .choose_weighted(&mut get_rng(), |(_, weight)| *weight)
+        .ok()
+        .map(|(id, _)| *id)
+}
+
+fn danger_level(region: &Region) -> u32 {
+    gobj::get_by_id_checked::<RegionGenObject>(&region.name)
+        .map(|rg| rg.danger_level)
+        .unwrap_or(0)
+}
+
+/// Biome at the position. Regions created from map templates are treated as plains.
+fn biome_at(region: &Region, pos: Vec2d) -> Biome {
+    if let Some((params, seed)) = super::region::gen_params(region) {
+        let biome = super::region_gen::biome_map(params, seed);
+        if let Some(biome) = biome.get(pos) {
+            return *biome;
+        }
+    }
+    Biome::Plains
+}
+
+fn is_night(gd: &GameData) -> bool {
+    let hour = gd.time.current_date().hour;
+    let [start, end] = RULES.encounter.night_hours;
+    if start <= end {
+        start <= hour && hour < end
+    } else {
+        start <= hour || hour < end
+    }
+}
+
+#[test]
+fn encounter_map_has_no_exit() {
+    use super::map::builder::generated_map_to_map;
+    use crate::map_generator::MapGenerator;
+
+    let mut gm = MapGenerator::new((10, 10)).flat().generate();
+    gm.entrance = Vec2d(0, 0);
+    gm.exit = Some(Vec2d(9, 9));
+    let map = generated_map_to_map(gm, &map_builder(10, 10));
+
+    assert!(map.tile.iter().all(|tile| match tile.special {
+        SpecialTileKind::Stairs { kind, .. } => kind != StairsKind::DownStairs,
+        _ => true,
+    }));
+    assert!(map.tile[Vec2d(9, 9)].special.is_none());
+}
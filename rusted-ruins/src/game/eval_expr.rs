@@ -27,6 +27,24 @@ impl EvalExpr for Expr {
                     Value::RefUnknownVar
                 }
             }
+            Expr::LVar(var_name) => {
+                if let Some(v) = gd.vars.local_var(var_name) {
+                    v.clone()
+                } else {
+                    Value::RefUnknownVar
+                }
+            }
+            Expr::CVar(var_name) => {
+                let v = gd
+                    .vars
+                    .script_cid()
+                    .and_then(|cid| gd.vars.chara_var(cid, var_name));
+                if let Some(v) = v {
+                    v.clone()
+                } else {
+                    Value::RefUnknownVar
+                }
+            }
             Expr::IsGVarEmpty(var_name) => Value::Bool(gd.vars.global_var(var_name).is_some()),
             Expr::CurrentTime => Value::Time(gd.time.current_time()),
             Expr::DurationHour(a, b) => match (a.eval(gd), b.eval(gd)) {
@@ -49,6 +67,14 @@ impl EvalExpr for Expr {
                     Value::Error(ExprErrorKind::UnknownIdRef)
                 }
             }
+            Expr::PlayerSkill(kind) => {
+                let player = gd.chara.get(CharaId::Player);
+                Value::Int(player.skills.get(*kind) as i32)
+            }
+            Expr::PlayerAttr(kind) => {
+                let player = gd.chara.get(CharaId::Player);
+                Value::Int(player.attr.get(*kind))
+            }
         }
     }
 }
@@ -60,10 +86,13 @@ fn binary_operation(o: Operator, a: Value, b: Value) -> Value {
     let (a, b) = match (a, b) {
         (Bool(a), Bool(b)) => (Bool(a), Bool(b)),
         (Int(a), Int(b)) => (Int(a), Int(b)),
+        (String(a), String(b)) => (String(a), String(b)),
         (Bool(a), RefUnknownVar) => (Bool(a), Bool(false)),
         (RefUnknownVar, Bool(b)) => (Bool(false), Bool(b)),
         (Int(a), RefUnknownVar) => (Int(a), Int(0)),
         (RefUnknownVar, Int(b)) => (Int(0), Int(b)),
+        (String(a), RefUnknownVar) => (String(a), String("".to_owned())),
+        (RefUnknownVar, String(b)) => (String("".to_owned()), String(b)),
         (Error(e), _) => {
             return Error(e);
         }
@@ -94,6 +123,7 @@ fn binary_operation(o: Operator, a: Value, b: Value) -> Value {
         Operator::GreaterEq => Bool(a >= b),
         Operator::Add => match (a, b) {
             (Int(a), Int(b)) => Int(a + b),
+            (String(a), String(b)) => String(a + &b),
             _ => Error(ExprErrorKind::InvalidType),
         },
         Operator::Sub => match (a, b) {
@@ -137,4 +167,12 @@ fn binary_operation_test() {
     assert_eq!(binary_operation(Operator::Sub, Int(20), Int(22)), Int(-2));
     assert_eq!(binary_operation(Operator::Mul, Int(11), Int(12)), Int(132));
     assert_eq!(binary_operation(Operator::Div, Int(150), Int(25)), Int(6));
+    assert_eq!(
+        binary_operation(Operator::Add, String("ab".into()), String("cd".into())),
+        String("abcd".into())
+    );
+    assert_eq!(
+        binary_operation(Operator::Eq, String("ab".into()), RefUnknownVar),
+        Bool(false)
+    );
 }
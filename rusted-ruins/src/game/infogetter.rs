@@ -95,6 +95,7 @@ impl InfoGetter for GameData {
             MapId::SiteMap { sid, floor } => match sid.kind {
                 SiteKind::AutoGenDungeon => false,
                 SiteKind::Town => floor == 0,
+                SiteKind::Encounter => true,
                 SiteKind::Other => false,
             },
             MapId::RegionMap { .. } => true,
@@ -55,13 +55,20 @@ pub fn change_equipment(
     slot: (EquipSlotKind, u8),
     il: ItemLocation,
 ) {
-    let item = gd.remove_item_and_get(il, 1);
+    // Ammunition is equipped as a stack
+    let n = if slot.0 == EquipSlotKind::Ammo {
+        gd.get_item(il).1
+    } else {
+        1
+    };
+    let item = gd.remove_item_and_get(il, n);
 
     game_log_i!("item-equip"; chara=gd.chara.get(cid), item=item);
-    if let Some(removed_equipment) = gd
-        .get_equip_list_mut(cid)
-        .equip(slot.0, slot.1 as usize, item)
+    if let Some((removed_equipment, removed_n)) =
+        gd.get_equip_list_mut(cid)
+            .equip_with_num(slot.0, slot.1 as usize, item, n)
     {
-        gd.get_item_list_mut(il.0).append(removed_equipment, 1);
+        gd.get_item_list_mut(il.0)
+            .append(removed_equipment, removed_n);
     }
 }
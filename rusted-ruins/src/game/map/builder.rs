@@ -1,8 +1,14 @@
-use crate::map_generator::{GeneratedMap, MapGenerator, TileKind};
+use super::from_template;
+use crate::map_generator::{DecoKind, GeneratedMap, MapGenerator, TileKind};
 use array2d::*;
 use common::gamedata::map::*;
 use common::gobj;
+use common::obj::MapTemplateObject;
 use common::objholder::*;
+use rules::dungeon_gen::{MapGenParams, PrefabParams};
+
+/// The number of retries when the generated map is invalid
+const MAX_TRY: usize = 10;
 
 #[derive(Default)]
 pub struct MapBuilder {
@@ -12,6 +18,13 @@ pub struct MapBuilder {
     is_deepest_floor: bool,
     tile: TileIdx,
     wall: WallIdx,
+    water: Option<TileIdx>,
+    chasm: Option<TileIdx>,
+    rubble: Vec<DecoIdx>,
+    map_gen: MapGenParams,
+    prefabs: Vec<PrefabParams>,
+    /// Tile and wall for each tile id of the generated map learned from examples
+    source_terrain: Vec<(TileIdx, Option<WallIdx>)>,
 }
 
 impl MapBuilder {
@@ -22,15 +35,98 @@ impl MapBuilder {
         map_builder
     }
 
-    pub fn build(self) -> Map {
-        let generated_map = MapGenerator::new((self.w, self.h)).fractal().generate();
-        generated_map_to_map(
-            generated_map,
-            self.tile,
-            self.wall,
-            self.floor,
-            self.is_deepest_floor,
-        )
+    pub fn build(mut self) -> Map {
+        let generator = MapGenerator::new((self.w, self.h));
+        let generator = match self.map_gen.clone() {
+            MapGenParams::Flat => generator.flat(),
+            MapGenParams::Lattice {
+                nx,
+                ny,
+                step_min,
+                step_max,
+                door_weight,
+            } => generator.lattice(nx, ny, step_min, step_max, door_weight),
+            MapGenParams::Fractal => generator.fractal(),
+            MapGenParams::Bsp {
+                min_room_size,
+                max_room_size,
+                door_weight,
+            } => generator.bsp(min_room_size, max_room_size, door_weight),
+            MapGenParams::Cave {
+                wall_ratio,
+                n_iteration,
+                water_ratio,
+                chasm_ratio,
+                rubble_ratio,
+            } => generator.cave(
+                wall_ratio,
+                n_iteration,
+                water_ratio,
+                chasm_ratio,
+                rubble_ratio,
+            ),
+            MapGenParams::Wfc {
+                templates,
+                n,
+                entrance,
+                exit,
+            } => {
+                let templates: Vec<&MapTemplateObject> = templates
+                    .iter()
+                    .filter_map(|id| {
+                        let t = gobj::get_by_id_checked(id);
+                        if t.is_none() {
+                            warn!("unknown map template \"{}\" for wfc", id);
+                        }
+                        t
+                    })
+                    .collect();
+                let (examples, kinds, source_terrain) = from_template::to_examples(&templates);
+                self.source_terrain = source_terrain;
+                generator.wfc(examples, kinds, n, entrance, exit)
+            }
+        };
+        let prefabs: Vec<(&PrefabParams, &MapTemplateObject)> = self
+            .prefabs
+            .iter()
+            .filter_map(|prefab| {
+                let t = gobj::get_by_id_checked(&prefab.template);
+                if t.is_none() {
+                    warn!("unknown map template \"{}\" for prefab", prefab.template);
+                }
+                Some((prefab, t?))
+            })
+            .collect();
+        let generator = generator.prefabs(
+            prefabs
+                .iter()
+                .map(|(prefab, t)| from_template::to_prefab(t, &prefab.connections))
+                .collect(),
+        );
+        let mut generated_map = generator.clone().generate();
+        for i in 1..=MAX_TRY {
+            let report = generated_map.validate(self.is_deepest_floor);
+            trace!("Generated map: {}", report);
+            if report.is_valid() {
+                break;
+            }
+            if i == MAX_TRY {
+                // Use the last map rather than failing to create the floor
+                warn!(
+                    "Valid map is not generated after {} tries, so an invalid map is used: {}",
+                    MAX_TRY, report
+                );
+                break;
+            }
+            warn!("Invalid generated map is discarded: {}", report);
+            generated_map = generator.clone().generate();
+        }
+        let placed_prefabs = generated_map.prefabs.clone();
+        let mut map = generated_map_to_map(generated_map, &self);
+        for placed in &placed_prefabs {
+            from_template::write_prefab(&mut map, prefabs[placed.index].1, placed.top_left);
+        }
+        map
     }
 
     pub fn floor(mut self, floor: u32) -> MapBuilder {
@@ -48,32 +144,72 @@ impl MapBuilder {
         self
     }
 
+    /// Tile for water pools. Floor tile is used if not given.
+    pub fn water(mut self, water: Option<TileIdx>) -> MapBuilder {
+        self.water = water;
+        self
+    }
+
+    /// Tile for chasms. Wall is used if not given.
+    pub fn chasm(mut self, chasm: Option<TileIdx>) -> MapBuilder {
+        self.chasm = chasm;
+        self
+    }
+
+    /// Decos for rubble. One of them is chosen for each rubble.
+    pub fn rubble(mut self, rubble: Vec<DecoIdx>) -> MapBuilder {
+        self.rubble = rubble;
+        self
+    }
+
+    /// Map templates placed into the generated map
+    pub fn prefabs(mut self, prefabs: Vec<PrefabParams>) -> MapBuilder {
+        self.prefabs = prefabs;
+        self
+    }
+
+    pub fn map_gen(mut self, map_gen: MapGenParams) -> MapBuilder {
+        self.map_gen = map_gen;
+        self
+    }
+
     pub fn deepest_floor(mut self, is_deepest_floor: bool) -> MapBuilder {
         self.is_deepest_floor = is_deepest_floor;
         self
     }
 }
 
-pub fn generated_map_to_map(
-    gm: GeneratedMap,
-    tile: TileIdx,
-    wall: WallIdx,
-    floor: u32,
-    is_deepest_floor: bool,
-) -> Map {
+pub fn generated_map_to_map(gm: GeneratedMap, builder: &MapBuilder) -> Map {
+    let floor = builder.floor;
+    let is_deepest_floor = builder.is_deepest_floor;
     let size = gm.size;
     let mut map = Map::new(size.0 as u32, size.1 as u32);
 
     trace!("New map creating");
 
     for p in size.iter_from_zero() {
+        let source_terrain = gm
+            .source_tile
+            .as_ref()
+            .and_then(|source_tile| builder.source_terrain.get(source_tile[p] as usize));
+        let (tile, wall) = match source_terrain {
+            Some((tile, wall)) => (*tile, wall.unwrap_or(builder.wall)),
+            None => (builder.tile, builder.wall),
+        };
         map.tile[p].tile = tile.into();
         match gm.tile[p] {
-            TileKind::Wall => {
+            TileKind::Water if builder.water.is_some() => {
+                map.tile[p].tile = builder.water.unwrap().into();
+            }
+            TileKind::Chasm if builder.chasm.is_some() => {
+                map.tile[p].tile = builder.chasm.unwrap().into();
+            }
+            TileKind::Wall | TileKind::Chasm => {
                 let piece_pattern = {
                     let f = |pos: Vec2d| {
                         if let Some(t) = gm.tile.get(pos) {
                             *t == TileKind::Wall
+                                || (*t == TileKind::Chasm && builder.chasm.is_none())
                         } else {
                             true
                         }
@@ -87,8 +223,17 @@ pub fn generated_map_to_map(
                 };
                 map.tile[p].wall = WallIdxPP::with_piece_pattern(wall, piece_pattern);
             }
+            TileKind::Door => {
+                map.tile[p].special = SpecialTileKind::Door {
+                    state: DoorState::Closed,
+                };
+            }
             _ => (),
         }
+        if gm.deco[p] == Some(DecoKind::Rubble) && !builder.rubble.is_empty() {
+            let i = rng::gen_range(0, builder.rubble.len());
+            map.tile[p].deco = Some(builder.rubble[i]);
+        }
     }
 
     // Set stairs
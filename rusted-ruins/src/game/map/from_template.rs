@@ -1,12 +1,22 @@
 use crate::game::item::gen::from_item_gen;
+use crate::map_generator::{Prefab, TileKind};
+use array2d::*;
 use common::gamedata::*;
 use common::gobj;
 use common::maptemplate::*;
+use common::obj::TileKind as TileObjKind;
+use common::objholder::*;
 
 pub fn from_template(t: &MapTemplateObject) -> Map {
     let mut map = create_terrain(t);
     set_boundary(&mut map, t, 0);
-    gen_items(&mut map, t);
+    gen_items(&mut map, t, Vec2d(0, 0));
+    map.triggers = t.triggers.clone();
+
+    let report = super::validate::validate_map(&map, true);
+    if !report.is_valid() {
+        warn!("Problems found in map template \"{}\":\n{}", t.id, report);
+    }
     map
 }
 
@@ -39,6 +49,75 @@ fn create_terrain(t: &MapTemplateObject) -> Map {
     map
 }
 
+/// Create a prefab for map generation.
+/// Passable tiles on the edges are used as connection points if not given.
+pub fn to_prefab(t: &MapTemplateObject, connections: &[Vec2d]) -> Prefab {
+    let tmap = create_terrain(t);
+    let tile = Array2d::from_fn(t.w, t.h, |p| {
+        let tile = &tmap.tile[p];
+        if !tile.wall.is_empty() {
+            TileKind::Wall
+        } else if gobj::get_obj(tile.main_tile()).kind == TileObjKind::Water {
+            TileKind::Water
+        } else {
+            TileKind::Floor
+        }
+    });
+    let mut prefab = Prefab::new(tile);
+    if !connections.is_empty() {
+        prefab.connections = connections.to_vec();
+    }
+    prefab
+}
+
+/// Convert templates to examples for map generators learning from example maps.
+/// Returns examples as tile ids, tile kinds and terrains of each id.
+pub fn to_examples(
+    templates: &[&MapTemplateObject],
+) -> (
+    Vec<Array2d<u32>>,
+    Vec<TileKind>,
+    Vec<(TileIdx, Option<WallIdx>)>,
+) {
+    let mut terrains: Vec<(TileIdx, Option<WallIdx>)> = Vec::new();
+    let mut kinds = Vec::new();
+    let examples = templates
+        .iter()
+        .map(|t| {
+            let tmap = create_terrain(t);
+            Array2d::from_fn(t.w, t.h, |p| {
+                let tile = &tmap.tile[p];
+                let terrain = (tile.main_tile(), tile.wall.idx());
+                if let Some(i) = terrains.iter().position(|a| *a == terrain) {
+                    return i as u32;
+                }
+                kinds.push(if terrain.1.is_some() {
+                    TileKind::Wall
+                } else if gobj::get_obj(terrain.0).kind == TileObjKind::Water {
+                    TileKind::Water
+                } else {
+                    TileKind::Floor
+                });
+                terrains.push(terrain);
+                terrains.len() as u32 - 1
+            })
+        })
+        .collect();
+    (examples, kinds, terrains)
+}
+
+/// Overwrite terrains and items of the map by the template placed at top_left
+pub fn write_prefab(map: &mut Map, t: &MapTemplateObject, top_left: Vec2d) {
+    let tmap = create_terrain(t);
+    for p in tmap.tile.iter_idx() {
+        let dest = &mut map.tile[top_left + p];
+        dest.tile = tmap.tile[p].tile.clone();
+        dest.wall = tmap.tile[p].wall;
+        dest.deco = tmap.tile[p].deco;
+    }
+    gen_items(map, t, top_left);
+}
+
 /// Setting Boundaries
 pub fn set_boundary(map: &mut Map, t: &MapTemplateObject, floor: u32) {
     let next_floor = BoundaryBehavior::Floor(floor + 1);
@@ -64,7 +143,7 @@ pub fn set_boundary(map: &mut Map, t: &MapTemplateObject, floor: u32) {
 }
 
 /// Generate items
-fn gen_items(map: &mut Map, t: &MapTemplateObject) {
+fn gen_items(map: &mut Map, t: &MapTemplateObject, top_left: Vec2d) {
     for (pos, item_gen) in &t.items {
         let item = if let Some(item) = from_item_gen(item_gen) {
             item
@@ -73,6 +152,6 @@ fn gen_items(map: &mut Map, t: &MapTemplateObject) {
         };
 
         // Locate item at the specified tile
-        map.locate_item(item, *pos, 1);
+        map.locate_item(item, top_left + *pos, 1);
     }
 }
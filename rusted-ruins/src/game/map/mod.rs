@@ -1,6 +1,7 @@
 pub mod builder;
 pub mod from_template;
 pub mod search;
+pub mod validate;
 
 use super::chara::gen::create_npc_chara;
 use super::item::gen::gen_dungeon_item;
@@ -16,7 +17,9 @@ use rules::RULES;
 pub trait MapEx {
     /// The tile is passable for given character or not.
     fn is_passable(&self, chara: &Chara, pos: Vec2d) -> bool;
-    fn move_chara(&mut self, cid: CharaId, dir: Direction) -> bool;
+    /// Move character to the next tile.
+    /// If the player steps on a trap, the trap is revealed and returned.
+    fn move_chara(&mut self, cid: CharaId, dir: Direction) -> Option<TrapKind>;
 }
 
 impl MapEx for Map {
@@ -25,6 +28,11 @@ impl MapEx for Map {
             return false;
         }
 
+        // Closed doors are opened by characters moving into them
+        if let Some(DoorState::Locked { .. }) = self.get_door(pos) {
+            return false;
+        }
+
         if self.tile[pos].wall.is_empty() {
             let tile = gobj::get_obj(self.tile[pos].main_tile());
             match tile.kind {
@@ -36,62 +44,139 @@ impl MapEx for Map {
         }
     }
 
-    fn move_chara(&mut self, cid: CharaId, dir: Direction) -> bool {
-        if let Some(p) = self.chara_pos(cid) {
-            let new_p = p + dir.as_vec();
-            self.swap_chara(p, new_p)
-        } else {
-            false
+    fn move_chara(&mut self, cid: CharaId, dir: Direction) -> Option<TrapKind> {
+        let p = self.chara_pos(cid)?;
+        let new_p = p + dir.as_vec();
+        if !self.swap_chara(p, new_p) {
+            return None;
         }
+
+        // Only the player triggers traps. NPCs know the traps on their own floor.
+        if cid != CharaId::Player {
+            return None;
+        }
+        let (kind, _) = self.get_trap(new_p)?;
+        self.tile[new_p].special = SpecialTileKind::Trap {
+            kind,
+            detected: true,
+        };
+        Some(kind)
     }
 }
 
 /// Switch current map to the specified map
 pub fn switch_map(game: &mut Game, mid: MapId) {
+    switch_map_with_pos(game, mid, None);
+}
+
+/// Switch current map and locate the player at the given position.
+/// If the position is not given, the player is located at the stairs or the entrance.
+pub fn switch_map_with_pos(game: &mut Game, mid: MapId, pos: Option<Vec2d>) {
     {
-        let save_dir = game.save_dir.as_ref().unwrap();
+        let save_dir = game.save_dir.as_ref().expect("save directory is not set");
         let gd = &mut game.gd;
 
         trace!("Switch map to {:?}", mid);
         // If next_mid floor doesn't exist, create new floor
         if !mid.is_region_map() && !gd.region.map_exist(mid) {
             info!("{:?} is not exist, so try to create new floor", mid);
-            super::dungeon_gen::extend_site_floor(gd, mid.sid());
+            if !super::dungeon_gen::extend_site_floor(gd, mid.sid()) {
+                return;
+            }
         }
         let prev_mid = gd.get_current_mapid();
-        gd.region.preload_map(mid, save_dir.join("maps"));
+        if !prev_mid.is_region_map() {
+            super::dungeon_gen::leave_floor(gd, prev_mid);
+        }
+        gd.region
+            .preload_map(mid, save_dir.join("maps"))
+            .expect("failed to load map");
         gd.set_current_mapid(mid);
 
-        let new_player_pos =
-            if mid.is_region_map() && !prev_mid.is_region_map() && mid.rid() == prev_mid.rid() {
-                // Exit from a site to region map
-                gd.region.get_site_pos(prev_mid.sid())
+        let new_player_pos = if let Some(pos) = pos.filter(|p| gd.get_current_map().is_inside(*p)) {
+            pos
+        } else if mid.is_region_map() && !prev_mid.is_region_map() && mid.rid() == prev_mid.rid() {
+            // Exit from a site to region map
+            gd.region.get_site_pos(prev_mid.sid())
+        } else {
+            // Move to another floor of the same site
+            let current_map = gd.get_current_map();
+            if let Some(p) = current_map.search_stairs(prev_mid.floor()) {
+                p
             } else {
-                // Move to another floor of the same site
-                let current_map = gd.get_current_map();
-                if let Some(p) = current_map.search_stairs(prev_mid.floor()) {
-                    p
-                } else {
-                    current_map.entrance
-                }
-            };
+                current_map.entrance
+            }
+        };
+
+        // The position given by scripts or the stairs may be blocked
+        let new_player_pos = player_pos_near(gd.get_current_map(), new_player_pos, |tile| {
+            gobj::get_obj(tile.main_tile()).kind == TileKind::Ground
+        })
+        .unwrap_or(new_player_pos);
 
         gd.get_current_map_mut()
             .locate_chara(CharaId::Player, new_player_pos);
+
+        // Encounter sites are temporary
+        if let MapId::SiteMap { sid, .. } = prev_mid {
+            if sid.kind == SiteKind::Encounter && mid.is_region_map() {
+                super::site::remove_site(gd, sid, save_dir.join("maps"));
+            }
+        }
+        if mid.is_region_map() {
+            super::dungeon_gen::expire_dungeons(gd, mid.rid());
+        } else {
+            super::dungeon_gen::repopulate(gd);
+        }
     }
     crate::audio::play_sound("floor-change");
     super::view::update_view_map(game);
+    super::trigger::enter_map(game);
+}
+
+/// The nearest tile from `pos` the player can be located on.
+/// `is_ground` checks the tile kind of tiles without walls.
+fn player_pos_near<F: Fn(&TileInfo) -> bool>(map: &Map, pos: Vec2d, is_ground: F) -> Option<Vec2d> {
+    map.tile
+        .iter_with_idx()
+        .filter(|(_, tile)| {
+            tile.wall.is_empty()
+                && tile.chara.map_or(true, |cid| cid == CharaId::Player)
+                && is_ground(tile)
+        })
+        .min_by_key(|(p, _)| p.mdistance(pos))
+        .map(|(p, _)| p)
 }
 
 pub fn gen_npcs(gd: &mut GameData, mid: MapId, n: u32, floor_level: u32) {
-    let dungeon_kind = match gd.region.get_site(mid.sid()).content {
-        SiteContent::AutoGenDungeon { dungeon_kind } => dungeon_kind,
-        _ => DungeonKind::Ruin,
+    gen_npcs_where(gd, mid, n, floor_level, |_| true);
+}
+
+/// Generate npcs only on the positions satisfying the condition
+pub fn gen_npcs_where<F: Fn(Vec2d) -> bool>(
+    gd: &mut GameData,
+    mid: MapId,
+    n: u32,
+    floor_level: u32,
+    f: F,
+) {
+    let npc_race_probability = match gd.region.get_site(mid.sid()).content {
+        SiteContent::AutoGenDungeon { dungeon_kind, .. } => {
+            &RULES.dungeon_gen[&dungeon_kind].npc_race_probability
+        }
+        SiteContent::Encounter { ref table } => match RULES.encounter.tables.get(table) {
+            Some(table) => &table.npc_race_probability,
+            None => {
+                warn!("unknown encounter table \"{}\"", table);
+                &RULES.dungeon_gen[&DungeonKind::Ruin].npc_race_probability
+            }
+        },
+        _ => &RULES.dungeon_gen[&DungeonKind::Ruin].npc_race_probability,
     };
 
     for _ in 0..n {
-        if let Some(p) = choose_empty_tile(gd.region.get_map(mid)) {
-            let chara = create_npc_chara(dungeon_kind, floor_level);
+        if let Some(p) = choose_empty_tile_where(gd.region.get_map(mid), &f) {
+            let chara = create_npc_chara(npc_race_probability, floor_level);
             trace!("Generate new npc {}", chara.to_text());
             let cid = gd.add_chara_to_map(chara, mid);
             let map = gd.region.get_map_mut(mid);
@@ -105,12 +190,17 @@ pub fn gen_npcs(gd: &mut GameData, mid: MapId, n: u32, floor_level: u32) {
 
 /// Choose one empty tile in random
 pub fn choose_empty_tile(map: &Map) -> Option<Vec2d> {
+    choose_empty_tile_where(map, |_| true)
+}
+
+/// Choose one empty tile satisfying the condition in random
+pub fn choose_empty_tile_where<F: Fn(Vec2d) -> bool>(map: &Map, f: F) -> Option<Vec2d> {
     use rng::gen_range;
     const MAX_TRY: usize = 10;
 
     // Function to determine the tile is empty or not
-    let is_tile_empty = |tile: &TileInfo| {
-        if tile.wall.is_empty() && tile.chara.is_none() && tile.special.is_none() {
+    let is_tile_empty = |p: Vec2d, tile: &TileInfo| {
+        if tile.wall.is_empty() && tile.chara.is_none() && tile.special.is_none() && f(p) {
             let tile_idx = tile.main_tile();
             let tile_obj = gobj::get_obj(tile_idx);
             tile_obj.kind == TileKind::Ground
@@ -124,13 +214,17 @@ pub fn choose_empty_tile(map: &Map) -> Option<Vec2d> {
         let tile = &map.tile[p];
 
         // Empty tile don't has wall, chara, and isn't special tile.
-        if is_tile_empty(tile) {
+        if is_tile_empty(p, tile) {
             return Some(p);
         }
     }
 
     // If random tile choosing is failed many times, count empty tiles and choose
-    let n_empty_tile = map.tile.iter().filter(|t| is_tile_empty(t)).count();
+    let n_empty_tile = map
+        .tile
+        .iter_with_idx()
+        .filter(|&(p, t)| is_tile_empty(p, t))
+        .count();
     if n_empty_tile == 0 {
         None
     } else {
@@ -138,7 +232,7 @@ pub fn choose_empty_tile(map: &Map) -> Option<Vec2d> {
         let p = map
             .tile
             .iter_with_idx()
-            .filter(|&(_, t)| is_tile_empty(t))
+            .filter(|&(p, t)| is_tile_empty(p, t))
             .nth(r)
             .unwrap()
             .0;
@@ -152,7 +246,7 @@ pub fn gen_items(gd: &mut GameData, mid: MapId) {
     let item_gen_probability = {
         let site = gd.region.get_site(mid.sid());
         match site.content {
-            SiteContent::AutoGenDungeon { dungeon_kind } => {
+            SiteContent::AutoGenDungeon { dungeon_kind, .. } => {
                 RULES.dungeon_gen[&dungeon_kind].item_gen_probability
             }
             _ => {
@@ -172,8 +266,12 @@ pub fn gen_items(gd: &mut GameData, mid: MapId) {
     let map = gd.region.get_map_mut(mid);
 
     for p in map.tile.iter_idx() {
-        let tile = &mut map.tile[p];
-        if !tile.wall.is_empty() {
+        let tile = &map.tile[p];
+        // Items must not block stairs and other special tiles
+        if !tile.wall.is_empty() || !tile.special.is_none() {
+            continue;
+        }
+        if gobj::get_obj(tile.main_tile()).kind != TileKind::Ground {
             continue;
         }
 
@@ -183,6 +281,81 @@ pub fn gen_items(gd: &mut GameData, mid: MapId) {
     }
 }
 
+/// Locate traps for a new map
+pub fn gen_traps(gd: &mut GameData, mid: MapId) {
+    use rng::*;
+    let (trap_range, trap_kind_probability) = {
+        let site = gd.region.get_site(mid.sid());
+        match site.content {
+            SiteContent::AutoGenDungeon { dungeon_kind, .. } => {
+                let params = &RULES.dungeon_gen[&dungeon_kind];
+                (params.trap_range, &params.trap_kind_probability)
+            }
+            _ => {
+                return;
+            } // No trap generation
+        }
+    };
+    let sum: f32 = trap_kind_probability.values().sum();
+    if !(sum > 0.0) {
+        return;
+    }
+    let n_trap = gen_range(trap_range[0], trap_range[1] + 1);
+    let map = gd.region.get_map_mut(mid);
+
+    for _ in 0..n_trap {
+        let p = if let Some(p) = choose_empty_tile(map) {
+            p
+        } else {
+            warn!("Failed trap generating because empty tile not found");
+            return;
+        };
+
+        let r = gen_range(0.0, sum);
+        let mut s = 0.0;
+        for (kind, probability) in trap_kind_probability.iter() {
+            s += probability;
+            if r < s {
+                map.tile[p].special = SpecialTileKind::Trap {
+                    kind: *kind,
+                    detected: false,
+                };
+                break;
+            }
+        }
+    }
+}
+
+/// Lock some doors of a new map
+pub fn lock_doors(gd: &mut GameData, mid: MapId) {
+    use rng::*;
+    let locked_door_probability = {
+        let site = gd.region.get_site(mid.sid());
+        match site.content {
+            SiteContent::AutoGenDungeon { dungeon_kind, .. } => {
+                RULES.dungeon_gen[&dungeon_kind].locked_door_probability
+            }
+            _ => {
+                return;
+            } // No locked doors
+        }
+    };
+    let level = mid.floor() + RULES.door.base_lock_level;
+    let map = gd.region.get_map_mut(mid);
+
+    for p in map.tile.iter_idx() {
+        if map.get_door(p) != Some(DoorState::Closed) {
+            continue;
+        }
+
+        if get_rng().gen_bool(locked_door_probability) {
+            map.tile[p].special = SpecialTileKind::Door {
+                state: DoorState::Locked { level },
+            };
+        }
+    }
+}
+
 pub fn update_observed_map(game: &mut Game) {
     let view_map = &game.view_map;
     let map = game.gd.get_current_map_mut();
@@ -198,7 +371,11 @@ pub fn update_observed_map(game: &mut Game) {
         observed_tile.tile = true;
         observed_tile.wall = tile.wall;
         observed_tile.deco = tile.deco;
-        observed_tile.special = tile.special;
+        observed_tile.special = if tile.special.is_hidden() {
+            SpecialTileKind::None
+        } else {
+            tile.special
+        };
         observed_tile.items.clear();
 
         if let Some(ref item_list) = tile.item_list {
@@ -208,3 +385,35 @@ pub fn update_observed_map(game: &mut Game) {
         }
     }
 }
+
+#[test]
+fn player_pos_on_blocked_tile() {
+    use common::objholder::WallIdx;
+
+    let mut map = Map::new(4, 3);
+    let any_tile = |_: &TileInfo| true;
+    map.tile[Vec2d(1, 1)].wall = WallIdxPP::new(WallIdx::from_usize(0));
+    map.tile[Vec2d(2, 1)].chara = Some(CharaId::OnMap {
+        mid: MapId::default(),
+        n: 0,
+    });
+    map.tile[Vec2d(3, 2)].chara = Some(CharaId::Player);
+
+    assert_eq!(
+        player_pos_near(&map, Vec2d(0, 0), any_tile),
+        Some(Vec2d(0, 0))
+    );
+    // The player can be moved on the same map
+    assert_eq!(
+        player_pos_near(&map, Vec2d(3, 2), any_tile),
+        Some(Vec2d(3, 2))
+    );
+    // Walls and other characters are avoided
+    for &blocked in &[Vec2d(1, 1), Vec2d(2, 1)] {
+        let pos = player_pos_near(&map, blocked, any_tile).unwrap();
+        assert_eq!(pos.mdistance(blocked), 1);
+        assert!(map.tile[pos].wall.is_empty());
+        assert!(map.tile[pos].chara.is_none());
+    }
+    assert_eq!(player_pos_near(&map, Vec2d(1, 1), |_| false), None);
+}
@@ -0,0 +1,85 @@
+//! Validation of generated maps and maps created from templates
+
+use crate::map_generator::{analyze, reach_map, MapReport};
+use array2d::*;
+use common::gamedata::*;
+use common::gobj;
+use common::obj::TileKind;
+
+/// Check stairs and boundaries are reachable and not blocked.
+/// Maps except for the deepest floor must have stairs to the next floor.
+pub fn validate_map(map: &Map, deepest_floor: bool) -> MapReport {
+    let passable = Array2d::from_fn(map.w, map.h, |p| {
+        let tile = &map.tile[p];
+        tile.wall.is_empty() && gobj::get_obj(tile.main_tile()).kind == TileKind::Ground
+    });
+
+    let mut problems = Vec::new();
+    let mut stairs = Vec::new();
+    let mut has_down_stairs = false;
+    for (p, tile) in map.tile.iter_with_idx() {
+        if let SpecialTileKind::Stairs { kind, .. } = tile.special {
+            stairs.push(p);
+            has_down_stairs |= kind == StairsKind::DownStairs;
+            if !passable[p] {
+                problems.push(format!("stairs at {} is not passable", p));
+            }
+            if tile.item_list.is_some() {
+                problems.push(format!("items are on stairs at {}", p));
+            }
+            if tile.chara.is_some() {
+                problems.push(format!("a character is on stairs at {}", p));
+            }
+        }
+    }
+
+    if !deepest_floor && !has_down_stairs {
+        problems.push("no stairs to the next floor".to_owned());
+    }
+
+    let (w, h) = (map.w as i32, map.h as i32);
+    let boundaries = [
+        ("north", map.boundary.n, RectIter::new((0, 0), (w - 1, 0))),
+        (
+            "south",
+            map.boundary.s,
+            RectIter::new((0, h - 1), (w - 1, h - 1)),
+        ),
+        (
+            "east",
+            map.boundary.e,
+            RectIter::new((w - 1, 0), (w - 1, h - 1)),
+        ),
+        ("west", map.boundary.w, RectIter::new((0, 0), (0, h - 1))),
+    ];
+    let boundary_tiles: Vec<(&str, Vec<Vec2d>)> = boundaries
+        .iter()
+        .filter(|(_, behavior, _)| *behavior != BoundaryBehavior::None)
+        .map(|(name, _, edge)| (*name, edge.clone().filter(|p| passable[*p]).collect()))
+        .collect();
+
+    // Maps without stairs are entered from boundaries
+    let start = if stairs.contains(&map.entrance) {
+        map.entrance
+    } else if let Some(p) = boundary_tiles.iter().flat_map(|(_, tiles)| tiles).next() {
+        *p
+    } else {
+        passable
+            .iter_with_idx()
+            .find(|(_, a)| **a)
+            .map(|(p, _)| p)
+            .unwrap_or(map.entrance)
+    };
+
+    let mut report = analyze(&passable, start, &stairs);
+    if report.n_reachable > 0 {
+        let reach = reach_map(&passable, start);
+        for (name, tiles) in &boundary_tiles {
+            if !tiles.iter().any(|p| reach[*p]) {
+                problems.push(format!("{} boundary is not reachable", name));
+            }
+        }
+    }
+    report.problems.extend(problems);
+    report
+}
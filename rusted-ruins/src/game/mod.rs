@@ -4,7 +4,9 @@ mod animation;
 pub mod chara;
 mod combat;
 mod command;
+mod door;
 mod dungeon_gen;
+mod encounter;
 mod eval_expr;
 pub mod frequent_tex;
 mod infogetter;
@@ -15,12 +17,15 @@ mod npc;
 pub mod playeract;
 pub mod quest;
 mod region;
+mod region_gen;
 pub mod saveload;
 mod script;
 pub mod shop;
 pub mod site;
 mod skill;
 mod town;
+mod trap;
+mod trigger;
 mod turnloop;
 pub mod view;
 
@@ -28,11 +33,13 @@ pub use self::animation::Animation;
 pub use self::command::Command;
 pub use self::infogetter::InfoGetter;
 pub use self::playeract::DoPlayerAction;
-pub use self::script::TalkText;
 use self::script::*;
+pub use self::script::{ScriptEngine, TalkText};
 use array2d::Vec2d;
 use common::gamedata::*;
+use common::script::TriggerScript;
 use std::borrow::Cow;
+use std::collections::VecDeque;
 use std::path::PathBuf;
 
 #[derive(Clone, Copy, PartialEq, Eq)]
@@ -51,6 +58,8 @@ pub struct Game {
     dialog_open_request: Option<DialogOpenRequest>,
     dying_charas: Vec<CharaId>,
     script: Option<ScriptEngine>,
+    /// Scripts launched by events, waiting for the current script to finish
+    triggered_scripts: VecDeque<(TriggerScript, Option<CharaId>)>,
     /// Player's current target of shot and similer actions
     target_chara: Option<CharaId>,
     save_dir: Option<PathBuf>,
@@ -68,6 +77,7 @@ impl Game {
             dialog_open_request: None,
             dying_charas: Vec::new(),
             script: None,
+            triggered_scripts: VecDeque::new(),
             target_chara: None,
             save_dir: Some(save_dir),
             view_map: view::ViewMap::new(),
@@ -75,6 +85,11 @@ impl Game {
         }
     }
 
+    /// Set the directory maps are loaded from and saved to
+    pub fn set_save_dir(&mut self, save_dir: PathBuf) {
+        self.save_dir = Some(save_dir);
+    }
+
     /// Create empty Game. This is used before starting actual gameplay.
     pub fn empty() -> Game {
         Game {
@@ -84,6 +99,7 @@ impl Game {
             dialog_open_request: None,
             dying_charas: Vec::new(),
             script: None,
+            triggered_scripts: VecDeque::new(),
             target_chara: None,
             save_dir: None,
             view_map: view::ViewMap::new(),
@@ -132,8 +148,38 @@ impl Game {
     }
 
     pub fn start_script(&mut self, id: &str, cid: Option<CharaId>) {
-        self.script = Some(ScriptEngine::new(id, cid));
-        self.advance_script(None);
+        self.start_script_from(id, "start", cid);
+    }
+
+    /// Start script from the given section
+    pub fn start_script_from(&mut self, id: &str, section: &str, cid: Option<CharaId>) {
+        self.start_script_engine(ScriptEngine::new(id, section, cid), cid);
+    }
+
+    /// Start script with the given engine
+    pub fn start_script_engine(
+        &mut self,
+        engine: ScriptEngine,
+        cid: Option<CharaId>,
+    ) -> AdvanceScriptResult {
+        self.gd.vars.begin_script(cid);
+        self.script = Some(engine);
+        self.advance_script(None)
+    }
+
+    /// Queue a script launched by an event
+    pub fn trigger_script(&mut self, script: TriggerScript, cid: Option<CharaId>) {
+        self.triggered_scripts.push_back((script, cid));
+    }
+
+    /// Start a queued script if no other script is running
+    pub fn start_triggered_script(&mut self) {
+        if self.script.is_some() || self.dialog_open_request.is_some() {
+            return;
+        }
+        if let Some((script, cid)) = self.triggered_scripts.pop_front() {
+            self.start_script_from(&script.id, script.section(), cid);
+        }
     }
 
     /// Advance current script.
@@ -156,7 +202,10 @@ impl Game {
             }
             ExecResult::Talk(cid, talk_text, need_open_talk_dialog) => {
                 if need_open_talk_dialog {
-                    self.request_dialog_open(DialogOpenRequest::Talk { cid, talk_text });
+                    self.request_dialog_open(DialogOpenRequest::Talk {
+                        cid,
+                        talk_text: talk_text.clone(),
+                    });
                 }
                 AdvanceScriptResult::UpdateTalkText(talk_text)
             }
@@ -172,6 +221,19 @@ impl Game {
                 self.request_dialog_open(DialogOpenRequest::Quest);
                 AdvanceScriptResult::Continue
             }
+            ExecResult::Teleport(mid, pos) => {
+                map::switch_map_with_pos(self, mid, Some(pos));
+                self.advance_script(None)
+            }
+            ExecResult::GainExp(exp) => {
+                chara::level::gain_exp(self, CharaId::Player, exp);
+                // Wait for the level up dialog
+                if self.dialog_open_request.is_some() {
+                    AdvanceScriptResult::Continue
+                } else {
+                    self.advance_script(None)
+                }
+            }
         }
     }
 
@@ -208,6 +270,11 @@ pub enum DialogOpenRequest {
     },
     ShopSell,
     Quest,
+    LevelUp {
+        level: u32,
+        old_attr: CharaAttributes,
+        new_attr: CharaAttributes,
+    },
     GameOver,
 }
 
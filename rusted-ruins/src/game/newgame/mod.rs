@@ -34,15 +34,13 @@ impl NewGameBuilder {
 
             gd.meta.set_save_name(self.player_name.as_ref().unwrap());
 
-            super::region::add_region(&mut gd, &RULES.newgame.start_region);
+            let rid = super::region::add_region(&mut gd, &RULES.newgame.start_region);
 
-            let mid = MapId::RegionMap {
-                rid: RegionId::default(),
-            };
+            let mid = MapId::RegionMap { rid };
             gd.set_initial_mapid(mid);
-            let start_pos = RULES.newgame.start_pos;
 
-            super::region::gen_dungeon(&mut gd, mid.rid());
+            let start_pos =
+                super::region::start_pos(gd, mid.rid()).unwrap_or(RULES.newgame.start_pos);
 
             let chara_template_id = &RULES.newgame.chara_template_table[&self.chara_class.unwrap()];
             let mut chara = super::chara::gen::create_chara(gobj::id_to_idx(chara_template_id), 1);
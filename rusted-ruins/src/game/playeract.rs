@@ -26,6 +26,10 @@ impl<'a> DoPlayerAction<'a> {
         &mut self.0.gd
     }
 
+    pub fn save_file(&mut self) {
+        self.0.save_file();
+    }
+
     pub fn try_move(&mut self, dir: Direction) {
         let dest_tile = self
             .gd()
@@ -69,6 +73,7 @@ impl<'a> DoPlayerAction<'a> {
         // Move to the next tile
         if action::try_move(self.0, CharaId::Player, dir) {
             self.0.finish_player_turn();
+            super::encounter::try_encounter(self.0);
         }
     }
 
@@ -80,9 +85,11 @@ impl<'a> DoPlayerAction<'a> {
             ExitToOutside,
             EnterSite(String),
             ChangeFloor,
+            Travel(String),
         }
 
         let log_msg: LogMessage;
+        let mut next_pos: Option<Vec2d> = None;
 
         // Use stairs
         if dir.is_none() {
@@ -106,7 +113,16 @@ impl<'a> DoPlayerAction<'a> {
                         // Enter other site from region map
                         let pos = gd.player_pos();
                         let region = gd.region.get(mid.rid());
-                        if let Some(sid) = region.get_id_by_pos(pos) {
+                        if let Some(link) = region.get_travel_link(pos) {
+                            // Travel to another region
+                            let dest = gd.region.get(link.dest);
+                            let msg = replace_str!(
+                                crate::text::ui_txt("dialog.travel_region");
+                                region=dest);
+                            log_msg = LogMessage::Travel(dest.to_text().to_string());
+                            next_pos = Some(link.dest_pos);
+                            (MapId::from(link.dest), msg.into())
+                        } else if let Some(sid) = region.get_id_by_pos(pos) {
                             let mid = MapId::site_first_floor(sid);
                             let site = gd.region.get_site(sid);
                             let msg = replace_str!(
@@ -140,8 +156,11 @@ impl<'a> DoPlayerAction<'a> {
                     LogMessage::ChangeFloor => {
                         game_log_i!("change-floor"; player=pa.gd().chara.get(CharaId::Player));
                     }
+                    LogMessage::Travel(r) => {
+                        game_log_i!("travel-region"; player=pa.gd().chara.get(CharaId::Player), region=r);
+                    }
                 }
-                super::map::switch_map(pa.0, next_mid);
+                super::map::switch_map_with_pos(pa.0, next_mid, next_pos);
             });
             self.0
                 .request_dialog_open(DialogOpenRequest::YesNo { callback: cb, msg });
@@ -174,6 +193,22 @@ impl<'a> DoPlayerAction<'a> {
                     self.gd().get_current_mapid().set_floor(floor)
                 }
                 BoundaryBehavior::MapId(_, _) => unimplemented!(),
+                BoundaryBehavior::Region(rid) => {
+                    let player_pos = self.gd().player_pos();
+                    next_pos = Some(super::region::border_dest_pos(
+                        self.gd_mut(),
+                        rid,
+                        player_pos,
+                        dir,
+                    ));
+                    log_msg = LogMessage::Travel(self.gd().region.get(rid).to_text().to_string());
+                    MapId::from(rid)
+                }
+            };
+            let msg = if let LogMessage::Travel(ref r) = log_msg {
+                replace_str!(crate::text::ui_txt("dialog.travel_region"); region=r).into()
+            } else {
+                msg_switch_map(next_mid)
             };
             let cb = Box::new(move |pa: &mut DoPlayerAction, result: bool| {
                 if !result {
@@ -189,13 +224,14 @@ impl<'a> DoPlayerAction<'a> {
                     LogMessage::ChangeFloor => {
                         game_log_i!("change-floor"; player=pa.gd().chara.get(CharaId::Player));
                     }
+                    LogMessage::Travel(r) => {
+                        game_log_i!("travel-region"; player=pa.gd().chara.get(CharaId::Player), region=r);
+                    }
                 }
-                super::map::switch_map(pa.0, next_mid);
-            });
-            self.0.request_dialog_open(DialogOpenRequest::YesNo {
-                callback: cb,
-                msg: msg_switch_map(next_mid),
+                super::map::switch_map_with_pos(pa.0, next_mid, next_pos);
             });
+            self.0
+                .request_dialog_open(DialogOpenRequest::YesNo { callback: cb, msg });
         }
     }
 
@@ -216,6 +252,37 @@ impl<'a> DoPlayerAction<'a> {
         }
     }
 
+    /// Close an adjacent open door
+    pub fn close_door(&mut self) {
+        if super::door::try_close(self.0, CharaId::Player) {
+            self.0.finish_player_turn();
+        }
+    }
+
+    /// Throw one item to current target
+    pub fn throw_item(&mut self, il: ItemLocation) {
+        if self.0.target_chara.is_none() {
+            self.0.target_chara = crate::game::map::search::search_nearest_target(
+                self.gd(),
+                CharaId::Player,
+                Relationship::HOSTILE,
+            );
+        }
+
+        let target_pos = if let Some(target) = self.0.target_chara {
+            self.gd().get_current_map().chara_pos(target)
+        } else {
+            None
+        };
+
+        if let Some(target_pos) = target_pos {
+            super::action::throw_item(self.0, il, CharaId::Player, target_pos);
+            self.0.finish_player_turn();
+        } else {
+            game_log_i!("no-target");
+        }
+    }
+
     /// Pick up an item on tile
     pub fn pick_up_item(&mut self, il: ItemLocation, n: u32) -> bool {
         let gd = self.gd_mut();
@@ -223,7 +290,9 @@ impl<'a> DoPlayerAction<'a> {
             cid: CharaId::Player,
         };
         game_log_i!("item-pickup"; chara=gd.chara.get(CharaId::Player), item=gd.get_item(il).0);
+        let item = gd.get_item(il).0.clone();
         gd.move_item(il, player_item_list_location, n);
+        super::trigger::pick_up_item(self.0, &item);
         true
     }
 
@@ -3,7 +3,9 @@
 use super::chara::gen::choose_npc_chara_template;
 use super::Game;
 use common::gamedata::*;
+use common::gobj;
 use common::objholder::CharaTemplateIdx;
+use common::script::ScriptQuest;
 use rules::RULES;
 
 /// Update quest list of current town
@@ -42,10 +44,39 @@ pub fn undertake_quest(game: &mut Game, i: u32) {
     game.gd.quest.start_new_quest(quest);
 }
 
+/// Start a quest given by a script. Returns false if the quest is invalid.
+pub fn start_script_quest(gd: &mut GameData, quest: &ScriptQuest, money: i64, exp: u32) -> bool {
+    let reward = Reward {
+        money,
+        exp,
+        item: Vec::new(),
+    };
+
+    let quest = match quest {
+        ScriptQuest::SlayMonsters(id, goal) => {
+            let idx = if let Some(idx) = gobj::id_to_idx_checked::<CharaTemplateIdx>(id) {
+                idx
+            } else {
+                warn!("script error: unknown chara template {}", id);
+                return false;
+            };
+            Quest::SlayMonsters {
+                reward,
+                idx,
+                goal: *goal,
+                killed: 0,
+            }
+        }
+    };
+    gd.quest.start_new_quest(quest);
+    true
+}
+
 /// Generate an quest
 fn gen_quest() -> Quest {
     let reward = Reward {
         money: 1000,
+        exp: 100,
         item: Vec::new(),
     };
 
@@ -76,8 +107,11 @@ pub fn count_slayed_monster(gd: &mut GameData, t: CharaTemplateIdx) {
     }
 }
 
-pub fn receive_rewards(gd: &mut GameData) -> bool {
+/// Receive rewards of completed quests.
+/// Returns the exp to be gained by the player, or None if no quest is completed.
+pub fn receive_rewards(gd: &mut GameData) -> Option<u32> {
     let mut money = 0;
+    let mut exp = 0;
     let mut exist_completed_quest = false;
 
     for (state, quest) in gd.quest.iter_mut() {
@@ -85,14 +119,19 @@ pub fn receive_rewards(gd: &mut GameData) -> bool {
             exist_completed_quest = true;
             let reward = quest.reward();
             money += reward.money;
+            exp += reward.exp;
             *state = QuestState::RewardReceived;
         }
     }
 
-    if exist_completed_quest {
-        gd.quest.remove_reward_received();
-        gd.player.add_money(money);
-        game_log_i!("quest-reward-receive-money"; money=money);
+    if !exist_completed_quest {
+        return None;
+    }
+    gd.quest.remove_reward_received();
+    gd.player.add_money(money);
+    game_log_i!("quest-reward-receive-money"; money=money);
+    if exp > 0 {
+        game_log_i!("quest-reward-receive-exp"; exp=exp);
     }
-    exist_completed_quest
+    Some(exp)
 }
@@ -1,29 +1,214 @@
 use super::map::choose_empty_tile;
+use super::region_gen::{choose_dungeon, site_positions, RegionTerrain};
 use super::saveload::{gen_box_id, get_map_dir};
+use array2d::{Direction, Vec2d};
 use common::basic::MAX_AUTO_GEN_DUNGEONS;
 use common::gamedata::*;
 use common::gobj;
+use common::obj::TileKind;
 use common::regiongen::*;
+use common::sitegen::{SiteGenObject, SitePlacement};
 use rng::*;
+use rules::RULES;
 
-pub fn add_region(gd: &mut GameData, id: &str) {
+/// Add the region and regions connected to it.
+/// If the region already exists, returns its id.
+pub fn add_region(gd: &mut GameData, id: &str) -> RegionId {
+    if let Some(rid) = gd.region.search_region(id) {
+        return rid;
+    }
     let rg: &RegionGenObject = gobj::get_by_id(id);
 
-    let map = if let Some(map) = super::map::from_template::from_template_id(&rg.id) {
-        map
+    let (map, seed) = if let Some(params) = rg.gen.as_ref() {
+        let seed = get_rng().gen();
+        (super::region_gen::gen_region_map(params, seed), Some(seed))
+    } else if let Some(map) = super::map::from_template::from_template_id(&rg.id) {
+        (map, None)
     } else {
         error!("Map generation failed from \"{}\"", rg.id);
         panic!();
     };
 
-    let region = Region::new(id, map, gen_box_id(gd));
+    let mut region = Region::new(id, map, gen_box_id(gd));
+    region.seed = seed;
     let rid = gd.region.add_region(region);
     add_sites_from_genobj(gd, rg, rid);
+    gen_dungeon(gd, rid);
+
+    for connection in &rg.connections {
+        let dest = add_region(gd, &connection.region);
+        connect_regions(gd, rid, dest, &connection.via);
+    }
+    rid
+}
+
+/// Choose the position to start on the region. None if the region is not generated.
+pub fn start_pos(gd: &GameData, rid: RegionId) -> Option<Vec2d> {
+    let region = gd.region.get(rid);
+    let (params, seed) = gen_params(region)?;
+    let map = region.get_map();
+    let terrain = RegionTerrain::new(map, params, seed);
+    terrain.choose_pos(map, &[], &SitePlacement::default(), 0)
+}
+
+/// The position on the destination region map after crossing the border.
+/// The nearest passable tile to the corresponding position on the opposite border is chosen.
+pub fn border_dest_pos(gd: &mut GameData, dest: RegionId, pos: Vec2d, dir: Direction) -> Vec2d {
+    let (src_w, src_h) = {
+        let map = gd.get_current_map();
+        (map.w as i32, map.h as i32)
+    };
+    let mid = MapId::from(dest);
+    gd.region
+        .preload_map(mid, get_map_dir(gd))
+        .expect("failed to load map");
+    let map = gd.region.get_map(mid);
+    let target = border_target(pos, dir, (src_w, src_h), (map.w as i32, map.h as i32));
+
+    map.tile
+        .iter_with_idx()
+        .filter(|(_, tile)| {
+            tile.wall.is_empty()
+                && tile.chara.is_none()
+                && gobj::get_obj(tile.main_tile()).kind == TileKind::Ground
+        })
+        .min_by_key(|(p, _)| p.mdistance(target))
+        .map(|(p, _)| p)
+        .unwrap_or(target)
+}
+
+/// The position on the opposite border of the destination map corresponding to `pos`
+fn border_target(pos: Vec2d, dir: Direction, src_size: (i32, i32), size: (i32, i32)) -> Vec2d {
+    let ((src_w, src_h), (w, h)) = (src_size, size);
+    let scale = |a: i32, src_len: i32, len: i32| {
+        if src_len > 1 {
+            a * (len - 1) / (src_len - 1)
+        } else {
+            0
+        }
+    };
+    let dest_pos = pos + dir.as_vec();
+    if dest_pos.1 < 0 {
+        Vec2d(scale(pos.0, src_w, w), h - 1)
+    } else if dest_pos.1 >= src_h {
+        Vec2d(scale(pos.0, src_w, w), 0)
+    } else if dest_pos.0 < 0 {
+        Vec2d(w - 1, scale(pos.1, src_h, h))
+    } else {
+        Vec2d(0, scale(pos.1, src_h, h))
+    }
+}
+
+fn connect_regions(gd: &mut GameData, rid: RegionId, dest: RegionId, via: &RegionConnectionKind) {
+    match via {
+        RegionConnectionKind::Border(border) => {
+            set_border(gd, rid, *border, BoundaryBehavior::Region(dest));
+            set_border(gd, dest, border.opposite(), BoundaryBehavior::Region(rid));
+        }
+        RegionConnectionKind::Site {
+            symbol,
+            pos,
+            dest_pos,
+            placement,
+        } => {
+            // Skip if the other region already made this connection
+            let connected = gd
+                .region
+                .get(rid)
+                .travel_links()
+                .iter()
+                .any(|link| link.dest == dest);
+            if connected {
+                return;
+            }
+            let pos = pos.or_else(|| choose_travel_site_pos(gd, rid, placement));
+            let dest_pos = dest_pos.or_else(|| choose_travel_site_pos(gd, dest, placement));
+            let (pos, dest_pos) = match (pos, dest_pos) {
+                (Some(pos), Some(dest_pos)) => (pos, dest_pos),
+                _ => {
+                    warn!("No place for travel sites between {:?} and {:?}", rid, dest);
+                    return;
+                }
+            };
+            add_travel_site(gd, rid, pos, dest, dest_pos, *symbol);
+            add_travel_site(gd, dest, dest_pos, rid, pos, *symbol);
+            debug!(
+                "Connected {:?} at {} and {:?} at {} by {:?}",
+                rid, pos, dest, dest_pos, symbol
+            );
+        }
+    }
+}
+
+fn set_border(gd: &mut GameData, rid: RegionId, border: MapBorder, behavior: BoundaryBehavior) {
+    let boundary = &mut gd.region.get_map_mut(MapId::from(rid)).boundary;
+    match border {
+        MapBorder::North => boundary.n = behavior,
+        MapBorder::South => boundary.s = behavior,
+        MapBorder::East => boundary.e = behavior,
+        MapBorder::West => boundary.w = behavior,
+    }
+}
+
+fn add_travel_site(
+    gd: &mut GameData,
+    rid: RegionId,
+    pos: Vec2d,
+    dest: RegionId,
+    dest_pos: Vec2d,
+    symbol: SiteSymbolKind,
+) {
+    gd.region.get_mut(rid).add_travel_link(TravelLink {
+        pos,
+        dest,
+        dest_pos,
+    });
+    gd.region.get_map_mut(MapId::from(rid)).tile[pos].special =
+        SpecialTileKind::SiteSymbol { kind: symbol };
+}
+
+fn choose_travel_site_pos(
+    gd: &GameData,
+    rid: RegionId,
+    placement: &SitePlacement,
+) -> Option<Vec2d> {
+    let region = gd.region.get(rid);
+    let map = region.get_map();
+    if let Some((params, seed)) = gen_params(region) {
+        let terrain = RegionTerrain::new(map, params, seed);
+        terrain.choose_pos(
+            map,
+            &site_positions(map),
+            placement,
+            params.min_site_distance,
+        )
+    } else {
+        choose_empty_tile(map)
+    }
+}
+
+/// Generation parameters and the seed if the region is generated procedurally
+pub fn gen_params(region: &Region) -> Option<(&'static RegionMapGenParams, u64)> {
+    let seed = region.seed?;
+    let rg: &RegionGenObject = gobj::get_by_id(&region.name);
+    Some((rg.gen.as_ref()?, seed))
+}
+
+/// Dungeon kinds which can be generated in the region
+fn dungeon_pool(region: &Region) -> Vec<DungeonKind> {
+    let rg: &RegionGenObject = gobj::get_by_id(&region.name);
+    if rg.dungeons.is_empty() {
+        RULES.dungeon_gen.keys().cloned().collect()
+    } else {
+        rg.dungeons.clone()
+    }
 }
 
 /// Generate dungeons up to the max
 pub fn gen_dungeon_max(gd: &mut GameData, rid: RegionId) {
     use common::basic::MAX_AUTO_GEN_DUNGEONS;
+    // Expired dungeons are replaced with new ones
+    super::dungeon_gen::expire_dungeons(gd, rid);
     let n_autogen_dungeons = gd.region.get(rid).get_site_n(SiteKind::AutoGenDungeon);
 
     if n_autogen_dungeons == MAX_AUTO_GEN_DUNGEONS {
@@ -42,23 +227,32 @@ pub fn gen_dungeon(gd: &mut GameData, rid: RegionId) {
     }
 
     let mid = MapId::from(rid);
-    gd.region.preload_map(mid, get_map_dir(gd));
+    gd.region
+        .preload_map(mid, get_map_dir(gd))
+        .expect("failed to load map");
 
-    let pos = {
+    let (dungeon_kind, pos) = if let Some(dungeon) = choose_dungeon_on_generated_region(gd, rid) {
+        dungeon
+    } else {
         let region_map = gd.region.get_map(mid);
-        match choose_empty_tile(region_map) {
+        let pos = match choose_empty_tile(region_map) {
             Some(pos) => pos,
             None => {
                 warn!("Dungeon generation failed: No empty tile");
                 return;
             }
-        }
+        };
+        let dungeon_kind = match dungeon_pool(gd.region.get(rid)).choose(&mut get_rng()) {
+            Some(dungeon_kind) => *dungeon_kind,
+            None => {
+                warn!("Dungeon generation failed: No dungeon kind for {:?}", rid);
+                return;
+            }
+        };
+        (dungeon_kind, pos)
     };
-    let dungeon_kind = *[DungeonKind::Cave, DungeonKind::Ruin]
-        .choose(&mut get_rng())
-        .unwrap();
 
-    super::dungeon_gen::add_dungeon_site(gd, dungeon_kind, pos);
+    super::dungeon_gen::add_dungeon_site(gd, dungeon_kind, rid, pos);
 
     let region_map = gd.region.get_map_mut(mid);
     let site_symbol_kind = match dungeon_kind {
@@ -70,6 +264,20 @@ pub fn gen_dungeon(gd: &mut GameData, rid: RegionId) {
     };
 }
 
+/// Dungeon kinds are distributed by biome on generated regions
+fn choose_dungeon_on_generated_region(
+    gd: &GameData,
+    rid: RegionId,
+) -> Option<(DungeonKind, Vec2d)> {
+    let region = gd.region.get(rid);
+    let (params, seed) = gen_params(region)?;
+    let dungeon = choose_dungeon(region.get_map(), params, seed, &dungeon_pool(region));
+    if dungeon.is_none() {
+        warn!("Dungeon generation failed: No tile for any dungeon kind");
+    }
+    dungeon
+}
+
 fn add_sites_from_genobj(gd: &mut GameData, rg: &RegionGenObject, rid: RegionId) {
     // Add towns
     for &(ref site_gen_id, pos) in &rg.towns {
@@ -79,4 +287,139 @@ fn add_sites_from_genobj(gd: &mut GameData, rg: &RegionGenObject, rid: RegionId)
             site_gen_id, pos, rid
         );
     }
+
+    // Add towns placed by their constraints on generated regions
+    let (params, seed) = match (rg.gen.as_ref(), gd.region.get(rid).seed) {
+        (Some(params), Some(seed)) => (params, seed),
+        _ => return,
+    };
+    let map = gd.region.get_map(MapId::from(rid));
+    let terrain = RegionTerrain::new(map, params, seed);
+    let positions: Vec<Option<Vec2d>> = rng::with_seed(seed, || {
+        let mut sites = site_positions(map);
+        params
+            .towns
+            .iter()
+            .map(|site_gen_id| {
+                let sg: &SiteGenObject = gobj::get_by_id(site_gen_id);
+                let pos = terrain.choose_pos(map, &sites, &sg.placement, params.min_site_distance);
+                sites.extend(pos);
+                pos
+            })
+            .collect()
+    });
+
+    for (site_gen_id, pos) in params.towns.iter().zip(positions) {
+        if let Some(pos) = pos {
+            super::town::add_town(gd, rid, pos, site_gen_id);
+            debug!(
+                "Created new town \"{}\" at {} in {:?}",
+                site_gen_id, pos, rid
+            );
+        } else {
+            warn!("No place for town \"{}\" in {:?}", site_gen_id, rid);
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_gamedata() -> (GameData, RegionId, RegionId) {
+    let mut gd = GameData::empty();
+    let a = gd.region.add_region(Region::new("a", Map::new(8, 6), 0));
+    let b = gd.region.add_region(Region::new("b", Map::new(5, 3), 1));
+    (gd, a, b)
+}
+
+#[test]
+fn connect_regions_by_border() {
+    let (mut gd, a, b) = test_gamedata();
+    connect_regions(
+        &mut gd,
+        a,
+        b,
+        &RegionConnectionKind::Border(MapBorder::East),
+    );
+
+    let boundary = gd.region.get_map(MapId::from(a)).boundary;
+    assert_eq!(boundary.e, BoundaryBehavior::Region(b));
+    assert_eq!(boundary.w, BoundaryBehavior::None);
+    let boundary = gd.region.get_map(MapId::from(b)).boundary;
+    assert_eq!(boundary.w, BoundaryBehavior::Region(a));
+    assert_eq!(boundary.e, BoundaryBehavior::None);
+}
+
+#[test]
+fn connect_regions_by_site() {
+    let (mut gd, a, b) = test_gamedata();
+    let via = RegionConnectionKind::Site {
+        symbol: SiteSymbolKind::Port,
+        pos: Some(Vec2d(1, 2)),
+        dest_pos: Some(Vec2d(3, 0)),
+        placement: SitePlacement::default(),
+    };
+    connect_regions(&mut gd, a, b, &via);
+    // The connection from the other region must not add sites again
+    connect_regions(&mut gd, b, a, &via);
+
+    let links = gd.region.get(a).travel_links();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].pos, Vec2d(1, 2));
+    assert_eq!(links[0].dest, b);
+    assert_eq!(links[0].dest_pos, Vec2d(3, 0));
+    let links = gd.region.get(b).travel_links();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].pos, Vec2d(3, 0));
+    assert_eq!(links[0].dest, a);
+    assert_eq!(links[0].dest_pos, Vec2d(1, 2));
+
+    let symbol = SpecialTileKind::SiteSymbol {
+        kind: SiteSymbolKind::Port,
+    };
+    assert_eq!(
+        gd.region.get_map(MapId::from(a)).tile[Vec2d(1, 2)].special,
+        symbol
+    );
+    assert_eq!(
+        gd.region.get_map(MapId::from(b)).tile[Vec2d(3, 0)].special,
+        symbol
+    );
+}
+
+#[test]
+fn add_existing_region() {
+    // Recursion on cyclic connections stops here without generating the region again
+    let (mut gd, a, b) = test_gamedata();
+    assert_eq!(add_region(&mut gd, "a"), a);
+    assert_eq!(add_region(&mut gd, "b"), b);
+}
+
+#[test]
+fn border_target_pos() {
+    let src = (8, 6);
+    let dest = (5, 3);
+    assert_eq!(
+        border_target(Vec2d(7, 0), Direction::N, src, dest),
+        Vec2d(4, 2)
+    );
+    assert_eq!(
+        border_target(Vec2d(0, 5), Direction::S, src, dest),
+        Vec2d(0, 0)
+    );
+    assert_eq!(
+        border_target(Vec2d(7, 5), Direction::E, src, dest),
+        Vec2d(0, 2)
+    );
+    assert_eq!(
+        border_target(Vec2d(0, 0), Direction::W, src, dest),
+        Vec2d(4, 0)
+    );
+    // Positions are scaled to the destination size
+    assert_eq!(
+        border_target(Vec2d(7, 4), Direction::E, src, dest),
+        Vec2d(0, 1)
+    );
+    assert_eq!(
+        border_target(Vec2d(4, 0), Direction::N, src, (1, 1)),
+        Vec2d(0, 0)
+    );
 }
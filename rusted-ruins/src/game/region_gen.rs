@@ -0,0 +1,202 @@
+//! Procedural generation of region maps.
+//! Biomes are determined by height and moisture noise created from the seed of the region,
+//! so the same terrain is regenerated from the stored seed.
+
+use crate::map_generator::{reach_map, value_noise};
+use array2d::*;
+use common::gamedata::*;
+use common::gobj;
+use common::obj::TileKind;
+use common::objholder::TileIdx;
+use common::regiongen::*;
+use common::sitegen::SitePlacement;
+use rng::*;
+use rules::RULES;
+
+const N_OCTAVE: u32 = 4;
+
+/// Create a region map from the seed
+pub fn gen_region_map(params: &RegionMapGenParams, seed: u64) -> Map {
+    let biome = biome_map(params, seed);
+    let mut map = Map::new(params.size.0 as u32, params.size.1 as u32);
+
+    let tiles: Vec<(Biome, TileIdx)> = params
+        .biome_tiles
+        .iter()
+        .filter_map(|bt| match gobj::id_to_idx_checked::<TileIdx>(&bt.tile) {
+            Some(idx) => Some((bt.biome, idx)),
+            None => {
+                warn!("Unknown tile \"{}\" for {:?}", bt.tile, bt.biome);
+                None
+            }
+        })
+        .collect();
+
+    for p in map.tile.iter_idx() {
+        if let Some((_, idx)) = tiles.iter().find(|(b, _)| *b == biome[p]) {
+            map.tile[p].tile = (*idx).into();
+        }
+    }
+    map
+}
+
+/// Biome of each tile. Water is in low places, and forests are in moist places.
+pub fn biome_map(params: &RegionMapGenParams, seed: u64) -> Array2d<Biome> {
+    let (height, moisture) = rng::with_seed(seed, || {
+        let height = value_noise(params.size, params.noise_scale, N_OCTAVE);
+        let moisture = value_noise(params.size, params.noise_scale / 2.0, N_OCTAVE);
+        (height, moisture)
+    });
+
+    Array2d::from_fn(params.size.0 as u32, params.size.1 as u32, |p| {
+        let p = Vec2d::from(p);
+        if height[p] < params.water_level {
+            Biome::Water
+        } else if height[p] > params.mountain_level {
+            Biome::Mountains
+        } else if moisture[p] > params.forest_level {
+            Biome::Forest
+        } else {
+            Biome::Plains
+        }
+    })
+}
+
+/// Terrain information to place sites on a generated region
+pub struct RegionTerrain {
+    biome: Array2d<Biome>,
+    /// The largest connected land. Sites are placed only on it to be reachable from each other.
+    mainland: Array2d<bool>,
+}
+
+impl RegionTerrain {
+    pub fn new(map: &Map, params: &RegionMapGenParams, seed: u64) -> RegionTerrain {
+        RegionTerrain {
+            biome: biome_map(params, seed),
+            mainland: mainland(map),
+        }
+    }
+
+    /// Choose a random position satisfying the placement constraints,
+    /// which is apart from the given sites by min_distance or more.
+    pub fn choose_pos(
+        &self,
+        map: &Map,
+        sites: &[Vec2d],
+        placement: &SitePlacement,
+        min_distance: u32,
+    ) -> Option<Vec2d> {
+        let candidates: Vec<Vec2d> = map
+            .tile
+            .iter_with_idx()
+            .filter(|(p, tile)| {
+                let biome = match self.biome.get(*p) {
+                    Some(biome) => *biome,
+                    None => return false,
+                };
+                let biome_ok = if placement.biomes.is_empty() {
+                    biome != Biome::Water
+                } else {
+                    placement.biomes.contains(&biome)
+                };
+                let water_ok = !placement.near_water
+                    || Direction::EIGHT_DIRS
+                        .iter()
+                        .any(|dir| self.biome.get(*p + dir.as_vec()) == Some(&Biome::Water));
+                self.mainland[*p]
+                    && biome_ok
+                    && water_ok
+                    && tile.special.is_none()
+                    && tile.chara.is_none()
+                    && sites
+                        .iter()
+                        .all(|site| site.mdistance(*p) >= min_distance as i32)
+            })
+            .map(|(p, _)| p)
+            .collect();
+        candidates.choose(&mut get_rng()).cloned()
+    }
+}
+
+/// Positions of site symbols on the region map
+pub fn site_positions(map: &Map) -> Vec<Vec2d> {
+    map.tile
+        .iter_with_idx()
+        .filter(|(_, tile)| match tile.special {
+            SpecialTileKind::SiteSymbol { .. } => true,
+            _ => false,
+        })
+        .map(|(p, _)| p)
+        .collect()
+}
+
+/// Choose a dungeon kind which can appear on the biome of the chosen position
+pub fn choose_dungeon(
+    map: &Map,
+    params: &RegionMapGenParams,
+    seed: u64,
+    kinds: &[DungeonKind],
+) -> Option<(DungeonKind, Vec2d)> {
+    let terrain = RegionTerrain::new(map, params, seed);
+    let sites = site_positions(map);
+    let mut kinds = kinds.to_vec();
+    kinds.shuffle(&mut get_rng());
+
+    for kind in kinds {
+        let placement = SitePlacement {
+            biomes: RULES.dungeon_gen[&kind].biomes.clone(),
+            near_water: false,
+        };
+        if let Some(pos) = terrain.choose_pos(map, &sites, &placement, params.min_site_distance) {
+            return Some((kind, pos));
+        }
+    }
+    None
+}
+
+fn mainland(map: &Map) -> Array2d<bool> {
+    let passable = Array2d::from_fn(map.w, map.h, |p| {
+        let tile = &map.tile[p];
+        tile.wall.is_empty() && gobj::get_obj(tile.main_tile()).kind == TileKind::Ground
+    });
+    let mut visited = Array2d::new(map.w, map.h, false);
+    let mut largest: Option<(Array2d<bool>, usize)> = None;
+
+    for p in passable.iter_idx() {
+        if !passable[p] || visited[p] {
+            continue;
+        }
+        let region = reach_map(&passable, p);
+        let n = region.iter().filter(|r| **r).count();
+        for (q, r) in region.iter_with_idx() {
+            if *r {
+                visited[q] = true;
+            }
+        }
+        if largest.as_ref().map(|(_, m)| n > *m).unwrap_or(true) {
+            largest = Some((region, n));
+        }
+    }
+
+    largest
+        .map(|(region, _)| region)
+        .unwrap_or_else(|| Array2d::new(map.w, map.h, false))
+}
+
+#[test]
+fn biome_map_test() {
+    let params = RegionMapGenParams {
+        size: Vec2d(32, 24),
+        noise_scale: 8.0,
+        water_level: 0.3,
+        mountain_level: 0.8,
+        forest_level: 0.6,
+        biome_tiles: Vec::new(),
+        towns: Vec::new(),
+        min_site_distance: 0,
+    };
+    // The same terrain is regenerated from the same seed
+    let biome = biome_map(&params, 42);
+    assert!(biome.iter().eq(biome_map(&params, 42).iter()));
+    assert!(!biome.iter().eq(biome_map(&params, 43).iter()));
+}
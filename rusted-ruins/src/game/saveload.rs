@@ -6,7 +6,7 @@ use std::fs;
 use std::path::PathBuf;
 
 impl Game {
-    pub fn save_file(&self) {
+    pub fn save_file(&mut self) {
         let save_dir = get_save_dir();
 
         if !save_dir.exists() {
@@ -1,34 +1,52 @@
 //! Script engine implementation
 
+use array2d::Vec2d;
 use common::gamedata::*;
 use common::gobj;
+use common::objholder::CharaTemplateIdx;
 use common::script::*;
 
 use crate::game::eval_expr::EvalExpr;
 use crate::game::InfoGetter;
+use crate::text;
 
 pub struct ScriptEngine {
     script: &'static Script,
     pos: ScriptPos,
     cid: Option<CharaId>,
     talking: bool,
+    /// Indices of the choices shown in the current talk
+    shown_choices: Vec<usize>,
+    debug_hook: Option<DebugHook>,
 }
 
+/// Called before executing each instruction. Used by debugging tools.
+pub type DebugHook = Box<dyn FnMut(&ScriptPos, &Instruction, &GameData)>;
+
 #[derive(PartialEq, Eq, Debug)]
 pub enum ExecResult {
     Talk(CharaId, TalkText, bool),
     ShopBuy(CharaId),
     ShopSell,
     Quest,
+    Teleport(MapId, Vec2d),
+    /// The player gains exp
+    GainExp(u32),
     Quit,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 pub struct TalkText {
     pub text_id: &'static str,
-    pub choices: Option<&'static [(String, String)]>,
+    /// Translated text with variables replaced
+    pub text: String,
+    /// Translated texts of the choices whose conditions are true
+    pub choices: Option<Vec<String>>,
 }
 
+/// Maximum depth of nested calls. Deeper calls are treated as infinite recursion.
+const MAX_CALL_DEPTH: usize = 64;
+
 /// Unwrap Value as bool
 macro_rules! as_bool {
     ($v:expr) => {{
@@ -83,27 +101,46 @@ macro_rules! ur {
 }
 
 impl ScriptEngine {
-    pub fn new(id: &str, cid: Option<CharaId>) -> ScriptEngine {
+    pub fn new(id: &str, section: &str, cid: Option<CharaId>) -> ScriptEngine {
         let script_obj: &ScriptObject = gobj::get_by_id(id);
+        ScriptEngine::from_script(&script_obj.script, section, cid)
+    }
+
+    /// Create engine for a script not loaded as an object
+    pub fn from_script(
+        script: &'static Script,
+        section: &str,
+        cid: Option<CharaId>,
+    ) -> ScriptEngine {
         ScriptEngine {
-            script: &script_obj.script,
-            pos: ScriptPos {
-                section: "start".to_owned(),
-                i: 0,
-            },
+            script,
+            pos: ScriptPos::new(section),
             cid,
             talking: false,
+            shown_choices: Vec::new(),
+            debug_hook: None,
         }
     }
 
+    pub fn set_debug_hook(&mut self, hook: DebugHook) {
+        self.debug_hook = Some(hook);
+    }
+
     pub fn exec(&mut self, gd: &mut GameData) -> ExecResult {
         let result = loop {
             let instruction = if let Some(instruction) = self.script.get(&self.pos) {
                 instruction
+            } else if self.pos.ret() {
+                // Reached the end of a called section
+                continue;
             } else {
                 break ExecResult::Quit;
             };
 
+            if let Some(hook) = self.debug_hook.as_mut() {
+                hook(&self.pos, instruction, gd);
+            }
+
             match instruction {
                 Instruction::Jump(section) => {
                     jump!(self, section);
@@ -114,7 +151,8 @@ impl ScriptEngine {
                     }
                 }
                 Instruction::Talk(text_id, choices) => {
-                    let cid = ur!(self.cid, "cid is needed");
+                    // Scripts launched by events talk with the player's image
+                    let cid = self.cid.unwrap_or(CharaId::Player);
                     let need_open_talk_dialog = if self.talking {
                         false
                     } else {
@@ -122,21 +160,54 @@ impl ScriptEngine {
                         true
                     };
 
-                    let choices = if choices.is_empty() {
+                    self.shown_choices = shown_choices(choices, gd);
+                    let choices = if self.shown_choices.is_empty() {
                         None
                     } else {
-                        Some(choices.as_ref())
+                        Some(
+                            self.shown_choices
+                                .iter()
+                                .map(|&i| text::talk_txt_with_vars(&choices[i].text_id, gd))
+                                .collect(),
+                        )
+                    };
+                    let talk_text = TalkText {
+                        text_id,
+                        text: text::talk_txt_with_vars(text_id, gd),
+                        choices,
                     };
-                    return ExecResult::Talk(
-                        cid,
-                        TalkText { text_id, choices },
-                        need_open_talk_dialog,
-                    );
+                    return ExecResult::Talk(cid, talk_text, need_open_talk_dialog);
+                }
+                Instruction::Call(section) => {
+                    if self.pos.call_stack.len() >= MAX_CALL_DEPTH {
+                        warn!(
+                            "script error: call depth exceeds {} when calling \"{}\"",
+                            MAX_CALL_DEPTH, section
+                        );
+                        return ExecResult::Quit;
+                    }
+                    self.pos.call(section);
+                    continue;
+                }
+                Instruction::Return => {
+                    if self.pos.ret() {
+                        continue;
+                    }
+                    break ExecResult::Quit;
                 }
                 Instruction::GSet(name, v) => {
                     let v = v.eval(gd);
                     gd.vars.set_global_var(name, v);
                 }
+                Instruction::LSet(name, v) => {
+                    let v = v.eval(gd);
+                    gd.vars.set_local_var(name, v);
+                }
+                Instruction::CSet(name, v) => {
+                    let cid = ur!(self.cid, "cid is needed");
+                    let v = v.eval(gd);
+                    gd.vars.set_chara_var(cid, name, v);
+                }
                 Instruction::ReceiveMoney(v) => {
                     let v = v.eval(gd);
                     gd.player.add_money(as_int!(v) as i64);
@@ -145,6 +216,68 @@ impl ScriptEngine {
                     let il = ur!(gd.player_item_location(item_id), "cannot find item");
                     gd.remove_item(il, 1);
                 }
+                Instruction::GiveItem(id, n) => {
+                    let item_gen = ItemGen { id: id.clone() };
+                    let item = ur!(super::item::gen::from_item_gen(&item_gen), "unknown item");
+                    game_log_i!("receive-item"; chara=gd.chara.get(CharaId::Player), item=&item, n=n);
+                    let ill = ItemListLocation::Chara {
+                        cid: CharaId::Player,
+                    };
+                    gd.get_item_list_mut(ill).append(item, *n);
+                }
+                Instruction::SpawnChara(id, pos, rel) => {
+                    let idx: CharaTemplateIdx =
+                        ur!(gobj::id_to_idx_checked(id), "unknown chara template");
+                    let mid = gd.get_current_mapid();
+                    {
+                        let map = gd.get_current_map();
+                        if !map.is_inside(*pos) || map.tile[*pos].chara.is_some() {
+                            warn!("script error: cannot spawn a character at {:?}", pos);
+                            return ExecResult::Quit;
+                        }
+                    }
+                    let ct = gobj::get_obj(idx);
+                    let mut chara = super::chara::gen::create_chara(idx, ct.gen_level);
+                    chara.rel = *rel;
+                    let cid = gd.add_chara_to_map(chara, mid);
+                    gd.get_current_map_mut().locate_chara(cid, *pos);
+                }
+                Instruction::SetRelationship(rel) => {
+                    let cid = ur!(self.cid, "cid is needed");
+                    gd.chara.get_mut(cid).rel = *rel;
+                }
+                Instruction::Teleport(dest, pos) => {
+                    let current_mid = gd.get_current_mapid();
+                    let mid = match dest {
+                        MapDest::Floor(floor) => {
+                            if current_mid.is_region_map() {
+                                warn!("script error: teleport to a floor on region map");
+                                return ExecResult::Quit;
+                            }
+                            current_mid.set_floor(*floor)
+                        }
+                        MapDest::Town(town_id, floor) => {
+                            let sid = ur!(gd.region.search_town(town_id), "unknown town");
+                            MapId::SiteMap { sid, floor: *floor }
+                        }
+                        MapDest::Region => MapId::from(current_mid.rid()),
+                    };
+                    if !gd.region.map_exist(mid) {
+                        warn!("script error: teleport to nonexistent map {:?}", mid);
+                        return ExecResult::Quit;
+                    }
+                    break ExecResult::Teleport(mid, *pos);
+                }
+                Instruction::StartQuest(quest, money, exp) => {
+                    let result = super::quest::start_script_quest(gd, quest, *money, *exp);
+                    gd.vars.set_last_result(Value::Bool(result));
+                }
+                Instruction::PlaySound(name) => {
+                    crate::audio::play_sound(name);
+                }
+                Instruction::PlayMusic(name) => {
+                    crate::audio::play_music(name);
+                }
                 Instruction::Special(SpecialInstruction::ShopBuy) => {
                     break ExecResult::ShopBuy(ur!(self.cid, "cid is needed"));
                 }
@@ -160,8 +293,11 @@ impl ScriptEngine {
                     break ExecResult::Quest;
                 }
                 Instruction::Special(SpecialInstruction::ReceiveQuestRewards) => {
-                    let result = super::quest::receive_rewards(gd);
-                    gd.vars.set_last_result(Value::Bool(result))
+                    let exp = super::quest::receive_rewards(gd);
+                    gd.vars.set_last_result(Value::Bool(exp.is_some()));
+                    if let Some(exp) = exp.filter(|exp| *exp > 0) {
+                        break ExecResult::GainExp(exp);
+                    }
                 }
             }
             self.pos.advance();
@@ -175,7 +311,7 @@ impl ScriptEngine {
         match self.script.get(&self.pos).expect("instruction not found") {
             Instruction::Talk(_, choices) => {
                 if let Some(c) = choice {
-                    let next_section = &choices[c as usize].1;
+                    let next_section = &choices[self.shown_choices[c as usize]].section;
                     if next_section == QUIT_SECTION {
                         return ExecResult::Quit;
                     }
@@ -185,7 +321,7 @@ impl ScriptEngine {
                         self.pos.set_section(next_section);
                     }
                 } else {
-                    assert!(choices.is_empty());
+                    assert!(self.shown_choices.is_empty());
                     self.pos.advance();
                 }
                 self.exec(gd)
@@ -194,3 +330,67 @@ impl ScriptEngine {
         }
     }
 }
+
+/// Returns the indices of the choices whose conditions are true.
+/// If no choice is shown, the talk is displayed without choices
+/// and the script continues to the next instruction after it.
+fn shown_choices(choices: &[TalkChoice], gd: &GameData) -> Vec<usize> {
+    choices
+        .iter()
+        .enumerate()
+        .filter(|(_, choice)| match choice.cond.as_ref() {
+            Some(cond) => cond.eval(gd) == Value::Bool(true),
+            None => true,
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+#[cfg(test)]
+fn test_choice(cond: Option<Expr>) -> TalkChoice {
+    TalkChoice {
+        text_id: "choice".to_owned(),
+        section: "next".to_owned(),
+        cond,
+    }
+}
+
+#[test]
+fn shown_choices_filtered_by_cond() {
+    let mut gd = GameData::empty();
+    gd.vars.set_global_var("a", Value::Bool(true));
+    gd.vars.set_global_var("b", Value::Bool(false));
+
+    let choices = vec![
+        test_choice(None),
+        test_choice(Some(Expr::GVar("a".to_owned()))),
+        test_choice(Some(Expr::GVar("b".to_owned()))),
+        // Unknown variables do not evaluate to true
+        test_choice(Some(Expr::GVar("unknown".to_owned()))),
+    ];
+    assert_eq!(shown_choices(&choices, &gd), vec![0, 1]);
+}
+
+#[test]
+fn shown_choices_all_hidden() {
+    let gd = GameData::empty();
+    let choices = vec![
+        test_choice(Some(Expr::Value(Value::Bool(false)))),
+        test_choice(Some(Expr::Value(Value::Int(1)))),
+    ];
+    assert!(shown_choices(&choices, &gd).is_empty());
+}
+
+#[test]
+fn call_depth_limit() {
+    let mut map = common::hashmap::HashMap::default();
+    map.insert(
+        "start".to_owned(),
+        vec![Instruction::Call("start".to_owned())],
+    );
+    let script: &'static Script = Box::leak(Box::new(Script::from_map(map)));
+    let mut gd = GameData::empty();
+    let mut engine = ScriptEngine::from_script(script, "start", None);
+    assert_eq!(engine.exec(&mut gd), ExecResult::Quit);
+    assert_eq!(engine.pos.call_stack.len(), MAX_CALL_DEPTH);
+}
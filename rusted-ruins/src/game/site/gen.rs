@@ -17,8 +17,20 @@ pub fn add_unique_citizens(gd: &mut GameData, sid: SiteId, sg: &SiteGenObject) {
             // Talk script setting
             chara.trigger_talk = Some(talk_script_id.to_owned());
         }
+        if let Some(trigger_death) = uc.trigger_death.as_ref() {
+            chara.trigger_death = Some(trigger_death.clone());
+        }
 
         let cid = gd.add_chara_to_site(chara, sid, uc.n);
         gd.region.get_map_mut(mid).locate_chara(cid, uc.pos);
     }
 }
+
+/// Schedule scripts from SiteGenObject
+pub fn schedule_scripts(gd: &mut GameData, sg: &SiteGenObject) {
+    for s in &sg.scheduled_scripts {
+        let mut time = gd.time.current_time();
+        time.advance(s.hours as u64 * SECS_PER_HOUR);
+        gd.schedule.add(time, s.script.clone());
+    }
+}
@@ -1,8 +1,31 @@
 pub mod gen;
 
 use common::gamedata::*;
+use std::path::Path;
 
 /// Additional Site method
 pub trait SiteEx {}
 
 impl SiteEx for Site {}
+
+/// Remove the site from its region with its symbol on the region map.
+/// Its map files are deleted at the next save.
+pub fn remove_site<P: AsRef<Path>>(gd: &mut GameData, sid: SiteId, map_dir: P) {
+    if gd.region.get_site_checked(sid).is_none() {
+        return;
+    }
+    trace!("Remove site {:?}", sid);
+    let region_mid = MapId::from(sid.rid);
+    gd.region
+        .preload_map(region_mid, map_dir.as_ref())
+        .expect("failed to load map");
+    let pos = gd.region.get_site_pos(sid);
+    let site = gd.region.get_mut(sid.rid).remove_site(sid).unwrap();
+
+    let tile = &mut gd.region.get_map_mut(region_mid).tile[pos];
+    if let SpecialTileKind::SiteSymbol { .. } = tile.special {
+        tile.special = SpecialTileKind::None;
+    }
+
+    site.visit_maps(|_, map| gd.remove_map_file(map.id()));
+}
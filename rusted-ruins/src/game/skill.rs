@@ -3,6 +3,12 @@ use common::gamedata::*;
 use fnv::FnvHashMap;
 use rules::RULES;
 
+/// Probability of success when a skill is used against the difficulty level
+pub fn calc_success_probability(skill_level: u32, difficulty: u32) -> f64 {
+    let d = skill_level as f64 - difficulty as f64;
+    1.0 / (1.0 + (-d * 0.25).exp())
+}
+
 pub trait SkillListEx {
     fn add_exp(&mut self, kind: SkillKind, add_exp: u32, base_level: u32) -> (bool, u32);
     fn learn_new_skill(&mut self, kind: SkillKind);
@@ -0,0 +1,135 @@
+//! Functions for trap detection, disarming and triggering
+
+use super::chara::CharaEx;
+use super::combat::DamageKind;
+use super::extrait::*;
+use super::skill::calc_success_probability;
+use super::{Game, InfoGetter};
+use crate::rng;
+use array2d::*;
+use common::gamedata::*;
+use rng::Rng;
+use rules::RULES;
+
+/// Trap level is calculated from the floor number of the current map
+fn trap_level(gd: &GameData) -> u32 {
+    let mid = gd.get_current_mapid();
+    if mid.is_region_map() {
+        RULES.trap.base_level
+    } else {
+        mid.floor() + RULES.trap.base_level
+    }
+}
+
+/// The player searches hidden traps around. This is called at the start of player's turn.
+pub fn search_traps(game: &mut Game) {
+    if game.gd.get_current_mapid().is_region_map() {
+        return;
+    }
+
+    let trap_level = trap_level(&game.gd);
+    let player_pos = game.gd.player_pos();
+    let skill_level = game
+        .gd
+        .chara
+        .get(CharaId::Player)
+        .skills
+        .get(SkillKind::Searching);
+    let p = calc_success_probability(skill_level, trap_level) * RULES.trap.detect_factor;
+    let p = if p > 1.0 { 1.0 } else { p };
+
+    let mut detected_traps = Vec::new();
+    {
+        let view_map = &game.view_map;
+        let map = game.gd.get_current_map_mut();
+
+        for (_, pos) in MDistRangeIter::new(player_pos, RULES.trap.search_range) {
+            if !view_map.get_tile_visible(pos) {
+                continue;
+            }
+            if let Some((kind, false)) = map.get_trap(pos) {
+                if rng::get_rng().gen_bool(p) {
+                    map.tile[pos].special = SpecialTileKind::Trap {
+                        kind,
+                        detected: true,
+                    };
+                    detected_traps.push(kind);
+                }
+            }
+        }
+    }
+
+    for kind in detected_traps {
+        let player = game.gd.chara.get_mut(CharaId::Player);
+        game_log_i!("trap-detected"; chara=player, trap=kind);
+        player.add_detect_trap_exp(trap_level);
+    }
+}
+
+/// Try to disarm a detected trap at given position
+pub fn try_disarm(game: &mut Game, cid: CharaId, pos: Vec2d, kind: TrapKind) {
+    let trap_level = trap_level(&game.gd);
+    let skill_level = game.gd.chara.get(cid).skills.get(SkillKind::Searching);
+    let p = calc_success_probability(skill_level, trap_level);
+
+    if rng::get_rng().gen_bool(p) {
+        game.gd.get_current_map_mut().tile[pos].special = SpecialTileKind::None;
+        let chara = game.gd.chara.get_mut(cid);
+        game_log!("trap-disarm-success"; chara=chara, trap=kind);
+        chara.add_disarm_trap_exp(trap_level);
+    } else {
+        {
+            let chara = game.gd.chara.get(cid);
+            game_log!("trap-disarm-failure"; chara=chara, trap=kind);
+        }
+        if rng::get_rng().gen_bool(RULES.trap.disarm_failure_trigger) {
+            trigger_trap(game, cid, kind);
+        }
+    }
+}
+
+/// Apply the effect of a trap to given character
+pub fn trigger_trap(game: &mut Game, cid: CharaId, kind: TrapKind) {
+    let trap_level = trap_level(&game.gd);
+    {
+        let chara = game.gd.chara.get(cid);
+        game_log!("trap-triggered"; chara=chara, trap=kind);
+    }
+
+    match kind {
+        TrapKind::Damage => {
+            let damage = rng::dice(RULES.trap.damage_dice[0], RULES.trap.damage_dice[1])
+                + trap_level as i32 * RULES.trap.damage_per_level;
+            {
+                let chara = game.gd.chara.get(cid);
+                game_log!("damaged-chara"; chara=chara, damage=damage);
+            }
+            super::chara::damage(game, cid, damage, DamageKind::Trap);
+        }
+        TrapKind::Poison => {
+            let chara = game.gd.chara.get_mut(cid);
+            chara.add_status(CharaStatus::Poisoned);
+            game_log!("poisoned"; chara=chara);
+        }
+        TrapKind::SleepGas => {
+            let chara = game.gd.chara.get_mut(cid);
+            chara.add_status(CharaStatus::Asleep {
+                turn_left: RULES.trap.sleep_turn,
+            });
+            game_log!("fall-asleep"; chara=chara);
+        }
+        TrapKind::Teleport => {
+            let map = game.gd.get_current_map_mut();
+            if let Some(p) = super::map::choose_empty_tile(map) {
+                map.locate_chara(cid, p);
+                let chara = game.gd.chara.get(cid);
+                game_log!("trap-teleported"; chara=chara);
+            }
+        }
+        TrapKind::Alarm => {
+            game_log!("trap-alarm");
+            let mid = game.gd.get_current_mapid();
+            super::map::gen_npcs(&mut game.gd, mid, RULES.trap.alarm_n_npc, mid.floor());
+        }
+    }
+}
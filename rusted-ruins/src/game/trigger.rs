@@ -0,0 +1,50 @@
+//! Event triggers which launch scripts outside of talking
+
+use super::Game;
+use array2d::Vec2d;
+use common::gamedata::*;
+use common::gobj;
+
+/// Launch the script for entering the current map
+pub fn enter_map(game: &mut Game) {
+    if let Some(t) = game.gd.get_current_map().triggers.enter.clone() {
+        game.trigger_script(t, None);
+    }
+}
+
+/// Launch the script for the tile which the player stepped onto
+pub fn step_on_tile(game: &mut Game, pos: Vec2d) {
+    if let Some(t) = game.gd.get_current_map().triggers.tile(pos).cloned() {
+        game.trigger_script(t, None);
+    }
+}
+
+/// Launch the script for picking up the item
+pub fn pick_up_item(game: &mut Game, item: &Item) {
+    let item_id = gobj::idx_to_id(item.idx);
+    if let Some(t) = game
+        .gd
+        .get_current_map()
+        .triggers
+        .item_pickup(item_id)
+        .cloned()
+    {
+        game.trigger_script(t, None);
+    }
+}
+
+/// Launch the script for the character's death.
+/// The character will be removed, so the script is not related to the character.
+pub fn chara_death(game: &mut Game, cid: CharaId) {
+    if let Some(t) = game.gd.chara.get(cid).trigger_death.clone() {
+        game.trigger_script(t, None);
+    }
+}
+
+/// Launch scheduled scripts whose time has come
+pub fn check_schedule(game: &mut Game) {
+    let now = game.gd.time.current_time();
+    while let Some(t) = game.gd.schedule.pop_due(now) {
+        game.trigger_script(t, None);
+    }
+}
@@ -14,6 +14,7 @@ pub fn turn_loop(game: &mut Game) {
         let (cid, advanced_clock) = decrease_wait_time(game);
 
         advance_game_time(game, advanced_clock);
+        super::trigger::check_schedule(game);
         if !game.anim_queue.is_empty() {
             return;
         }
@@ -22,6 +23,7 @@ pub fn turn_loop(game: &mut Game) {
             if cid == CharaId::Player {
                 game.state = GameState::PlayerTurn;
                 game.update_before_player_turn();
+                super::trap::search_traps(game);
                 return;
             } else {
                 process_npc_turn(game, cid);
@@ -59,6 +61,7 @@ fn decrease_wait_time(game: &mut Game) -> (CharaId, u32) {
 
 /// Dying chara is removed before new turn processing
 fn remove_dying_charas(game: &mut Game) {
+    let mut removed = false;
     for &cid in &game.gd.get_charas_on_map() {
         let chara = game.gd.chara.get(cid);
         if chara.hp <= 0 {
@@ -66,14 +69,19 @@ fn remove_dying_charas(game: &mut Game) {
                 game.request_dialog_open(DialogOpenRequest::GameOver);
                 return;
             }
+            super::trigger::chara_death(game, cid);
             // Remove dying chara
             game.gd.remove_chara(cid);
             // If the current target is cid, remove it
             if game.target_chara == Some(cid) {
                 game.target_chara = None;
             }
+            removed = true;
         }
     }
+    if removed {
+        super::dungeon_gen::check_cleared(&mut game.gd);
+    }
 }
 
 fn advance_game_time(game: &mut Game, advanced_clock: u32) {
@@ -0,0 +1,177 @@
+//! Reload paks, rules and text files while the game runs. Used in developer mode.
+
+use crate::config;
+use crate::game::Game;
+use common::gobj;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+
+/// Interval to check modification times of the watched files
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DataKind {
+    Pak,
+    Rules,
+    Text,
+}
+
+impl DataKind {
+    fn dir_name(self) -> &'static str {
+        match self {
+            DataKind::Pak => "paks",
+            DataKind::Rules => "rules",
+            DataKind::Text => "text",
+        }
+    }
+
+    fn can_reload(self) -> bool {
+        match self {
+            DataKind::Pak => gobj::can_reload(),
+            DataKind::Rules => rules::RULES.can_reload(),
+            DataKind::Text => crate::text::can_reload(),
+        }
+    }
+}
+
+pub struct HotReloader {
+    last_check: Instant,
+    watched: Vec<WatchedFiles>,
+}
+
+struct WatchedFiles {
+    kind: DataKind,
+    dirs: Vec<PathBuf>,
+    mtimes: HashMap<PathBuf, SystemTime>,
+}
+
+impl WatchedFiles {
+    fn new(kind: DataKind) -> WatchedFiles {
+        let dirs = config::get_data_dirs()
+            .into_iter()
+            .map(|dir| dir.join(kind.dir_name()))
+            .collect();
+        let mut watched = WatchedFiles {
+            kind,
+            dirs,
+            mtimes: HashMap::new(),
+        };
+        watched.mtimes = watched.scan();
+        watched
+    }
+
+    fn scan(&self) -> HashMap<PathBuf, SystemTime> {
+        let mut mtimes = HashMap::new();
+        for dir in &self.dirs {
+            for entry in WalkDir::new(dir).into_iter().filter_map(|e| e.ok()) {
+                if !entry.file_type().is_file() {
+                    continue;
+                }
+                if let Some(mtime) = entry.metadata().ok().and_then(|m| m.modified().ok()) {
+                    mtimes.insert(entry.into_path(), mtime);
+                }
+            }
+        }
+        mtimes
+    }
+
+    /// Returns changed file paths
+    fn update(&mut self) -> Vec<PathBuf> {
+        let mtimes = self.scan();
+        let mut changed: Vec<PathBuf> = mtimes
+            .iter()
+            .filter(|(path, mtime)| self.mtimes.get(*path) != Some(mtime))
+            .map(|(path, _)| path.clone())
+            .chain(
+                self.mtimes
+                    .keys()
+                    .filter(|path| !mtimes.contains_key(*path))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        self.mtimes = mtimes;
+        changed
+    }
+}
+
+impl HotReloader {
+    pub fn new() -> HotReloader {
+        info!("Developer mode: watching paks, rules and text files");
+        HotReloader {
+            last_check: Instant::now(),
+            watched: vec![
+                WatchedFiles::new(DataKind::Pak),
+                WatchedFiles::new(DataKind::Rules),
+                WatchedFiles::new(DataKind::Text),
+            ],
+        }
+    }
+
+    /// Returns the kinds of data whose files are changed since the last check
+    pub fn check(&mut self) -> Vec<DataKind> {
+        if self.last_check.elapsed() < CHECK_INTERVAL {
+            return Vec::new();
+        }
+        self.last_check = Instant::now();
+
+        let mut kinds = Vec::new();
+        for watched in self.watched.iter_mut() {
+            let changed = watched.update();
+            for path in &changed {
+                info!("\"{}\" is changed", path.to_string_lossy());
+            }
+            if !changed.is_empty() {
+                kinds.push(watched.kind);
+            }
+        }
+        kinds
+    }
+}
+
+/// Reload the given kinds of data. Indices in the game data are converted for reloaded objects.
+pub fn reload(game: &mut Game, kinds: &[DataKind]) {
+    for kind in kinds {
+        if !kind.can_reload() {
+            warn!(
+                "Reached the reload limit of {}, restart to apply changes",
+                kind.dir_name()
+            );
+            continue;
+        }
+        match kind {
+            DataKind::Pak => {
+                let (objects, idx_conv_table) = match gobj::reload() {
+                    Some(reloaded) => reloaded,
+                    None => continue,
+                };
+                if let Some(idx_conv_table) = idx_conv_table {
+                    let map_dir = crate::game::saveload::get_map_dir(&game.gd);
+                    if let Err(e) = game.gd.convert_idx(idx_conv_table, map_dir) {
+                        error!(
+                            "Failed to convert indices, so the reloaded objects are not used: {}",
+                            e
+                        );
+                        continue;
+                    }
+                }
+                gobj::install(objects);
+                game.frequent_tex = crate::game::frequent_tex::FrequentTextures::new();
+                game.update_before_player_turn();
+            }
+            DataKind::Rules => {
+                if let Err(e) = rules::reload() {
+                    warn!("Failed to reload rules: {}", e);
+                    continue;
+                }
+            }
+            DataKind::Text => {
+                crate::text::init();
+            }
+        }
+        let data = kind.dir_name();
+        game_log_i!("data-reloaded"; data = data);
+    }
+}
@@ -1,6 +1,7 @@
 extern crate rusted_ruins_array2d as array2d;
 extern crate rusted_ruins_audio as audio;
 extern crate rusted_ruins_common as common;
+extern crate rusted_ruins_makepak as makepak;
 extern crate rusted_ruins_map_generator as map_generator;
 extern crate rusted_ruins_rng as rng;
 extern crate rusted_ruins_rules as rules;
@@ -23,17 +24,26 @@ mod context;
 mod draw;
 mod eventhandler;
 mod game;
+mod hot_reload;
 mod screen;
+mod script_runner;
 mod sdltypeconv;
 mod text;
 mod window;
 
 fn main() {
+    let matches = create_matches();
+
     setup_logger();
     init_lazy_statics();
     init_obj();
     init_rules();
 
+    if let Some(matches) = matches.subcommand_matches("run-script") {
+        let exit_code = run_script(matches);
+        std::process::exit(exit_code);
+    }
+
     let sdl_context = SdlContext::init();
     let mut screen = screen::Screen::new(&sdl_context.sdl_context);
 
@@ -58,6 +68,107 @@ impl SdlContext {
     }
 }
 
+fn run_script(matches: &clap::ArgMatches) -> i32 {
+    let values = |name| -> Vec<&str> {
+        matches
+            .values_of(name)
+            .map(|v| v.collect())
+            .unwrap_or_default()
+    };
+
+    let choices: Result<Vec<u32>, _> = values("choices").iter().map(|c| c.parse()).collect();
+    let breakpoints: Result<Vec<_>, _> = values("break").iter().map(|b| b.parse()).collect();
+    let expects: Option<Vec<_>> = values("expect")
+        .iter()
+        .map(|e| script_runner::parse_expect(e))
+        .collect();
+    let (choices, breakpoints, expects) = match (choices, breakpoints, expects) {
+        (Ok(c), Ok(b), Some(e)) => (c, b, e),
+        _ => {
+            eprintln!("invalid arguments\n{}", matches.usage());
+            return 1;
+        }
+    };
+
+    script_runner::run(script_runner::RunnerOptions {
+        file: matches.value_of("FILE").unwrap().to_owned(),
+        section: matches.value_of("section").unwrap_or("start").to_owned(),
+        save: matches.value_of("save").map(|s| s.to_owned()),
+        chara: matches.value_of("chara").map(|s| s.to_owned()),
+        choices,
+        breakpoints,
+        expects,
+    })
+}
+
+fn create_matches() -> clap::ArgMatches<'static> {
+    use clap::{App, Arg, SubCommand};
+
+    App::new("rusted-ruins")
+        .subcommand(
+            SubCommand::with_name("run-script")
+                .about("Run a script without the game window")
+                .arg(
+                    Arg::with_name("section")
+                        .short("s")
+                        .long("section")
+                        .value_name("SECTION")
+                        .help("Section to start from")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("save")
+                        .long("save")
+                        .value_name("DIR")
+                        .help("Run with a copy of the given save data instead of a new game")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("chara")
+                        .long("chara")
+                        .value_name("ID")
+                        .help("Run for the character given by \"player\" or its template id on the current map")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("choices")
+                        .short("c")
+                        .long("choices")
+                        .value_name("N,N,..")
+                        .help("Choices given to talk in order")
+                        .takes_value(true)
+                        .use_delimiter(true),
+                )
+                .arg(
+                    Arg::with_name("break")
+                        .short("b")
+                        .long("break")
+                        .value_name("SECTION[:N]")
+                        .help("Set breakpoint")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("expect")
+                        .short("e")
+                        .long("expect")
+                        .value_name("VAR=VALUE")
+                        .help("Check the variable after execution. Use $l(VAR) or $c(VAR) for local or character variables")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1),
+                )
+                .arg(
+                    Arg::with_name("FILE")
+                        .help("Input rrscript file")
+                        .index(1)
+                        .required(true),
+                ),
+        )
+        .get_matches()
+}
+
 /// Initialize lazy_static values
 fn init_lazy_statics() {
     config::init();
@@ -75,7 +186,7 @@ fn init_obj() {
 }
 
 fn init_rules() {
-    rules::init(&*crate::config::APP_DIR);
+    rules::init(&crate::config::get_data_dirs());
 }
 
 /// Setup logger. It is not game logger. It is for debug and warning infomation.
@@ -0,0 +1,334 @@
+//! Standalone script runner for testing and debugging scripts without the game window.
+
+use crate::game::newgame::NewGameBuilder;
+use crate::game::{AdvanceScriptResult, DialogOpenRequest, Game, ScriptEngine, TalkText};
+use common::gamedata::*;
+use common::gobj;
+use common::obj::Object;
+use common::script::{Instruction, ScriptPos, Value};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+pub struct RunnerOptions {
+    pub file: String,
+    pub section: String,
+    pub save: Option<String>,
+    /// Character the script is executed for, referred by CSet and $c(..)
+    pub chara: Option<String>,
+    pub choices: Vec<u32>,
+    pub breakpoints: Vec<Breakpoint>,
+    pub expects: Vec<(String, Value)>,
+}
+
+/// Breakpoint at the given section. If index is not specified, break at every instruction.
+pub struct Breakpoint {
+    section: String,
+    i: Option<usize>,
+}
+
+impl std::str::FromStr for Breakpoint {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Breakpoint, ()> {
+        let mut it = s.splitn(2, ':');
+        let section = it.next().ok_or(())?.to_owned();
+        let i = if let Some(i) = it.next() {
+            Some(i.parse().map_err(|_| ())?)
+        } else {
+            None
+        };
+        Ok(Breakpoint { section, i })
+    }
+}
+
+impl Breakpoint {
+    fn matches(&self, pos: &ScriptPos) -> bool {
+        self.section == pos.section && self.i.map(|i| i == pos.i).unwrap_or(true)
+    }
+}
+
+/// Parse "name=value" given to check variables after execution
+pub fn parse_expect(s: &str) -> Option<(String, Value)> {
+    let mut it = s.splitn(2, '=');
+    let name = it.next()?.trim().to_owned();
+    let value = it.next()?.trim();
+    Some((name, parse_value(value)))
+}
+
+fn parse_value(s: &str) -> Value {
+    match s {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => {
+            if let Ok(n) = s.parse() {
+                Value::Int(n)
+            } else {
+                Value::String(s.trim_matches('"').to_owned())
+            }
+        }
+    }
+}
+
+/// Runs the script and returns the process exit code
+pub fn run(opts: RunnerOptions) -> i32 {
+    let script = match makepak::rrscript::read_rrscript(&opts.file) {
+        Ok(Object::Script(o)) => o.script,
+        Ok(_) => unreachable!(),
+        Err(e) => {
+            eprintln!("{}: {}", opts.file, e);
+            return 1;
+        }
+    };
+    // Script must live during the execution as objects loaded from pak files
+    let script = Box::leak(Box::new(script));
+
+    // Maps created or removed during the execution must not change the player's save directory,
+    // so the save is copied to the temporary directory
+    let save_dir = std::env::temp_dir().join("rusted-ruins-script-runner");
+    if save_dir.exists() {
+        if let Err(e) = std::fs::remove_dir_all(&save_dir) {
+            eprintln!("failed to clean \"{}\": {}", save_dir.to_string_lossy(), e);
+            return 1;
+        }
+    }
+    let gd = if let Some(save) = opts.save.as_ref() {
+        if let Err(e) = copy_dir(save, &save_dir) {
+            eprintln!("failed to copy \"{}\": {}", save, e);
+            return 1;
+        }
+        match GameData::load(&save_dir) {
+            Ok(gd) => gd,
+            Err(e) => {
+                eprintln!("failed to load \"{}\": {}", save, e);
+                return 1;
+            }
+        }
+    } else {
+        let mut builder = NewGameBuilder::new();
+        builder.set_player_name("script-runner");
+        builder.set_chara_class(CharaClass::Adventurer);
+        builder.build()
+    };
+    let mut game = Game::new(gd);
+    game.set_save_dir(save_dir);
+
+    let cid = if let Some(chara) = opts.chara.as_ref() {
+        match find_chara(&game.gd, chara) {
+            Some(cid) => Some(cid),
+            None => {
+                eprintln!("character \"{}\" is not found on the current map", chara);
+                return 1;
+            }
+        }
+    } else {
+        None
+    };
+
+    let debugger = Rc::new(RefCell::new(Debugger {
+        breakpoints: opts.breakpoints,
+        step: false,
+    }));
+    let mut engine = ScriptEngine::from_script(script, &opts.section, cid);
+    let d = debugger.clone();
+    engine.set_debug_hook(Box::new(move |pos, instruction, gd| {
+        d.borrow_mut().on_instruction(pos, instruction, gd)
+    }));
+
+    let mut choices: VecDeque<u32> = opts.choices.into();
+    let mut result = game.start_script_engine(engine, cid);
+    loop {
+        print_dialog_requests(&mut game);
+        result = match result {
+            AdvanceScriptResult::UpdateTalkText(talk_text) => {
+                print_talk_text(&talk_text);
+                let choice = if let Some(c) = talk_text.choices.as_ref() {
+                    match next_choice(&mut choices, c.len()) {
+                        Some(choice) => Some(choice),
+                        None => {
+                            eprintln!("no choice given");
+                            return 1;
+                        }
+                    }
+                } else {
+                    None
+                };
+                game.advance_script(Some(choice))
+            }
+            AdvanceScriptResult::Continue => game.advance_script(None),
+            AdvanceScriptResult::Quit => break,
+        };
+    }
+    crate::log::new_line();
+    crate::log::with_lines(0, |line| println!("log: {}", line.concat()));
+
+    check_expects(&game.gd, &opts.expects)
+}
+
+/// Copy the directory recursively
+fn copy_dir<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dest: Q) -> io::Result<()> {
+    std::fs::create_dir_all(dest.as_ref())?;
+    for entry in std::fs::read_dir(src)? {
+        let entry = entry?;
+        let dest = dest.as_ref().join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(entry.path(), dest)?;
+        } else {
+            std::fs::copy(entry.path(), dest)?;
+        }
+    }
+    Ok(())
+}
+
+/// Find the character by "player" or its template id on the current map
+fn find_chara(gd: &GameData, id: &str) -> Option<CharaId> {
+    if id == "player" {
+        return Some(CharaId::Player);
+    }
+    gd.get_charas_on_map()
+        .into_iter()
+        .find(|cid| gobj::idx_to_id(gd.chara.get(*cid).template) == id)
+}
+
+struct Debugger {
+    breakpoints: Vec<Breakpoint>,
+    step: bool,
+}
+
+impl Debugger {
+    fn on_instruction(&mut self, pos: &ScriptPos, instruction: &Instruction, gd: &GameData) {
+        if !self.step && !self.breakpoints.iter().any(|b| b.matches(pos)) {
+            return;
+        }
+        self.step = false;
+        println!("break at {}:{} {:?}", pos.section, pos.i, instruction);
+
+        loop {
+            let line = if let Some(line) = prompt("debug") {
+                line
+            } else {
+                return;
+            };
+            let mut args = line.split_whitespace();
+            match args.next() {
+                Some("c") | Some("continue") | None => return,
+                Some("s") | Some("step") => {
+                    self.step = true;
+                    return;
+                }
+                Some("v") | Some("vars") => print_vars(gd),
+                Some("p") | Some("print") => {
+                    if let Some(name) = args.next() {
+                        println!("{} = {:?}", name, get_var(gd, name));
+                    }
+                }
+                Some("bt") => {
+                    for (section, i) in pos.call_stack.iter().rev() {
+                        println!("  called from {}:{}", section, i);
+                    }
+                }
+                Some("q") | Some("quit") => std::process::exit(0),
+                Some(_) => {
+                    println!("commands: c(ontinue), s(tep), v(ars), p(rint) NAME, bt, q(uit)")
+                }
+            }
+        }
+    }
+}
+
+fn print_talk_text(talk_text: &TalkText) {
+    println!("talk [{}]: {}", talk_text.text_id, talk_text.text);
+    if let Some(choices) = talk_text.choices.as_ref() {
+        for (i, choice) in choices.iter().enumerate() {
+            println!("  {}) {}", i, choice);
+        }
+    }
+}
+
+fn print_dialog_requests(game: &mut Game) {
+    match game.pop_dialog_open_request() {
+        Some(DialogOpenRequest::ShopBuy { cid }) => println!("open shop buy ({:?})", cid),
+        Some(DialogOpenRequest::ShopSell) => println!("open shop sell"),
+        Some(DialogOpenRequest::Quest) => println!("open quest"),
+        _ => (),
+    }
+}
+
+/// Get next choice from given list or stdin
+fn next_choice(choices: &mut VecDeque<u32>, n: usize) -> Option<u32> {
+    if let Some(c) = choices.pop_front() {
+        if (c as usize) < n {
+            println!("> {}", c);
+            return Some(c);
+        }
+        eprintln!("choice {} is out of range", c);
+        return None;
+    }
+    loop {
+        let line = prompt("choice")?;
+        match line.trim().parse::<u32>() {
+            Ok(c) if (c as usize) < n => return Some(c),
+            _ => println!("input a number from 0 to {}", n - 1),
+        }
+    }
+}
+
+/// Read one line from stdin. Returns None at EOF.
+fn prompt(s: &str) -> Option<String> {
+    print!("{}> ", s);
+    io::stdout().flush().ok()?;
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line),
+    }
+}
+
+/// Get variable by the name written in the same way as scripts.
+/// "$l(name)" and "$c(name)" refer local and character variables,
+/// "$(name)" and "name" refer global variables.
+fn get_var<'a>(gd: &'a GameData, name: &str) -> Option<&'a Value> {
+    let inner = |prefix: &str| {
+        if name.starts_with(prefix) && name.ends_with(')') {
+            Some(&name[prefix.len()..(name.len() - 1)])
+        } else {
+            None
+        }
+    };
+    if let Some(name) = inner("$l(") {
+        gd.vars.local_var(name)
+    } else if let Some(name) = inner("$c(") {
+        gd.vars
+            .script_cid()
+            .and_then(|cid| gd.vars.chara_var(cid, name))
+    } else {
+        gd.vars.global_var(inner("$(").unwrap_or(name))
+    }
+}
+
+fn print_vars(gd: &GameData) {
+    for (name, value) in gd.vars.iter_global() {
+        println!("  {} = {:?}", name, value);
+    }
+    for (name, value) in gd.vars.iter_local() {
+        println!("  $l({}) = {:?}", name, value);
+    }
+}
+
+fn check_expects(gd: &GameData, expects: &[(String, Value)]) -> i32 {
+    let mut failed = false;
+    for (name, expected) in expects {
+        let value = get_var(gd, name);
+        if value != Some(expected) {
+            eprintln!("failed: {} is {:?}, expected {:?}", name, value, expected);
+            failed = true;
+        }
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
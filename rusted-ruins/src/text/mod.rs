@@ -4,29 +4,35 @@ mod to_text;
 use crate::config;
 use crate::error::*;
 use common::basic;
+use common::gamedata::*;
+use common::reloadable::Reloadable;
+use common::script::Value;
 use std::collections::HashMap;
 use std::fs;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
-/// Initialize lazy static
+/// Load text maps. This is also used to reload text files.
 pub fn init() {
-    ::lazy_static::initialize(&OBJ_TXT_MAP);
-    ::lazy_static::initialize(&LOG_TXT_MAP);
-    ::lazy_static::initialize(&UI_TXT_MAP);
-    ::lazy_static::initialize(&TALK_TXT_MAP);
-    ::lazy_static::initialize(&MISC_TXT_MAP);
+    OBJ_TXT_MAP.set(load_trans_txt(basic::OBJ_TXT_DIR));
+    LOG_TXT_MAP.set(load_trans_txt(basic::LOG_TXT_DIR));
+    UI_TXT_MAP.set(load_trans_txt(basic::UI_TXT_DIR));
+    TALK_TXT_MAP.set(load_trans_txt(basic::TALK_TXT_DIR));
+    MISC_TXT_MAP.set(load_trans_txt(basic::MISC_TXT_DIR));
 }
 
-lazy_static! {
-    static ref OBJ_TXT_MAP: HashMap<String, String> = load_trans_txt(basic::OBJ_TXT_DIR);
-    static ref LOG_TXT_MAP: HashMap<String, String> = load_trans_txt(basic::LOG_TXT_DIR);
-    static ref UI_TXT_MAP: HashMap<String, String> = load_trans_txt(basic::UI_TXT_DIR);
-    static ref TALK_TXT_MAP: HashMap<String, String> = load_trans_txt(basic::TALK_TXT_DIR);
-    static ref MISC_TXT_MAP: HashMap<String, String> = load_trans_txt(basic::MISC_TXT_DIR);
+/// Returns false if text files have been reloaded too many times
+pub fn can_reload() -> bool {
+    OBJ_TXT_MAP.can_reload()
 }
 
+static OBJ_TXT_MAP: Reloadable<HashMap<String, String>> = Reloadable::new();
+static LOG_TXT_MAP: Reloadable<HashMap<String, String>> = Reloadable::new();
+static UI_TXT_MAP: Reloadable<HashMap<String, String>> = Reloadable::new();
+static TALK_TXT_MAP: Reloadable<HashMap<String, String>> = Reloadable::new();
+static MISC_TXT_MAP: Reloadable<HashMap<String, String>> = Reloadable::new();
+
 pub fn obj_txt<'a>(id: &'a str) -> &'a str {
     if let Some(txt) = OBJ_TXT_MAP.get(id) {
         txt
@@ -74,6 +80,45 @@ pub fn talk_txt<'a>(id: &'a str) -> &'a str {
     }
 }
 
+/// Get talk text with variables replaced.
+/// "$(player_name)", "$(money)", and "$(quest_*)" for the latest active quest are available.
+/// Other names are replaced by global variables.
+pub fn talk_txt_with_vars(id: &str, gd: &GameData) -> String {
+    crate::util::replace_str_with(talk_txt(id), |name| talk_var(gd, name))
+}
+
+fn talk_var(gd: &GameData, name: &str) -> Option<String> {
+    let quest = || {
+        gd.quest
+            .iter()
+            .rev()
+            .find(|(state, _)| *state == QuestState::Active)
+            .map(|(_, quest)| quest)
+    };
+
+    let s = match name {
+        "player_name" => gd.chara.get(CharaId::Player).to_text().into_owned(),
+        "money" => gd.player.money().to_string(),
+        "quest_target" => match quest()? {
+            Quest::SlayMonsters { idx, .. } => idx.to_text().into_owned(),
+        },
+        "quest_goal" => match quest()? {
+            Quest::SlayMonsters { goal, .. } => goal.to_string(),
+        },
+        "quest_progress" => match quest()? {
+            Quest::SlayMonsters { killed, .. } => killed.to_string(),
+        },
+        "quest_reward_money" => quest()?.reward().money.to_string(),
+        _ => match gd.vars.global_var(name)? {
+            Value::Bool(v) => v.to_string(),
+            Value::Int(v) => v.to_string(),
+            Value::String(v) => v.clone(),
+            _ => return None,
+        },
+    };
+    Some(s)
+}
+
 #[allow(unused)]
 pub fn talk_txt_checked(id: &str) -> Option<&'static str> {
     TALK_TXT_MAP.get(id).map(|txt| txt.as_ref())
@@ -213,3 +258,21 @@ fn remove_last_newline(s: &mut String) {
         }
     }
 }
+
+#[test]
+fn talk_var_test() {
+    let mut gd = GameData::empty();
+    gd.player.set_money(120);
+    gd.vars.set_global_var("flag", Value::Bool(true));
+    gd.vars.set_global_var("count", Value::Int(3));
+    gd.vars
+        .set_global_var("name", Value::String("abc".to_owned()));
+
+    assert_eq!(talk_var(&gd, "money"), Some("120".to_owned()));
+    assert_eq!(talk_var(&gd, "flag"), Some("true".to_owned()));
+    assert_eq!(talk_var(&gd, "count"), Some("3".to_owned()));
+    assert_eq!(talk_var(&gd, "name"), Some("abc".to_owned()));
+    assert_eq!(talk_var(&gd, "unknown"), None);
+    // No active quest
+    assert_eq!(talk_var(&gd, "quest_goal"), None);
+}
@@ -32,6 +32,8 @@ impl ToTextId for SkillKind {
             SkillKind::MartialArts => "!skill_kind.martial_arts",
             SkillKind::Defence => "!skill_kind.defence",
             SkillKind::Evasion => "!skill_kind.evasion",
+            SkillKind::Searching => "!skill_kind.searching",
+            SkillKind::Lockpicking => "!skill_kind.lockpicking",
             SkillKind::Weapon(weapon_kind) => weapon_kind.to_textid(),
         }
     }
@@ -50,3 +52,15 @@ impl ToTextId for WeaponKind {
         }
     }
 }
+
+impl ToTextId for TrapKind {
+    fn to_textid(&self) -> &'static str {
+        match self {
+            TrapKind::Damage => "!trap_kind.damage",
+            TrapKind::Poison => "!trap_kind.poison",
+            TrapKind::SleepGas => "!trap_kind.sleep_gas",
+            TrapKind::Teleport => "!trap_kind.teleport",
+            TrapKind::Alarm => "!trap_kind.alarm",
+        }
+    }
+}
@@ -18,8 +18,9 @@ impl ToText for Site {
         }
 
         match self.content {
-            SiteContent::AutoGenDungeon { dungeon_kind } => text::to_txt(&dungeon_kind).into(),
+            SiteContent::AutoGenDungeon { dungeon_kind, .. } => text::to_txt(&dungeon_kind).into(),
             SiteContent::Town { ref town } => text::obj_txt(town.id()).into(),
+            SiteContent::Encounter { .. } => text::misc_txt("!site.encounter").into(),
             SiteContent::Other => {
                 warn!("Unnamed other kind site");
                 "".into()
@@ -28,6 +29,12 @@ impl ToText for Site {
     }
 }
 
+impl ToText for Region {
+    fn to_text(&self) -> Cow<str> {
+        text::obj_txt(&self.name).into()
+    }
+}
+
 impl ToText for Item {
     fn to_text(&self) -> Cow<str> {
         crate::text::obj_txt(gobj::idx_to_id(self.idx)).into()
@@ -12,6 +12,16 @@ pub fn read_file_as_string<P: AsRef<Path>>(path: P) -> Result<String, Error> {
 }
 
 pub fn replace_str<S0: AsRef<str>, S1: AsRef<str>>(s: &str, table: &[(S0, S1)]) -> String {
+    replace_str_with(s, |var_name| {
+        table
+            .iter()
+            .find(|t| t.0.as_ref() == var_name)
+            .map(|t| t.1.as_ref().to_owned())
+    })
+}
+
+/// Replace "$(var_name)" by the given function. Unknown variables are replaced by its name.
+pub fn replace_str_with<F: FnMut(&str) -> Option<String>>(s: &str, mut f: F) -> String {
     enum State {
         Normal,
         DollarFound,
@@ -41,13 +51,10 @@ pub fn replace_str<S0: AsRef<str>, S1: AsRef<str>>(s: &str, table: &[(S0, S1)])
             }
             State::Var => {
                 if c == ')' {
-                    {
-                        let a = table
-                            .iter()
-                            .find(|t| t.0.as_ref() == var_name)
-                            .map(|t| t.1.as_ref())
-                            .unwrap_or(&var_name);
-                        rst.push_str(a);
+                    if let Some(a) = f(&var_name) {
+                        rst.push_str(&a);
+                    } else {
+                        rst.push_str(&var_name);
                     }
                     var_name.clear();
                     state = State::Normal;
@@ -80,3 +87,19 @@ macro_rules! replace_str {
         $crate::util::replace_str(text_raw, table.as_slice())
     }}
 }
+
+#[test]
+fn replace_str_with_vars() {
+    let f = |name: &str| match name {
+        "a" => Some("xyz".to_owned()),
+        _ => None,
+    };
+    assert_eq!(replace_str_with("foo $(a) bar", f), "foo xyz bar");
+    assert_eq!(replace_str_with("$(a)$(a)", f), "xyzxyz");
+    // Unknown variables are replaced by its name
+    assert_eq!(replace_str_with("foo $(b)", f), "foo b");
+    // "$" without "(" is kept
+    assert_eq!(replace_str_with("10$ $a $", f), "10$ $a $");
+    // Unterminated variables are removed
+    assert_eq!(replace_str_with("foo $(a", f), "foo ");
+}
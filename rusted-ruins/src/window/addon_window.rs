@@ -0,0 +1,94 @@
+use super::commonuse::*;
+use super::widget::*;
+use crate::config::{load_addon_list, save_addon_list, UI_CFG};
+use crate::context::textrenderer::FontKind;
+use crate::context::TextCache;
+use crate::text;
+use common::addon::Addon;
+
+/// Addon list to enable or disable addons
+pub struct AddonListDialog {
+    rect: Rect,
+    list: TextListWidget,
+    label: LabelWidget,
+    addons: Vec<Addon>,
+}
+
+impl AddonListDialog {
+    pub fn new() -> AddonListDialog {
+        let rect: Rect = UI_CFG.addon_list_dialog.rect.into();
+        let h_label = UI_CFG.list_widget.h_row_with_text;
+        let addons = load_addon_list();
+        let msg = if addons.is_empty() {
+            text::ui_txt("addon.no_addons")
+        } else {
+            text::ui_txt("addon.restart_required")
+        };
+
+        AddonListDialog {
+            rect,
+            list: TextListWidget::text_choices(
+                (0, 0, rect.width(), rect.height() - h_label as u32),
+                addon_rows(&addons),
+            ),
+            label: LabelWidget::new(
+                (
+                    0,
+                    rect.height() as i32 - h_label,
+                    rect.width(),
+                    h_label as u32,
+                ),
+                msg,
+                FontKind::M,
+            ),
+            addons,
+        }
+    }
+}
+
+fn addon_rows(addons: &[Addon]) -> Vec<String> {
+    addons
+        .iter()
+        .map(|a| {
+            format!(
+                "[{}] {} {}",
+                if a.enabled { "x" } else { " " },
+                a.manifest.name,
+                a.manifest.version
+            )
+        })
+        .collect()
+}
+
+impl Window for AddonListDialog {
+    fn draw(&mut self, context: &mut Context, _game: &Game, _anim: Option<(&Animation, u32)>) {
+        draw_rect_border(context, self.rect);
+        self.list.draw(context);
+        self.label.draw(context);
+    }
+}
+
+impl DialogWindow for AddonListDialog {
+    fn process_command(&mut self, command: &Command, _pa: &mut DoPlayerAction) -> DialogResult {
+        if let Some(ListWidgetResponse::Select(i)) = self.list.process_command(&command) {
+            // Toggle the selected addon, and save immediately
+            let addon = &mut self.addons[i as usize];
+            addon.enabled = !addon.enabled;
+            save_addon_list(&self.addons);
+            let rows = addon_rows(&self.addons)
+                .into_iter()
+                .map(|s| TextCache::new(&[s], FontKind::M, UI_CFG.color.normal_font.into()))
+                .collect();
+            self.list.set_items(rows);
+            return DialogResult::Continue;
+        }
+        match *command {
+            Command::Cancel => DialogResult::Close,
+            _ => DialogResult::Continue,
+        }
+    }
+
+    fn mode(&self) -> InputMode {
+        InputMode::Dialog
+    }
+}
@@ -4,7 +4,7 @@ use super::msg_dialog;
 use super::talk_window;
 use super::DialogWindow;
 use crate::game::{DialogOpenRequest, DoPlayerAction, Game, TalkText};
-use common::gamedata::CharaId;
+use common::gamedata::{CharaAttributes, CharaId};
 
 pub fn create_dialog_from_request(
     req: DialogOpenRequest,
@@ -29,10 +29,35 @@ pub fn create_dialog_from_request(
             Box::new(ItemWindow::new(ItemWindowMode::ShopSell, pa.game()))
         }
         DialogOpenRequest::Quest => Box::new(super::quest_window::QuestWindow::new(game)),
+        DialogOpenRequest::LevelUp {
+            level,
+            old_attr,
+            new_attr,
+        } => Box::new(create_level_up_dialog(level, &old_attr, &new_attr)),
         DialogOpenRequest::GameOver => Box::new(super::exit_window::GameOverWindow::new()),
     })
 }
 
+fn create_level_up_dialog(
+    level: u32,
+    old_attr: &CharaAttributes,
+    new_attr: &CharaAttributes,
+) -> msg_dialog::MsgDialog {
+    let msg = replace_str!(
+        crate::text::ui_txt("dialog.level_up");
+        level=&level,
+        old_hp=&old_attr.max_hp, hp=&new_attr.max_hp,
+        old_str=&old_attr.str, str=&new_attr.str,
+        old_vit=&old_attr.vit, vit=&new_attr.vit,
+        old_dex=&old_attr.dex, dex=&new_attr.dex,
+        old_int=&old_attr.int, int=&new_attr.int,
+        old_wil=&old_attr.wil, wil=&new_attr.wil,
+        old_cha=&old_attr.cha, cha=&new_attr.cha,
+        old_spd=&old_attr.spd, spd=&new_attr.spd);
+    let choices = vec![crate::text::ui_txt("dialog.choice.close").to_owned()];
+    msg_dialog::MsgDialog::new(&msg, choices, |_, _| super::DialogResult::Close)
+}
+
 pub fn create_talk_dialog(
     talk_text: TalkText,
     cid: CharaId,
@@ -43,10 +43,16 @@ impl EquipWindow {
         self.list.set_n_item(equips.n_slots());
 
         self.list.update_rows_by_func(|i| {
-            let (esk, _, item) = equips.slot_iter().nth(i as usize).unwrap();
+            let (esk, esk_i, item) = equips.slot_iter().nth(i as usize).unwrap();
             let esk_icon = slotkind_to_icon_idx(esk);
             if let Some(item) = item {
                 let item_text = text::obj_txt(&gobj::get_obj(item.idx).id).to_owned();
+                let item_text = if esk == EquipSlotKind::Ammo {
+                    let n = equips.item_with_num(esk, esk_i as usize).unwrap().1;
+                    format!("{} x {}", item_text, n)
+                } else {
+                    item_text
+                };
                 let tc = TextCache::one(item_text, FontKind::M, UI_CFG.color.normal_font.into());
                 (esk_icon, IconIdx::Item(item.idx), tc)
             } else {
@@ -132,6 +138,7 @@ fn slotkind_to_icon_idx(esk: EquipSlotKind) -> IconIdx {
     let id = match esk {
         EquipSlotKind::MeleeWeapon => "!icon-melee-weapon",
         EquipSlotKind::RangedWeapon => "!icon-ranged-weapon",
+        EquipSlotKind::Ammo => "!icon-ammo",
         EquipSlotKind::BodyArmor => "!icon-bodyarmor",
         EquipSlotKind::Shield => "!icon-shield",
     };
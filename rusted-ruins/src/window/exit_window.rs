@@ -63,7 +63,7 @@ impl DialogWindow for ExitWindow {
                 let n = *v.downcast::<u32>().unwrap();
                 match n {
                     0 => {
-                        pa.game().save_file();
+                        pa.save_file();
                         return DialogResult::Close;
                     }
                     1 => return DialogResult::Quit,
@@ -162,9 +162,8 @@ impl TabsNavigator {
 
 impl Window for TabsNavigator {
     fn draw(&mut self, context: &mut Context, _game: &Game, _anim: Option<(&Animation, u32)>) {
-        lazy_static! {
-            static ref MAKE_DARK_IDX: UIImgIdx = common::gobj::id_to_idx("!make-dark");
-        };
+        // Not cached because indices may be changed by reloading objects
+        let make_dark_idx: UIImgIdx = common::gobj::id_to_idx("!make-dark");
         crate::draw::border::draw_rect_border(context, self.rect);
 
         use sdl2::pixels::Color;
@@ -219,7 +218,7 @@ impl Window for TabsNavigator {
 
                 // Make rendered text and icon dark if not selected
                 context.render_tex(
-                    *MAKE_DARK_IDX,
+                    make_dark_idx,
                     Rect::new(
                         w * i + WINDOW_BORDER_THICKNESS as i32,
                         0,
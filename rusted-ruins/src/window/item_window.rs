@@ -20,6 +20,7 @@ pub enum ItemWindowMode {
     Drop,
     Drink,
     Eat,
+    Throw,
     ShopSell,
     ShopBuy {
         cid: CharaId,
@@ -156,6 +157,13 @@ impl ItemWindow {
                     gd.get_filtered_item_list(ill, ItemFilter::new().flags(ItemFlags::EATABLE));
                 self.update_list(filtered_list);
             }
+            ItemWindowMode::Throw => {
+                let ill = ItemListLocation::Chara {
+                    cid: CharaId::Player,
+                };
+                let filtered_list = gd.get_filtered_item_list(ill, ItemFilter::all());
+                self.update_list(filtered_list);
+            }
             ItemWindowMode::ShopBuy { cid } => {
                 let ill = ItemListLocation::Shop { cid };
                 let filtered_list = gd.get_filtered_item_list(ill, ItemFilter::new());
@@ -234,6 +242,10 @@ impl ItemWindow {
                 self.update_by_mode(pa.gd());
                 DialogResult::CloseAll
             }
+            ItemWindowMode::Throw => {
+                pa.throw_item(il);
+                DialogResult::CloseAll
+            }
             ItemWindowMode::ShopBuy { .. } => {
                 pa.buy_item(il);
                 self.update_by_mode(pa.gd());
@@ -1,3 +1,4 @@
+mod addon_window;
 mod choose_window;
 mod dialogreq;
 mod equip_window;
@@ -25,8 +26,10 @@ mod winpos;
 use self::log_window::LogWindow;
 use self::main_window::MainWindow;
 use self::widget::WidgetTrait;
+use crate::context::texture::TextureHolder;
 use crate::eventhandler::EventHandler;
 use crate::game::{Command, DoPlayerAction, GameState, InfoGetter};
+use crate::hot_reload::{self, DataKind, HotReloader};
 use crate::SdlContext;
 use array2d::*;
 use common::gamedata::*;
@@ -120,6 +123,7 @@ pub struct WindowManager<'sdl, 't> {
     passed_frame: u32,
     window_stack: Vec<Box<dyn DialogWindow>>,
     targeting_mode: bool,
+    hot_reloader: Option<HotReloader>,
 }
 
 impl<'sdl, 't> WindowManager<'sdl, 't> {
@@ -141,6 +145,11 @@ impl<'sdl, 't> WindowManager<'sdl, 't> {
             passed_frame: 0,
             window_stack,
             targeting_mode: false,
+            hot_reloader: if crate::config::CONFIG.developer_mode {
+                Some(HotReloader::new())
+            } else {
+                None
+            },
         }
     }
 
@@ -153,6 +162,21 @@ impl<'sdl, 't> WindowManager<'sdl, 't> {
             self.game.advance_turn();
         }
 
+        if self.window_stack.is_empty() && self.game.get_state() == GameState::PlayerTurn {
+            // Reload changed data files in developer mode
+            if self.mode.is_on_game() {
+                if let Some(hot_reloader) = self.hot_reloader.as_mut() {
+                    let kinds = hot_reloader.check();
+                    if !kinds.is_empty() {
+                        self.reload_data(&kinds);
+                    }
+                }
+            }
+
+            // Start a script launched by events if no dialog is open
+            self.game.start_triggered_script();
+        }
+
         // If game requests dialog popup for player
         if let Some(dialog_open_request) = self.game.pop_dialog_open_request() {
             let dialog = dialogreq::create_dialog_from_request(dialog_open_request, &mut self.game);
@@ -359,6 +383,9 @@ impl<'sdl, 't> WindowManager<'sdl, 't> {
                         ItemWindowMode::Drop,
                     )));
             }
+            Command::CloseDoor => {
+                pa.close_door();
+            }
             Command::DrinkItem => {
                 self.window_stack
                     .push(Box::new(item_window::create_item_window_group(
@@ -373,6 +400,10 @@ impl<'sdl, 't> WindowManager<'sdl, 't> {
                         ItemWindowMode::Eat,
                     )));
             }
+            Command::ThrowItem => {
+                self.window_stack
+                    .push(Box::new(ItemWindow::new(ItemWindowMode::Throw, pa.game())));
+            }
             Command::TargetingMode => {
                 self.targeting_mode = true;
                 match self.mode {
@@ -445,6 +476,16 @@ impl<'sdl, 't> WindowManager<'sdl, 't> {
         }
     }
 
+    /// Reload data files, and recreate textures and windows which may hold old data
+    fn reload_data(&mut self, kinds: &[DataKind]) {
+        hot_reload::reload(&mut self.game, kinds);
+        if kinds.contains(&DataKind::Pak) {
+            self.sdl_values.texture_holder =
+                TextureHolder::new(common::gobj::get_objholder(), self.sdl_values.tc);
+        }
+        self.mode = WindowManageMode::OnGame(GameWindows::new());
+    }
+
     fn process_command_targeting_mode(&mut self, command: Command) {
         let main_window = match self.mode {
             WindowManageMode::OnGame(ref mut game_windows) => &mut game_windows.main_window,
@@ -1,3 +1,4 @@
+use super::addon_window::AddonListDialog;
 use super::commonuse::*;
 use super::widget::*;
 use super::SpecialDialogResult;
@@ -35,6 +36,7 @@ impl StartDialog {
         let choices = vec![
             text::ui_txt("dialog.choice.newgame").to_owned(),
             text::ui_txt("dialog.choice.loadgame").to_owned(),
+            text::ui_txt("dialog.choice.addons").to_owned(),
             text::ui_txt("dialog.choice.exit").to_owned(),
         ];
         let rect = UI_CFG.start_dialog.rect.into();
@@ -65,6 +67,10 @@ impl DialogWindow for StartDialog {
                     return DialogResult::Special(SpecialDialogResult::StartDialogLoadGame);
                 }
                 ListWidgetResponse::Select(2) => {
+                    // Addon list
+                    return DialogResult::OpenChildDialog(Box::new(AddonListDialog::new()));
+                }
+                ListWidgetResponse::Select(3) => {
                     // Exit
                     return DialogResult::Quit;
                 }
@@ -54,7 +54,17 @@ impl StatusWindow {
         let rect: Rect = UI_CFG.info_window.rect.into();
         let chara = gd.chara.get(CharaId::Player);
         let image = ImageWidget::chara(cfg.image_rect, chara.template);
-        let name_label = LabelWidget::new(cfg.name_label_rect, &chara.to_text(), FontKind::M);
+        let required_exp = crate::game::chara::level::required_exp(chara.level);
+        let name_label = LabelWidget::new(
+            cfg.name_label_rect,
+            &replace_str!(
+                crate::text::ui_txt("status.name_level");
+                name=chara,
+                level=&chara.level,
+                exp=&chara.exp,
+                required_exp=&required_exp),
+            FontKind::M,
+        );
         let hp_label = LabelWidget::new(
             cfg.hp_label_rect,
             &format!("HP  {} / {}", chara.hp, chara.attr.max_hp),
@@ -6,7 +6,6 @@ use super::winpos::*;
 use crate::config::UI_CFG;
 use crate::context::textrenderer::FontKind;
 use crate::game::{AdvanceScriptResult, TalkText};
-use crate::text;
 use common::basic::TILE_SIZE;
 use common::objholder::CharaTemplateIdx;
 use std::any::Any;
@@ -38,7 +37,7 @@ impl TalkWindow {
         );
         let mut talk_window = TalkWindow {
             rect,
-            talk_text,
+            talk_text: talk_text.clone(),
             label,
             image_window: ImageWindow::chara(rect_image_window, chara_template_idx),
             msg_text: MsgText::default(),
@@ -50,23 +49,19 @@ impl TalkWindow {
 
     fn update_page(&mut self, talk_text: Option<TalkText>) {
         if let Some(talk_text) = talk_text {
+            self.msg_text = MsgText::new(&talk_text.text);
             self.talk_text = talk_text;
-            self.msg_text = MsgText::new(&*talk_text.text_id);
             self.choose_win = None;
         }
 
         // Create answers
         if self.msg_text.is_final_page() {
-            if let Some(choices) = self.talk_text.choices {
+            if let Some(choices) = self.talk_text.choices.as_ref() {
                 let winpos = WindowPos::new(
                     WindowHPos::RightX(self.rect.right()),
                     WindowVPos::TopMargin(self.rect.bottom() + UI_CFG.gap_len_between_dialogs),
                 );
-                let choices: Vec<String> = choices
-                    .iter()
-                    .map(|a| text::talk_txt(&*a.0).to_owned())
-                    .collect();
-                self.choose_win = Some(ChooseWindow::new(winpos, choices, None));
+                self.choose_win = Some(ChooseWindow::new(winpos, choices.clone(), None));
             }
         }
 
@@ -163,16 +158,9 @@ struct MsgText {
 }
 
 impl MsgText {
-    fn new(text_id: &str) -> MsgText {
-        let mut lines: Vec<Cow<'static, str>> = Vec::new();
-
-        if let Some(s) = text::talk_txt_checked(text_id) {
-            for line in s.lines() {
-                lines.push(line.into());
-            }
-        } else {
-            lines.push(text_id.to_owned().into());
-        }
+    fn new(text: &str) -> MsgText {
+        let lines: Vec<Cow<'static, str>> =
+            text.lines().map(|line| line.to_owned().into()).collect();
 
         MsgText {
             lines,